@@ -0,0 +1,179 @@
+use rayon::prelude::*;
+use structopt::StructOpt;
+
+use evolve_keyboard_layout::common;
+use layout_evaluation::{
+    evaluation::Evaluator, metric_analysis::MetricAnalysis, results::EvaluationResult,
+};
+use layout_optimization::common::PermutationLayoutGenerator;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Metric sensitivity and correlation analysis")]
+struct Options {
+    /// Evaluation parameters
+    #[structopt(flatten)]
+    evaluation_parameters: common::Options,
+
+    /// Number of random layouts to evaluate
+    #[structopt(short, long, default_value = "1000")]
+    number_of_samples: usize,
+
+    /// Number of layouts to optimize (greedy key swaps starting from random layouts)
+    #[structopt(long, default_value = "4")]
+    optimized_samples: usize,
+
+    /// Number of attempted key swaps for each optimized layout
+    #[structopt(long, default_value = "5000")]
+    optimization_steps: usize,
+
+    /// Additionally analyze the layouts in this file (one per line, e.g. from `--append-solutions-to`)
+    #[structopt(long)]
+    layouts_file: Option<String>,
+
+    /// Do not permutate those keys (wrt. --fix-from)
+    #[structopt(short, long)]
+    fix: Option<String>,
+
+    /// Sample layouts as permutations of this layout
+    #[structopt(long, default_value = "xvlcwkhgfqßuiaeosnrtdyüöäpzbm,.j")]
+    fix_from: String,
+
+    /// Metrics whose standard deviation is below this fraction of the total cost's standard deviation are considered inert
+    #[structopt(long, default_value = "0.01")]
+    inert_threshold: f64,
+
+    /// Report pairs of metrics whose absolute correlation is at least this value
+    #[structopt(long, default_value = "0.8")]
+    correlation_threshold: f64,
+
+    /// If to only output the results as JSON to stdout
+    #[structopt(long)]
+    return_json: bool,
+}
+
+/// Optimize a random layout by greedily accepting random swaps of key pairs that reduce the cost.
+fn optimize_greedily(
+    pm: &PermutationLayoutGenerator,
+    evaluator: &Evaluator,
+    steps: usize,
+) -> EvaluationResult {
+    let mut permutation = pm.generate_random();
+    let mut result = evaluator.evaluate_layout(&pm.generate_layout(&permutation));
+    for _ in 0..steps {
+        let candidate = pm.switch_n_keys(&permutation, 1);
+        let candidate_result = evaluator.evaluate_layout(&pm.generate_layout(&candidate));
+        if candidate_result.total_cost() < result.total_cost() {
+            permutation = candidate;
+            result = candidate_result;
+        }
+    }
+    log::info!("Optimized layout: {}", pm.generate_string(&permutation));
+
+    result
+}
+
+fn print_analysis(title: &str, analysis: &MetricAnalysis, correlation_threshold: f64) {
+    println!("{}:\n{}", title, analysis);
+
+    let pairs = analysis.correlated_pairs(correlation_threshold);
+    if !pairs.is_empty() {
+        println!("  Correlated metrics (|r| >= {}):", correlation_threshold);
+        for (i, j, r) in pairs {
+            println!(
+                "    {:>6.3} {} / {}",
+                r, analysis.metrics[i].name, analysis.metrics[j].name
+            );
+        }
+    }
+
+    let inert: Vec<&str> = analysis
+        .metrics
+        .iter()
+        .filter(|m| m.inert)
+        .map(|m| m.name.as_str())
+        .collect();
+    if !inert.is_empty() {
+        println!("  Inert metrics: {}", inert.join(", "));
+    }
+    println!();
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let options = Options::from_args();
+    if !options.return_json {
+        env_logger::init();
+    }
+
+    let (layout_generator, evaluator) = common::init(&options.evaluation_parameters);
+    let fix = options.fix.clone().unwrap_or_default();
+    let pm = PermutationLayoutGenerator::new(&options.fix_from, &fix, &layout_generator);
+
+    let mut groups: Vec<(&str, Vec<EvaluationResult>)> = Vec::new();
+
+    log::info!("Evaluating {} random layouts", options.number_of_samples);
+    let random_results: Vec<EvaluationResult> = (0..options.number_of_samples)
+        .into_par_iter()
+        .map(|_| evaluator.evaluate_layout(&pm.generate_layout(&pm.generate_random())))
+        .collect();
+    groups.push(("Random layouts", random_results));
+
+    if options.optimized_samples > 0 {
+        log::info!("Optimizing {} layouts", options.optimized_samples);
+        let optimized_results: Vec<EvaluationResult> = (0..options.optimized_samples)
+            .into_par_iter()
+            .map(|_| optimize_greedily(&pm, &evaluator, options.optimization_steps))
+            .collect();
+        groups.push(("Optimized layouts", optimized_results));
+    }
+
+    if let Some(filename) = &options.layouts_file {
+        let content = std::fs::read_to_string(filename)
+            .unwrap_or_else(|e| panic!("Could not read layouts file {}: {}", filename, e));
+        let layouts: Vec<&str> = content
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect();
+
+        log::info!("Evaluating {} layouts from {}", layouts.len(), filename);
+        let file_results: Vec<EvaluationResult> = layouts
+            .par_iter()
+            .map(|layout_str| {
+                let layout = layout_generator
+                    .generate(layout_str)
+                    .unwrap_or_else(|e| panic!("Invalid layout '{}': {:?}", layout_str, e));
+                evaluator.evaluate_layout(&layout)
+            })
+            .collect();
+        groups.push(("Layouts from file", file_results));
+    }
+
+    let analyses: Vec<(&str, MetricAnalysis)> = groups
+        .iter()
+        .filter(|(_, results)| !results.is_empty())
+        .map(|(title, results)| {
+            (
+                *title,
+                MetricAnalysis::from_results(results, options.inert_threshold),
+            )
+        })
+        .collect();
+
+    if options.return_json {
+        let json: serde_json::Map<String, serde_json::Value> = analyses
+            .iter()
+            .map(|(title, analysis)| {
+                (
+                    title.to_string(),
+                    serde_json::to_value(analysis).expect("Could not serialize analysis"),
+                )
+            })
+            .collect();
+        println!("{}", serde_json::Value::Object(json));
+    } else {
+        for (title, analysis) in analyses.iter() {
+            print_analysis(title, analysis, options.correlation_threshold);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use layout_evaluation::{
+    calibration::{
+        calibrate, metric_costs, pairwise_agreement, total_cost, CalibrationParameters,
+        CalibrationTarget,
+    },
+    evaluation::MetricParameters,
+    metric_registry::MetricRegistry,
+};
+
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use evolve_keyboard_layout::common;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Metric weight calibration")]
+struct Options {
+    /// Filename of the reference layouts (with target costs or ordered from best to worst)
+    #[structopt(default_value = "config/calibration_reference.yml")]
+    reference: String,
+
+    /// Evaluation parameters
+    #[structopt(flatten)]
+    evaluation_parameters: common::Options,
+
+    /// Strength of the penalty for deviating from the current weights
+    #[structopt(long, default_value = "0.001")]
+    regularization: f64,
+
+    /// Minimal cost difference between better and worse layouts (as fraction of the mean cost) for a ranking
+    #[structopt(long, default_value = "0.01")]
+    margin: f64,
+
+    /// Number of iterations of the fit
+    #[structopt(long, default_value = "20000")]
+    iterations: usize,
+
+    /// Write the calibrated metrics configuration to this file instead of stdout
+    #[structopt(short, long)]
+    output: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReferenceLayout {
+    layout: String,
+    #[serde(default)]
+    cost: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct References {
+    layouts: Vec<ReferenceLayout>,
+}
+
+#[derive(Serialize, Debug)]
+struct MetricsBlock {
+    metrics: MetricParameters,
+}
+
+/// Round to four significant digits (calibrated weights are not more precise than that).
+fn round_weight(weight: f64) -> f64 {
+    if weight == 0.0 {
+        return 0.0;
+    }
+    let factor = 10f64.powi(3 - weight.abs().log10().floor() as i32);
+
+    (weight * factor).round() / factor
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    env_logger::init();
+    let options = Options::from_args();
+
+    let f = std::fs::File::open(&options.reference).unwrap_or_else(|e| {
+        panic!(
+            "Could not open reference layouts file {}: {}",
+            options.reference, e
+        )
+    });
+    let references: References = serde_yaml::from_reader(f).unwrap_or_else(|e| {
+        panic!(
+            "Could not read reference layouts file {}: {}",
+            options.reference, e
+        )
+    });
+    let target = match references
+        .layouts
+        .iter()
+        .map(|r| r.cost)
+        .collect::<Option<Vec<f64>>>()
+    {
+        Some(costs) => CalibrationTarget::Costs(costs),
+        None if references.layouts.iter().all(|r| r.cost.is_none()) => CalibrationTarget::Ranking,
+        None => panic!("Either all or none of the reference layouts need to specify a cost"),
+    };
+
+    let eval_params =
+        common::EvaluationParameters::from_yaml(&options.evaluation_parameters.eval_parameters)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Could not read evaluation yaml file {}: {}",
+                    options.evaluation_parameters.eval_parameters, e
+                )
+            });
+    let entries = eval_params
+        .metrics
+        .enabled_entries(&MetricRegistry::default())
+        .expect("Could not determine the configured metrics");
+
+    let (layout_generator, evaluator) = common::init(&options.evaluation_parameters);
+
+    let costs: Vec<Vec<f64>> = references
+        .layouts
+        .iter()
+        .map(|reference| {
+            let layout = match layout_generator.generate(&reference.layout) {
+                Ok(layout) => layout,
+                Err(e) => {
+                    log::error!("Error in generating layout: {:?}", e);
+                    panic!("{:?}", e);
+                }
+            };
+            log::info!("Evaluating reference layout '{}'", reference.layout);
+            let costs = metric_costs(&evaluator.evaluate_layout(&layout));
+            assert_eq!(
+                costs.len(),
+                entries.len(),
+                "Evaluation results do not match the configured metrics"
+            );
+
+            costs
+        })
+        .collect();
+
+    let params = CalibrationParameters {
+        regularization: options.regularization,
+        margin: options.margin,
+        iterations: options.iterations,
+    };
+    let calibration = calibrate(&costs, &target, &params);
+
+    let ones = vec![1.0; entries.len()];
+    let current: Vec<f64> = costs.iter().map(|c| total_cost(c, &ones)).collect();
+    let calibrated: Vec<f64> = costs
+        .iter()
+        .map(|c| total_cost(c, &calibration.scales))
+        .collect();
+
+    println!("Reference layouts (current cost -> calibrated cost):");
+    for (i, reference) in references.layouts.iter().enumerate() {
+        let target_str = match reference.cost {
+            Some(cost) => format!("target {:>9.4}", cost),
+            None => format!("rank {:>3}", i + 1),
+        };
+        println!(
+            "  {} | {:>9.4} -> {:>9.4} | {}",
+            target_str, current[i], calibrated[i], reference.layout
+        );
+    }
+    println!(
+        "Pairwise order agreement: {:.1}% -> {:.1}%",
+        100.0 * pairwise_agreement(&current, &target),
+        100.0 * pairwise_agreement(&calibrated, &target)
+    );
+    println!(
+        "Loss: {:.6} -> {:.6}\n",
+        calibration.initial_loss, calibration.final_loss
+    );
+
+    let mut metrics = eval_params.metrics.clone();
+    println!("Metric weights (current -> calibrated):");
+    for ((_, name), scale) in entries.iter().zip(calibration.scales.iter()) {
+        if let Some((_, config)) = metrics.0.iter_mut().find(|(n, _)| n == name) {
+            let weight = round_weight(config.weight * scale);
+            println!("  {:<40} {:>10.4} -> {:>10.4}", name, config.weight, weight);
+            config.weight = weight;
+        }
+    }
+
+    let yaml = serde_yaml::to_string(&MetricsBlock { metrics })
+        .expect("Could not serialize calibrated metrics");
+    match options.output {
+        Some(filename) => {
+            std::fs::write(&filename, yaml).unwrap_or_else(|e| {
+                panic!("Could not write calibrated metrics to {}: {}", filename, e)
+            });
+            println!("\nWrote calibrated metrics configuration to {}", filename);
+        }
+        None => println!("\nCalibrated metrics configuration:\n{}", yaml),
+    }
+}
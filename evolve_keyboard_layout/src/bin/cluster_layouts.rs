@@ -0,0 +1,149 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use structopt::StructOpt;
+
+use evolve_keyboard_layout::common;
+use layout_evaluation::{
+    clustering::cluster,
+    layout_distance::{DistanceMeasure, LayoutDistance, LayoutSignature},
+};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Clustering of layouts")]
+struct Options {
+    /// File with layouts (one per line, e.g. from `--append-solutions-to`)
+    layouts_file: String,
+
+    /// Evaluation parameters
+    #[structopt(flatten)]
+    evaluation_parameters: common::Options,
+
+    /// Distance measure (key_displacement, finger_assignment, weighted_key_displacement, or weighted_finger_assignment)
+    #[structopt(short, long, default_value = "weighted_key_displacement")]
+    measure: DistanceMeasure,
+
+    /// Maximal average distance between the layouts of a cluster
+    #[structopt(long, default_value = "0.1")]
+    threshold: f64,
+
+    /// Show all layouts of each cluster
+    #[structopt(long)]
+    show_members: bool,
+
+    /// If to only output the results as JSON to stdout
+    #[structopt(long)]
+    return_json: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct LayoutFamily {
+    /// Layout with the lowest cost
+    best: String,
+    /// Layout with the smallest total distance to the other members
+    representative: String,
+    /// Mean distance of the members to the representative
+    mean_distance: f64,
+    min_cost: f64,
+    mean_cost: f64,
+    max_cost: f64,
+    /// Members with their costs (sorted by cost)
+    members: Vec<(String, f64)>,
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let options = Options::from_args();
+    if !options.return_json {
+        env_logger::init();
+    }
+
+    let content = std::fs::read_to_string(&options.layouts_file).unwrap_or_else(|e| {
+        panic!(
+            "Could not read layouts file {}: {}",
+            options.layouts_file, e
+        )
+    });
+    let layout_strs: Vec<&str> = content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    if layout_strs.is_empty() {
+        panic!("No layouts found in {}", options.layouts_file);
+    }
+
+    let (layout_generator, evaluator) = common::init(&options.evaluation_parameters);
+    let layouts: Vec<_> = layout_strs
+        .iter()
+        .map(|layout_str| {
+            layout_generator
+                .generate(layout_str)
+                .unwrap_or_else(|e| panic!("Invalid layout '{}': {:?}", layout_str, e))
+        })
+        .collect();
+
+    log::info!("Evaluating {} layouts", layouts.len());
+    let costs: Vec<f64> = layouts
+        .par_iter()
+        .map(|layout| evaluator.evaluate_layout(layout).total_cost())
+        .collect();
+
+    let distance = LayoutDistance::new(options.measure, &evaluator.symbol_frequencies(&layouts[0]));
+    let signatures: Vec<LayoutSignature> = layouts.iter().map(LayoutSignature::new).collect();
+    let distances = distance.distance_matrix(&signatures);
+    let clusters = cluster(&distances, options.threshold);
+
+    let families: Vec<LayoutFamily> = clusters
+        .iter()
+        .map(|c| {
+            let mut members: Vec<(String, f64)> = c
+                .members
+                .iter()
+                .map(|i| (layout_strs[*i].to_string(), costs[*i]))
+                .collect();
+            members.sort_by(|(_, c1), (_, c2)| c1.total_cmp(c2));
+
+            LayoutFamily {
+                best: members[0].0.clone(),
+                representative: layout_strs[c.medoid].to_string(),
+                mean_distance: c.mean_distance,
+                min_cost: members[0].1,
+                mean_cost: members.iter().map(|(_, c)| c).sum::<f64>() / members.len() as f64,
+                max_cost: members[members.len() - 1].1,
+                members,
+            }
+        })
+        .collect();
+
+    if options.return_json {
+        println!("{}", serde_json::to_string(&families).unwrap());
+        return;
+    }
+
+    println!(
+        "{} layouts in {} clusters ({:?}, threshold {}):\n",
+        layouts.len(),
+        families.len(),
+        options.measure,
+        options.threshold
+    );
+    for (i, family) in families.iter().enumerate() {
+        println!(
+            "Cluster {}: {} layouts, cost {:.4} - {:.4} (mean {:.4}), mean distance {:.3}",
+            i + 1,
+            family.members.len(),
+            family.min_cost,
+            family.max_cost,
+            family.mean_cost,
+            family.mean_distance
+        );
+        println!("  best:           {}", family.best);
+        println!("  representative: {}", family.representative);
+        if options.show_members {
+            for (layout, cost) in family.members.iter() {
+                println!("    {:.4} {}", cost, layout);
+            }
+        }
+        println!();
+    }
+}
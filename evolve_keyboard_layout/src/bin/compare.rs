@@ -0,0 +1,53 @@
+use layout_evaluation::comparison::LayoutComparison;
+use structopt::StructOpt;
+
+use evolve_keyboard_layout::common;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Keyboard layout comparison")]
+struct Options {
+    /// Layouts to compare (keys from left to right, top to bottom), the first one is the reference
+    layout_str: Vec<String>,
+
+    /// Evaluation parameters
+    #[structopt(flatten)]
+    evaluation_parameters: common::Options,
+
+    /// Number of bigrams with the largest cost differences to show
+    #[structopt(long, default_value = "10")]
+    bigrams: usize,
+
+    /// If to only output the results as JSON to stdout
+    #[structopt(long)]
+    return_json: bool,
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let options = Options::from_args();
+    if !options.return_json {
+        env_logger::init();
+    }
+
+    if options.layout_str.len() < 2 {
+        panic!("At least two layouts are required for a comparison");
+    }
+
+    let (layout_generator, evaluator) = common::init(&options.evaluation_parameters);
+    let layouts: Vec<_> = options
+        .layout_str
+        .iter()
+        .map(|layout_str| {
+            layout_generator
+                .generate(layout_str)
+                .unwrap_or_else(|e| panic!("Invalid layout '{}': {:?}", layout_str, e))
+        })
+        .collect();
+
+    let comparison = LayoutComparison::new(&evaluator, &layouts, options.bigrams);
+    if options.return_json {
+        println!("{}", serde_json::to_string(&comparison).unwrap());
+    } else {
+        println!("{}", comparison);
+    }
+}
@@ -1,4 +1,7 @@
-use layout_evaluation::results::EvaluationResult;
+use layout_evaluation::{evaluation::Evaluator, results::EvaluationResult};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::io::Write;
 use structopt::StructOpt;
 
 use evolve_keyboard_layout::common;
@@ -16,6 +19,146 @@ struct Options {
     /// If to only output the results as JSON to stdout
     #[structopt(long)]
     return_json: bool,
+
+    /// If to print the key costs and unbalancing positions in effect (and generated ones if a key cost generator is configured)
+    #[structopt(long)]
+    key_costs: bool,
+
+    /// If to print the load of each key (in percent), finger, and hand
+    #[structopt(long)]
+    heatmap: bool,
+
+    /// Write an SVG heatmap of the key loads to this file (an index is appended for multiple layouts)
+    #[structopt(long)]
+    heatmap_svg: Option<String>,
+
+    /// Evaluate the layouts in this file (one per line) in parallel and write a table of the costs
+    #[structopt(long)]
+    from_file: Option<String>,
+
+    /// File to write the batch evaluation (`--from-file`) to (default: stdout)
+    #[structopt(short, long)]
+    output: Option<String>,
+
+    /// Format of the batch evaluation (csv or jsonl, default: from the output file's extension or csv)
+    #[structopt(long)]
+    format: Option<String>,
+}
+
+/// Costs of a layout from a batch evaluation (or the reason why it could not be evaluated).
+#[derive(Serialize, Debug)]
+struct BatchResult {
+    layout: String,
+    total_cost: Option<f64>,
+    /// Weighted costs of the individual metrics
+    metric_costs: Vec<(String, f64)>,
+    error: Option<String>,
+}
+
+/// Quote a CSV field if required.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Evaluate many layouts in parallel. Invalid layouts are recorded with their error. The results
+/// are sorted by total cost (invalid layouts last).
+fn evaluate_batch(
+    layout_strs: &[String],
+    layout_generator: &keyboard_layout::layout_generator::NeoLayoutGenerator,
+    evaluator: &Evaluator,
+) -> Vec<BatchResult> {
+    let mut results: Vec<BatchResult> = layout_strs
+        .par_iter()
+        .map(|layout_str| match layout_generator.generate(layout_str) {
+            Ok(layout) => {
+                let result = evaluator.evaluate_layout(&layout);
+                BatchResult {
+                    layout: layout_str.to_string(),
+                    total_cost: Some(result.total_cost()),
+                    metric_costs: result
+                        .iter()
+                        .flat_map(|mr| mr.metric_costs.iter())
+                        .map(|mc| (mc.core.name.clone(), mc.weighted_cost))
+                        .collect(),
+                    error: None,
+                }
+            }
+            Err(e) => {
+                log::warn!("Skipping layout '{}': {}", layout_str, e);
+                BatchResult {
+                    layout: layout_str.to_string(),
+                    total_cost: None,
+                    metric_costs: Vec::new(),
+                    error: Some(e.to_string()),
+                }
+            }
+        })
+        .collect();
+
+    results.sort_by(|r1, r2| match (r1.total_cost, r2.total_cost) {
+        (Some(c1), Some(c2)) => c1.total_cmp(&c2),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+
+    results
+}
+
+/// Write the batch results as CSV (one column per metric) or JSONL.
+fn write_batch(results: &[BatchResult], format: &str, out: &mut dyn Write) -> std::io::Result<()> {
+    match format {
+        "jsonl" => {
+            for result in results {
+                writeln!(out, "{}", serde_json::to_string(result).unwrap())?;
+            }
+        }
+        _ => {
+            let metric_names: Vec<&str> = results
+                .iter()
+                .find(|r| r.error.is_none())
+                .map(|r| r.metric_costs.iter().map(|(n, _)| n.as_str()).collect())
+                .unwrap_or_default();
+            let header: Vec<String> = ["layout", "total_cost"]
+                .iter()
+                .chain(metric_names.iter())
+                .chain(["error"].iter())
+                .map(|h| csv_field(h))
+                .collect();
+            writeln!(out, "{}", header.join(","))?;
+
+            for result in results {
+                let mut fields = vec![
+                    csv_field(&result.layout),
+                    result.total_cost.map(|c| c.to_string()).unwrap_or_default(),
+                ];
+                if result.error.is_none() {
+                    fields.extend(result.metric_costs.iter().map(|(_, c)| c.to_string()));
+                } else {
+                    fields.extend(metric_names.iter().map(|_| String::new()));
+                }
+                fields.push(csv_field(result.error.as_deref().unwrap_or("")));
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Filename for the SVG heatmap of the layout with the given index.
+fn svg_filename(filename: &str, index: usize, n_layouts: usize) -> String {
+    if n_layouts <= 1 {
+        return filename.to_string();
+    }
+    match filename.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}_{}.{}", stem, index + 1, extension),
+        None => format!("{}_{}", filename, index + 1),
+    }
 }
 
 fn main() {
@@ -27,8 +170,51 @@ fn main() {
 
     let (layout_generator, evaluator) = common::init(&options.evaluation_parameters);
 
+    if let Some(filename) = &options.from_file {
+        let content = std::fs::read_to_string(filename)
+            .unwrap_or_else(|e| panic!("Could not read layouts file {}: {}", filename, e));
+        let layout_strs: Vec<String> = options
+            .layout_str
+            .iter()
+            .map(|l| l.trim())
+            .chain(content.lines().map(|l| l.trim()))
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_string())
+            .collect();
+
+        log::info!("Evaluating {} layouts", layout_strs.len());
+        let results = evaluate_batch(&layout_strs, &layout_generator, &evaluator);
+        let n_invalid = results.iter().filter(|r| r.error.is_some()).count();
+        if n_invalid > 0 {
+            log::warn!("{} of {} layouts are invalid", n_invalid, results.len());
+        }
+
+        let format = options.format.clone().unwrap_or_else(|| {
+            match &options.output {
+                Some(output) if output.ends_with(".jsonl") || output.ends_with(".json") => "jsonl",
+                _ => "csv",
+            }
+            .to_string()
+        });
+        if format != "csv" && format != "jsonl" {
+            panic!("Unknown output format '{}' (expected csv or jsonl)", format);
+        }
+
+        let mut out: Box<dyn Write> = match &options.output {
+            Some(output) => Box::new(
+                std::fs::File::create(output)
+                    .unwrap_or_else(|e| panic!("Could not create {}: {}", output, e)),
+            ),
+            None => Box::new(std::io::stdout()),
+        };
+        write_batch(&results, &format, &mut out)
+            .unwrap_or_else(|e| panic!("Could not write results: {}", e));
+
+        return;
+    }
+
     let mut results: Vec<EvaluationResult> = Vec::new();
-    for layout_str in options.layout_str.iter() {
+    for (i, layout_str) in options.layout_str.iter().enumerate() {
         let layout = match layout_generator.generate(layout_str) {
             Ok(layout) => layout,
             Err(e) => {
@@ -38,9 +224,42 @@ fn main() {
         };
         let evaluation_result = evaluator.evaluate_layout(&layout);
         results.push(evaluation_result.clone());
+        if let Some(filename) = &options.heatmap_svg {
+            let filename = svg_filename(filename, i, options.layout_str.len());
+            std::fs::write(&filename, evaluator.load_report(&layout).svg(&layout))
+                .unwrap_or_else(|e| panic!("Could not write heatmap to {}: {}", filename, e));
+        }
         if !options.return_json {
             println!("Layout (layer 1):\n{}", layout.plot_layer(0));
             println!("Layout compact (layer 1):\n{}", layout.plot_compact());
+            if options.key_costs {
+                let keyboard = &layout.keyboard;
+                let (costs, unbalancing): (Vec<f64>, Vec<f64>) =
+                    keyboard.keys.iter().map(|k| (k.cost, k.unbalancing)).unzip();
+                println!("Key costs (in effect):\n{}", keyboard.plot_values(&costs));
+                println!(
+                    "Unbalancing positions (in effect):\n{}",
+                    keyboard.plot_values(&unbalancing)
+                );
+                match keyboard.generated_key_costs() {
+                    Ok(Some(generated)) => {
+                        let (costs, unbalancing): (Vec<f64>, Vec<f64>) =
+                            generated.into_iter().unzip();
+                        println!("Key costs (generated):\n{}", keyboard.plot_values(&costs));
+                        println!(
+                            "Unbalancing positions (generated):\n{}",
+                            keyboard.plot_values(&unbalancing)
+                        );
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Could not generate key costs: {:#}", e),
+                }
+            }
+            if options.heatmap {
+                let load_report = evaluator.load_report(&layout);
+                println!("Key loads (%):\n{}", load_report.plot(&layout));
+                println!("{}", load_report);
+            }
             println!("{}", evaluation_result);
         }
     }
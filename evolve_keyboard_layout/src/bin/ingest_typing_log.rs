@@ -0,0 +1,94 @@
+use layout_evaluation::typing_log::{read_keystrokes, BigramLatencies};
+use structopt::StructOpt;
+
+use evolve_keyboard_layout::common;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Keystroke log ingestion")]
+struct Options {
+    /// Keystroke log (CSV with `key,time` or JSONL with `{"key": ..., "time": ...}`, time in milliseconds)
+    keystroke_log: String,
+
+    /// Layout that was used for typing the log (keys from left to right, top to bottom)
+    #[structopt(long)]
+    layout: String,
+
+    /// Filename of layout configuration file to use
+    #[structopt(short, long, default_value = "config/standard_keyboard.yml")]
+    layout_config: String,
+
+    /// Keystrokes with a longer pause (in milliseconds) in between are not considered a bigram
+    #[structopt(long, default_value = "1000")]
+    max_pause: f64,
+
+    /// File to write the measured latencies to
+    #[structopt(short, long, default_value = "bigram_latencies.yml")]
+    output: String,
+
+    /// Only show key pairs with at least this many measurements in the summary
+    #[structopt(long, default_value = "5")]
+    min_count: usize,
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    env_logger::init();
+    let options = Options::from_args();
+
+    let layout_generator = common::init_layout_generator(&options.layout_config);
+    let layout = layout_generator
+        .generate(&options.layout)
+        .unwrap_or_else(|e| panic!("Invalid layout '{}': {:?}", options.layout, e));
+
+    let keystrokes = read_keystrokes(&options.keystroke_log)
+        .unwrap_or_else(|e| panic!("Could not read keystroke log: {:?}", e));
+    let latencies = BigramLatencies::from_keystrokes(&keystrokes, &layout, options.max_pause);
+
+    let n_measurements: usize = latencies.latencies.iter().map(|l| l.count).sum();
+    println!(
+        "Read {} keystrokes, measured {} bigrams for {} pairs of keys",
+        keystrokes.len(),
+        n_measurements,
+        latencies.latencies.len()
+    );
+
+    let mut frequent: Vec<_> = latencies
+        .latencies
+        .iter()
+        .filter(|l| l.count >= options.min_count)
+        .collect();
+    frequent.sort_by(|l1, l2| l1.latency.total_cmp(&l2.latency));
+    // symbol on the base layer for a matrix position
+    let key_at = |pos| {
+        layout
+            .layerkeys
+            .iter()
+            .find(|k| k.layer == 0 && k.key.matrix_position == pos)
+            .map(|k| k.symbol)
+            .unwrap_or('?')
+    };
+    if !frequent.is_empty() {
+        println!(
+            "Fastest and slowest pairs of keys (with at least {} measurements):",
+            options.min_count
+        );
+        let n = frequent.len().min(5);
+        for l in frequent[..n]
+            .iter()
+            .chain(frequent[(frequent.len() - n).max(n)..].iter())
+        {
+            println!(
+                "  {}{} {:>7.1} ms ({} measurements)",
+                key_at(l.from).escape_debug(),
+                key_at(l.to).escape_debug(),
+                l.latency,
+                l.count
+            );
+        }
+    }
+
+    latencies
+        .save_yaml(&options.output)
+        .unwrap_or_else(|e| panic!("Could not write latencies: {:?}", e));
+    println!("Wrote latencies to {}", options.output);
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use layouts_webservice_client::{api::LayoutRecord, Client};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Layout database import and export")]
+struct Options {
+    /// Url of the webservice
+    #[structopt(
+        long,
+        default_value = "https://keyboard-layout-optimizer.herokuapp.com/api"
+    )]
+    url: String,
+
+    /// Admin token of the webservice (the admin secret works as well)
+    #[structopt(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Export all layouts with their results and provenance as JSONL
+    Export {
+        /// Only export the layouts of this profile (default: all profiles)
+        #[structopt(long)]
+        profile: Option<String>,
+
+        /// Write the layouts to this file (default: stdout)
+        #[structopt(long)]
+        output: Option<String>,
+    },
+    /// Import layouts, skipping those that are stored already
+    Import {
+        /// File with one layout per line, either as exported (JSON) or as plain layout string
+        file: String,
+
+        /// Evaluate all layouts with the webservice's current configuration instead of keeping
+        /// their exported results
+        #[structopt(long)]
+        reevaluate: bool,
+
+        /// Profile of plain layout strings (default: the webservice's default profile)
+        #[structopt(long)]
+        profile: Option<String>,
+
+        /// Publish plain layout strings under this name
+        #[structopt(long)]
+        published_by: Option<String>,
+
+        /// Highlight plain layout strings
+        #[structopt(long)]
+        highlight: bool,
+    },
+}
+
+/// Convert the lines of an import file into JSONL. Lines that are not JSON objects are taken as
+/// plain layout strings.
+fn to_jsonl(
+    content: &str,
+    profile: Option<&str>,
+    published_by: Option<&str>,
+    highlight: bool,
+) -> Result<String> {
+    let mut jsonl = String::new();
+    for line in content.lines().map(|line| line.trim()) {
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('{') {
+            jsonl.push_str(line);
+        } else {
+            let record = LayoutRecord {
+                profile: profile.map(|profile| profile.to_string()),
+                layout: line.to_string(),
+                published_by: published_by.map(|name| name.to_string()),
+                highlight,
+                ..Default::default()
+            };
+            jsonl.push_str(&serde_json::to_string(&record)?);
+        }
+        jsonl.push('\n');
+    }
+
+    Ok(jsonl)
+}
+
+fn run(options: &Options) -> Result<()> {
+    let client = Client::new(&options.url)?.with_token(options.token.as_deref());
+
+    match &options.command {
+        Command::Export { profile, output } => {
+            let jsonl = client.export(profile.as_deref())?;
+            match output {
+                Some(filename) => {
+                    std::fs::write(filename, &jsonl)
+                        .with_context(|| format!("Could not write layouts to {}", filename))?;
+                    eprintln!("Exported {} layouts to {}", jsonl.lines().count(), filename);
+                }
+                None => print!("{}", jsonl),
+            }
+        }
+        Command::Import {
+            file,
+            reevaluate,
+            profile,
+            published_by,
+            highlight,
+        } => {
+            let content = std::fs::read_to_string(file)
+                .with_context(|| format!("Could not read layouts from {}", file))?;
+            let jsonl = to_jsonl(
+                &content,
+                profile.as_deref(),
+                published_by.as_deref(),
+                *highlight,
+            )?;
+            let summary = client.import(jsonl, *reevaluate)?;
+            println!(
+                "Imported {} layouts to {} ({} evaluated, {} stored already)",
+                summary.imported, options.url, summary.evaluated, summary.duplicates
+            );
+            for layout in summary.foreign_results.iter() {
+                println!("Evaluated again (exported with another configuration): {}", layout);
+            }
+            for error in summary.errors.iter() {
+                eprintln!("Skipped {}", error);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    env_logger::init();
+    let options = Options::from_args();
+
+    if let Err(e) = run(&options) {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Instant;
 use structopt::StructOpt;
 
 use evolve_keyboard_layout::common;
@@ -49,6 +50,10 @@ struct Options {
     )]
     publish_to: String,
 
+    /// Token for publishing to the webservice (credits the layout to the token's user)
+    #[structopt(long, env = "PUBLISH_TOKEN", hide_env_values = true)]
+    publish_token: Option<String>,
+
     /// Repeat optimizations indefinitely
     #[structopt(long)]
     run_forever: bool,
@@ -77,8 +82,12 @@ fn main() {
         .unwrap_or(&options.fix_from)
         .to_string();
 
+    let config_hash =
+        common::publish_config_hash(&options.evaluation_parameters, &options.publish_as);
+
     loop {
-        let layout = optimization::optimize(
+        let start = Instant::now();
+        let (layout, generations) = optimization::optimize(
             &optimization_params,
             &evaluator,
             &fix_from,
@@ -98,7 +107,20 @@ fn main() {
 
         // Publish to webservice.
         if let Some(publish_name) = &options.publish_as {
-            common::publish_to_webservice(&layout, publish_name, &options.publish_to);
+            let provenance = common::publish_provenance(
+                "genevo",
+                &optimization_params,
+                generations,
+                start.elapsed(),
+                config_hash.as_deref(),
+            );
+            common::publish_to_webservice(
+                &layout,
+                publish_name,
+                &options.publish_to,
+                options.publish_token.as_deref(),
+                &provenance,
+            );
         }
 
         if !options.run_forever {
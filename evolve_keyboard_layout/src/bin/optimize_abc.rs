@@ -1,6 +1,10 @@
+use std::time::Instant;
 use structopt::StructOpt;
 
 use evolve_keyboard_layout::common;
+use keyboard_layout::layout_generator::NeoLayoutGenerator;
+use layout_evaluation::evaluation::Evaluator;
+use layout_optimization::observer::{OptimizationObserver, ProgressEvent};
 use layout_optimization_abc::optimization;
 
 #[derive(StructOpt, Debug)]
@@ -40,6 +44,60 @@ struct Options {
         default_value = "https://keyboard-layout-optimizer.herokuapp.com/api"
     )]
     publish_to: String,
+
+    /// Token for publishing to the webservice (credits the layout to the token's user)
+    #[structopt(long, env = "PUBLISH_TOKEN", hide_env_values = true)]
+    publish_token: Option<String>,
+}
+
+/// Prints, logs, and publishes each new best layout.
+struct NewBestObserver<'a> {
+    options: &'a Options,
+    optimization_params: &'a optimization::Parameters,
+    evaluator: &'a Evaluator,
+    layout_generator: &'a NeoLayoutGenerator,
+    config_hash: Option<String>,
+    start: Instant,
+}
+
+impl OptimizationObserver for NewBestObserver<'_> {
+    fn notify(&self, event: &ProgressEvent) {
+        let layout = match self.layout_generator.generate(&event.best_layout) {
+            Ok(layout) => layout,
+            Err(e) => {
+                log::error!("Could not generate layout {}: {:?}", event.best_layout, e);
+                return;
+            }
+        };
+        println!("{}", layout.plot());
+        println!("{}", layout.plot_compact());
+
+        let evaluation_result = self.evaluator.evaluate_layout(&layout);
+        println!("{}", evaluation_result);
+
+        // Log solution to file.
+        if let Some(filename) = &self.options.append_solutions_to {
+            common::append_to_file(&layout, filename);
+        }
+
+        // Publish to webservice.
+        if let Some(publish_name) = &self.options.publish_as {
+            let provenance = common::publish_provenance(
+                "abc",
+                self.optimization_params,
+                event.iteration,
+                self.start.elapsed(),
+                self.config_hash.as_deref(),
+            );
+            common::publish_to_webservice(
+                &layout,
+                publish_name,
+                &self.options.publish_to,
+                self.options.publish_token.as_deref(),
+                &provenance,
+            );
+        }
+    }
 }
 
 fn main() {
@@ -57,29 +115,28 @@ fn main() {
 
     let fix_from = options.fix_from.to_string();
 
-    for new_best in optimization::optimize(
+    let fixed_characters = options.fix.clone().unwrap_or_else(|| "".to_string());
+    let config_hash =
+        common::publish_config_hash(&options.evaluation_parameters, &options.publish_as);
+    let observer = NewBestObserver {
+        options: &options,
+        optimization_params: &optimization_params,
+        evaluator: &evaluator,
+        layout_generator: &layout_generator,
+        config_hash,
+        start: Instant::now(),
+    };
+
+    // runs until it is interrupted
+    optimization::optimize_with_observer(
         &optimization_params,
-        &evaluator,
-        &fix_from,
-        &layout_generator,
-        &options.fix.clone().unwrap_or_else(|| "".to_string()),
-        !options.no_cache_results,
-    ) {
-        let layout = new_best.solution;
-        println!("{}", layout.plot());
-        println!("{}", layout.plot_compact());
-
-        let evaluation_result = evaluator.evaluate_layout(&layout);
-        println!("{}", evaluation_result);
-
-        // Log solution to file.
-        if let Some(filename) = &options.append_solutions_to {
-            common::append_to_file(&layout, filename);
-        }
-
-        // Publish to webservice.
-        if let Some(publish_name) = &options.publish_as {
-            common::publish_to_webservice(&layout, publish_name, &options.publish_to);
-        }
-    }
+        &optimization::OptimizationOptions {
+            evaluator: &evaluator,
+            layout_generator: &layout_generator,
+            layout_str: &fix_from,
+            fixed_characters: &fixed_characters,
+            cache_results: !options.no_cache_results,
+        },
+        &observer,
+    );
 }
@@ -1,4 +1,5 @@
 use rayon::iter::{ParallelBridge, ParallelIterator};
+use std::time::Instant;
 use structopt::StructOpt;
 
 use evolve_keyboard_layout::common;
@@ -59,6 +60,10 @@ struct Options {
     )]
     publish_to: String,
 
+    /// Token for publishing to the webservice (credits the layout to the token's user)
+    #[structopt(long, env = "PUBLISH_TOKEN", hide_env_values = true)]
+    publish_token: Option<String>,
+
     /// Repeat optimizations indefinitely
     #[structopt(long)]
     run_forever: bool,
@@ -152,6 +157,9 @@ fn main() {
         false => None,
     };
 
+    let config_hash =
+        common::publish_config_hash(&options.evaluation_parameters, &options.publish_as);
+
     layout_iterator
         .enumerate()
         .par_bridge()
@@ -163,7 +171,8 @@ fn main() {
             }
 
             // Perform the optimization.
-            let layout = optimization::optimize(
+            let start = Instant::now();
+            let (layout, iterations) = optimization::optimize(
                 &format!("Process {:>3}", i),
                 &optimization_params,
                 &fix_from,
@@ -189,7 +198,20 @@ fn main() {
 
             // Publish to webservice.
             if let Some(publish_name) = &options.publish_as {
-                common::publish_to_webservice(&layout, publish_name, &options.publish_to);
+                let provenance = common::publish_provenance(
+                    "sa",
+                    &optimization_params,
+                    iterations,
+                    start.elapsed(),
+                    config_hash.as_deref(),
+                );
+                common::publish_to_webservice(
+                    &layout,
+                    publish_name,
+                    &options.publish_to,
+                    options.publish_token.as_deref(),
+                    &provenance,
+                );
             }
         });
 }
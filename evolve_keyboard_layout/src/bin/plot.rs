@@ -1,3 +1,4 @@
+use keyboard_layout::render::RenderOptions;
 use structopt::StructOpt;
 
 use evolve_keyboard_layout::common;
@@ -11,6 +12,22 @@ struct Options {
     /// Filename of layout configuration file to use
     #[structopt(short, long, default_value = "config/standard_keyboard.yml")]
     layout_config: String,
+
+    /// Write an SVG rendering of the layout (all layers) to this file
+    #[structopt(long)]
+    svg: Option<String>,
+
+    /// Write a PNG rendering of the layout (all layers) to this file
+    #[structopt(long)]
+    png: Option<String>,
+
+    /// TrueType/OpenType font for the labels in the PNG rendering
+    #[structopt(long)]
+    font: Option<String>,
+
+    /// Do not color the keys according to the fingers pressing them in the renderings
+    #[structopt(long)]
+    no_finger_colors: bool,
 }
 
 fn main() {
@@ -59,4 +76,26 @@ fn main() {
     );
     println!("Layout compact: \n{}", layout.plot_compact());
     println!("Layout as text: \n{}", layout);
+
+    let render_options = RenderOptions {
+        finger_colors: !options.no_finger_colors,
+        ..Default::default()
+    };
+    if let Some(filename) = &options.svg {
+        std::fs::write(filename, layout.svg(&render_options))
+            .unwrap_or_else(|e| panic!("Could not write SVG to {}: {}", filename, e));
+    }
+    if let Some(filename) = &options.png {
+        let font = options.font.as_ref().map(|f| {
+            std::fs::read(f).unwrap_or_else(|e| panic!("Could not read font {}: {}", f, e))
+        });
+        if font.is_none() {
+            log::warn!("No font given (see --font), the PNG rendering will not contain labels");
+        }
+        let png = layout
+            .png(&render_options, font.as_deref())
+            .unwrap_or_else(|e| panic!("Could not render PNG: {:?}", e));
+        std::fs::write(filename, png)
+            .unwrap_or_else(|e| panic!("Could not write PNG to {}: {}", filename, e));
+    }
 }
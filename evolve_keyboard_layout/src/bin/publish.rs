@@ -0,0 +1,87 @@
+use layouts_webservice_client::{api::PostLayout, Client};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "Keyboard layout publication")]
+struct Options {
+    /// List of Layout keys from left to right, top to bottom
+    layout_str: Vec<String>,
+
+    /// Publish the layouts in this file (one per line)
+    #[structopt(long)]
+    from_file: Option<String>,
+
+    /// Publish the layouts under this name
+    #[structopt(long)]
+    publish_as: Option<String>,
+
+    /// Publish the layouts to webservice at this url
+    #[structopt(
+        long,
+        default_value = "https://keyboard-layout-optimizer.herokuapp.com/api"
+    )]
+    publish_to: String,
+
+    /// Profile of the webservice to publish to (default: its default profile)
+    #[structopt(long)]
+    profile: Option<String>,
+
+    /// Token for publishing to the webservice (credits the layouts to the token's user)
+    #[structopt(long, env = "PUBLISH_TOKEN", hide_env_values = true)]
+    publish_token: Option<String>,
+
+    /// Highlight the layouts (requires an admin token or the secret)
+    #[structopt(long)]
+    highlight: bool,
+
+    /// Admin secret of the webservice (for highlighting without admin token)
+    #[structopt(long, env = "SECRET", hide_env_values = true)]
+    secret: Option<String>,
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    env_logger::init();
+    let options = Options::from_args();
+
+    let mut layout_strs = options.layout_str.clone();
+    if let Some(filename) = &options.from_file {
+        let content = std::fs::read_to_string(filename)
+            .unwrap_or_else(|_| panic!("Could not read layouts from {}.", filename));
+        layout_strs.extend(
+            content
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty()),
+        );
+    }
+
+    let client = Client::new(&options.publish_to)
+        .expect("Invalid url of the webservice")
+        .with_token(options.publish_token.as_deref());
+
+    let mut failed = false;
+    for layout_str in layout_strs {
+        let post = PostLayout {
+            layout: layout_str.clone(),
+            published_by: options.publish_as.clone(),
+            highlight: options.highlight.then_some(true),
+            secret: options.secret.clone(),
+            provenance: None,
+        };
+        match client.publish(options.profile.as_deref(), &post) {
+            Ok(evaluation) => println!(
+                "Published {} to {} (cost: {:.4})",
+                evaluation.layout, options.publish_to, evaluation.total_cost
+            ),
+            Err(e) => {
+                eprintln!("Could not publish {}: {:#}", layout_str, e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -8,10 +8,14 @@ use layout_evaluation::{
     ngram_mapper::on_demand_ngram_mapper::{NgramMapperConfig, OnDemandNgramMapper},
     ngrams::{Bigrams, Trigrams, Unigrams},
 };
+use layouts_webservice_client::{
+    api::{PostLayout, PublishProvenance},
+    config_hash::{read, sha256_hex, ConfigFiles},
+    Client,
+};
 
 use anyhow::Result;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::sync::Arc;
@@ -97,13 +101,17 @@ pub fn init(options: &Options) -> (NeoLayoutGenerator, Evaluator) {
     )
 }
 
-pub fn init_layout_generator(layout_config: &str) -> NeoLayoutGenerator {
-    let layout_config = LayoutConfig::from_yaml(layout_config)
-        .expect(&format!("Could not load config file {}", layout_config));
+pub fn init_layout_generator(layout_config_file: &str) -> NeoLayoutGenerator {
+    let layout_config = LayoutConfig::from_yaml(layout_config_file)
+        .expect(&format!("Could not load config file {}", layout_config_file));
 
-    let keyboard = Arc::new(Keyboard::from_yaml_object(layout_config.keyboard));
+    let keyboard = Arc::new(
+        Keyboard::from_yaml_object(layout_config.keyboard)
+            .unwrap_or_else(|e| panic!("Invalid keyboard config {}: {:#}", layout_config_file, e)),
+    );
 
     NeoLayoutGenerator::from_object(layout_config.base_layout, keyboard)
+        .unwrap_or_else(|e| panic!("Invalid layout config {}: {}", layout_config_file, e))
 }
 
 pub fn init_evaluator(options: &Options) -> Evaluator {
@@ -159,7 +167,9 @@ pub fn init_evaluator(options: &Options) -> Evaluator {
         }
     };
 
-    Evaluator::default(Box::new(ngram_provider)).default_metrics(&eval_params.metrics)
+    Evaluator::default(Box::new(ngram_provider))
+        .default_metrics(&eval_params.metrics)
+        .expect("Could not initialize metrics")
 }
 
 /// Appends a layout-string to a file.
@@ -176,20 +186,90 @@ pub fn append_to_file(layout: &Layout, filename: &str) {
     }
 }
 
-/// Publishes the layout to a webservice.
-pub fn publish_to_webservice(layout: &Layout, publish_name: &str, publish_to: &str) {
-    let client = reqwest::blocking::Client::new();
-    let mut body = HashMap::new();
-    body.insert("published_by", publish_name.to_string());
-    body.insert("layout", layout.as_text());
-    let resp = client.post(publish_to).json(&body).send().ok();
-    if let Some(resp) = resp {
-        if resp.status().is_success() {
-            log::info!("Published layout '{}' to {}", layout.as_text(), publish_to);
-        } else {
-            log::error!("Could not publish result to webservice: {:?}", &resp.text());
-        }
-    } else {
-        log::error!("Could not publish result to webservice");
+/// How a layout was found by an optimizer, to be published with it. The `config_hash` is
+/// computed once at startup with `publish_config_hash`.
+pub fn publish_provenance<P: Serialize>(
+    optimizer: &str,
+    parameters: &P,
+    iterations: u64,
+    runtime: std::time::Duration,
+    config_hash: Option<&str>,
+) -> PublishProvenance {
+    PublishProvenance {
+        optimizer: Some(optimizer.to_string()),
+        parameters: serde_json::to_value(parameters).ok(),
+        iterations: Some(iterations),
+        runtime: Some(runtime.as_secs_f64()),
+        config_hash: config_hash.map(|hash| hash.to_string()),
+    }
+}
+
+/// The hash of the evaluation configuration (see `config_hash`) to publish layouts with, if they
+/// shall be published at all. Failures are logged, the layouts are published without the hash.
+pub fn publish_config_hash(options: &Options, publish_as: &Option<String>) -> Option<String> {
+    publish_as.as_ref()?;
+
+    config_hash(options)
+        .map_err(|e| log::warn!("Could not hash the evaluation configuration: {:?}", e))
+        .ok()
+}
+
+/// Hash the evaluation parameters, the layout configuration, and the ngram files the same way
+/// the webservice identifies its configuration versions, so that a published layout can be
+/// matched to the configuration it was optimized with. Options deviating from the configuration
+/// files (e.g. a corpus instead of the ngram files) are hashed as well.
+pub fn config_hash(options: &Options) -> Result<String> {
+    let ngrams: NGramConfig = EvaluationParameters::from_yaml(&options.eval_parameters)?.ngrams;
+    let files = ConfigFiles {
+        eval_parameters: &options.eval_parameters,
+        layout_config: &options.layout_config,
+        unigrams: &ngrams.unigrams,
+        bigrams: &ngrams.bigrams,
+        trigrams: &ngrams.trigrams,
+    };
+
+    let mut overrides = Vec::new();
+    if let Some(corpus) = &options.corpus {
+        overrides.push(format!("corpus: {}", sha256_hex(&read(corpus)?)));
+    }
+    if let Some(text) = &options.text {
+        overrides.push(format!("text: {}", sha256_hex(text.as_bytes())));
+    }
+    if let Some(tops) = options.tops {
+        overrides.push(format!("tops: {}", tops));
+    }
+    if options.no_split_modifiers {
+        overrides.push("no_split_modifiers".to_string());
+    }
+    if options.no_add_secondary_bigrams {
+        overrides.push("no_add_secondary_bigrams".to_string());
+    }
+    if options.no_increase_common_bigrams {
+        overrides.push("no_increase_common_bigrams".to_string());
+    }
+
+    Ok(files.hash(&overrides)?.hash)
+}
+
+/// Publishes the layout to a webservice. With a token, the layout is credited to the token's
+/// user (and subject to the user's instead of the anonymous rate limit).
+pub fn publish_to_webservice(
+    layout: &Layout,
+    publish_name: &str,
+    publish_to: &str,
+    publish_token: Option<&str>,
+    provenance: &PublishProvenance,
+) {
+    let post = PostLayout {
+        layout: layout.as_text(),
+        published_by: Some(publish_name.to_string()),
+        provenance: Some(provenance.clone()),
+        ..Default::default()
+    };
+    let result = Client::new(publish_to)
+        .and_then(|client| client.with_token(publish_token).publish(None, &post));
+    match result {
+        Ok(_) => log::info!("Published layout '{}' to {}", layout.as_text(), publish_to),
+        Err(e) => log::error!("Could not publish result to webservice: {:#}", e),
     }
 }
@@ -7,6 +7,7 @@ use wasm_bindgen::prelude::*;
 use keyboard_layout::{
     keyboard::{Keyboard, KeyboardYAML},
     layout_generator::{BaseLayoutYAML, NeoLayoutGenerator},
+    render::RenderOptions,
 };
 
 use layout_evaluation::{
@@ -84,10 +85,14 @@ impl LayoutPlotter {
         let layout_cfg: LayoutConfig = serde_yaml::from_str(layout_cfg_str)
             .map_err(|e| format!("Could not read layout config: {:?}", e))?;
 
-        let keyboard = Arc::new(Keyboard::from_yaml_object(layout_cfg.keyboard));
+        let keyboard = Arc::new(
+            Keyboard::from_yaml_object(layout_cfg.keyboard)
+                .map_err(|e| format!("Invalid keyboard config: {:#}", e))?,
+        );
 
         let layout_generator =
-            NeoLayoutGenerator::from_object(layout_cfg.base_layout, keyboard.clone());
+            NeoLayoutGenerator::from_object(layout_cfg.base_layout, keyboard.clone())
+                .map_err(|e| format!("Invalid layout config: {}", e))?;
 
         Ok(LayoutPlotter { layout_generator })
     }
@@ -99,6 +104,14 @@ impl LayoutPlotter {
             .map_err(|e| format!("Could not plot the layout: {:?}", e))?;
         Ok(layout.plot_layer(layer))
     }
+
+    pub fn svg(&self, layout_str: &str) -> Result<String, JsValue> {
+        let layout = self
+            .layout_generator
+            .generate_unchecked(layout_str)
+            .map_err(|e| format!("Could not plot the layout: {:?}", e))?;
+        Ok(layout.svg(&RenderOptions::default()))
+    }
 }
 
 #[wasm_bindgen]
@@ -164,16 +177,21 @@ impl LayoutEvaluator {
         let layout_cfg: LayoutConfig = serde_yaml::from_str(layout_cfg_str)
             .map_err(|e| format!("Could not read layout config: {:?}", e))?;
 
-        let keyboard = Arc::new(Keyboard::from_yaml_object(layout_cfg.keyboard));
+        let keyboard = Arc::new(
+            Keyboard::from_yaml_object(layout_cfg.keyboard)
+                .map_err(|e| format!("Invalid keyboard config: {:#}", e))?,
+        );
 
         let layout_generator =
-            NeoLayoutGenerator::from_object(layout_cfg.base_layout, keyboard.clone());
+            NeoLayoutGenerator::from_object(layout_cfg.base_layout, keyboard.clone())
+                .map_err(|e| format!("Invalid layout config: {}", e))?;
 
         let eval_params: EvaluationParameters = serde_yaml::from_str(eval_params_str)
             .map_err(|e| format!("Could not read evaluation parameters: {:?}", e))?;
 
         let evaluator = Evaluator::default(Box::new(ngram_provider.ngram_provider.clone()))
-            .default_metrics(&eval_params.metrics);
+            .default_metrics(&eval_params.metrics)
+            .map_err(|e| format!("Could not initialize metrics: {:?}", e))?;
 
         Ok(LayoutEvaluator {
             layout_generator,
@@ -206,6 +224,14 @@ impl LayoutEvaluator {
         Ok(layout.plot_layer(layer))
     }
 
+    pub fn svg(&self, layout_str: &str) -> Result<String, JsValue> {
+        let layout = self
+            .layout_generator
+            .generate_unchecked(layout_str)
+            .map_err(|e| format!("Could not plot the layout: {:?}", e))?;
+        Ok(layout.svg(&RenderOptions::default()))
+    }
+
     pub fn permutable_keys(&self) -> JsValue {
         let permutable_keys = self.layout_generator.permutable_keys();
         return JsValue::from_serde(&permutable_keys).unwrap();
@@ -1,10 +1,10 @@
 //! This module provides structs for representing physical properties of keys in a keyboard
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Row and columnar location on the keyboard
-#[derive(Clone, Copy, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash, Debug)]
 pub struct MatrixPosition(pub isize, pub isize);
 
 impl MatrixPosition {
@@ -22,7 +22,7 @@ impl Default for MatrixPosition {
 }
 
 /// 2D position on the keyboard
-#[derive(Clone, Copy, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Debug)]
 pub struct Position(pub f64, pub f64);
 
 impl Position {
@@ -38,7 +38,17 @@ impl Default for Position {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Debug)]
+/// Width and height of a key (in units of a standard key)
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Debug)]
+pub struct Size(pub f64, pub f64);
+
+impl Default for Size {
+    fn default() -> Self {
+        Size(1.0, 1.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Debug)]
 pub enum Finger {
     Pinky = 4,
     Ring = 3,
@@ -60,7 +70,7 @@ impl Finger {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Debug)]
 pub enum Hand {
     Left = 0,
     Right = 1,
@@ -226,6 +236,9 @@ pub struct Key {
     /// 2D position of the key
     pub position: Position,
 
+    /// Width and height of the key
+    pub size: Size,
+
     /// Symmetriy index: Two different keys with identical symmetry index are considered symmetrical
     pub symmetry_index: usize,
 
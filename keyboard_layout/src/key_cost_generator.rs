@@ -0,0 +1,194 @@
+//! This module provides a generator for default key costs and unbalancing values of a keyboard.
+//!
+//! Each finger rests on a home key. The effort for reaching another key with that finger is
+//! modeled by an ellipse around the home key's position: The ellipse's half-axes describe how far
+//! the finger can comfortably reach horizontally and vertically. The resulting (elliptic) distance
+//! determines the key's cost and whether the key forces the hand away from its home position.
+
+use crate::key::{Finger, FingerMap, HandFingerMap, Key, MatrixPosition, Position};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Horizontal and vertical half-axes of the ellipse describing the comfortable reach of a finger.
+#[derive(Clone, Copy, Deserialize, Debug)]
+pub struct Reach(pub f64, pub f64);
+
+impl Default for Reach {
+    fn default() -> Self {
+        Reach(1.0, 1.0)
+    }
+}
+
+fn default_base_cost() -> f64 {
+    3.0
+}
+
+fn default_reach_cost() -> f64 {
+    3.5
+}
+
+fn default_exponent() -> f64 {
+    2.0
+}
+
+fn default_upward_factor() -> f64 {
+    1.4
+}
+
+fn default_max_cost() -> f64 {
+    80.0
+}
+
+fn default_unbalancing_threshold() -> f64 {
+    1.0
+}
+
+fn default_reach() -> HashMap<Finger, Reach> {
+    vec![
+        (Finger::Thumb, Reach(1.5, 0.5)),
+        (Finger::Pointer, Reach(0.8, 0.8)),
+        (Finger::Middle, Reach(0.4, 1.0)),
+        (Finger::Ring, Reach(0.45, 0.9)),
+        (Finger::Pinky, Reach(1.4, 0.75)),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn default_finger_factors() -> HashMap<Finger, f64> {
+    vec![
+        (Finger::Thumb, 1.0),
+        (Finger::Pointer, 1.0),
+        (Finger::Middle, 1.2),
+        (Finger::Ring, 1.3),
+        (Finger::Pinky, 1.2),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// Configuration of the key cost generator.
+///
+/// Corresponds to (parts of) a YAML configuration file.
+#[derive(Clone, Deserialize, Debug)]
+pub struct KeyCostGeneratorYAML {
+    /// Matrix positions of the keys that the fingers rest on (one per finger and hand)
+    home_keys: Vec<MatrixPosition>,
+    /// Cost of pressing a home key (before applying the finger factor)
+    #[serde(default = "default_base_cost")]
+    base_cost: f64,
+    /// Cost for reaching a key at an elliptic distance of one from the home key
+    #[serde(default = "default_reach_cost")]
+    reach_cost: f64,
+    /// The elliptic distance is raised to this power
+    #[serde(default = "default_exponent")]
+    exponent: f64,
+    /// Vertical distances for reaching upwards (towards lower rows) are multiplied with this factor
+    #[serde(default = "default_upward_factor")]
+    upward_factor: f64,
+    /// Generated costs are capped at this value
+    #[serde(default = "default_max_cost")]
+    max_cost: f64,
+    /// Keys beyond this elliptic distance are considered unbalancing (by the excess distance)
+    #[serde(default = "default_unbalancing_threshold")]
+    unbalancing_threshold: f64,
+    /// Ellipse of comfortable reach for each finger
+    #[serde(default = "default_reach")]
+    reach: HashMap<Finger, Reach>,
+    /// All costs of a finger are multiplied with this factor
+    #[serde(default = "default_finger_factors")]
+    finger_factors: HashMap<Finger, f64>,
+}
+
+/// Generates key costs and unbalancing values from the keys' positions, their finger assignments,
+/// and the home positions of the fingers.
+#[derive(Clone, Debug)]
+pub struct KeyCostGenerator {
+    home_keys: Vec<MatrixPosition>,
+    base_cost: f64,
+    reach_cost: f64,
+    exponent: f64,
+    upward_factor: f64,
+    max_cost: f64,
+    unbalancing_threshold: f64,
+    reach: FingerMap<Reach>,
+    finger_factors: FingerMap<f64>,
+}
+
+impl KeyCostGenerator {
+    /// Generate a `KeyCostGenerator` from a `KeyCostGeneratorYAML` object
+    pub fn from_yaml_object(g: KeyCostGeneratorYAML) -> Self {
+        Self {
+            home_keys: g.home_keys,
+            base_cost: g.base_cost,
+            reach_cost: g.reach_cost,
+            exponent: g.exponent,
+            upward_factor: g.upward_factor,
+            max_cost: g.max_cost,
+            unbalancing_threshold: g.unbalancing_threshold,
+            reach: FingerMap::with_hashmap(&g.reach, Reach::default()),
+            finger_factors: FingerMap::with_hashmap(&g.finger_factors, 1.0),
+        }
+    }
+
+    /// Determine the home position of each finger from the configured home keys
+    pub fn home_positions(&self, keys: &[Key]) -> HandFingerMap<Option<Position>> {
+        let mut home_positions = HandFingerMap::with_default(None);
+        keys.iter()
+            .filter(|k| self.home_keys.contains(&k.matrix_position))
+            .for_each(|k| home_positions.set(&k.hand, &k.finger, Some(k.position)));
+
+        home_positions
+    }
+
+    /// Elliptic distance of a key from the home position of its finger
+    fn reach_distance(&self, key: &Key, home_position: &Position) -> f64 {
+        let reach = self.reach.get(&key.finger);
+        let dx = (key.position.0 - home_position.0) / reach.0;
+        let mut dy = (key.position.1 - home_position.1) / reach.1;
+        if dy < 0.0 {
+            dy *= self.upward_factor;
+        }
+
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    /// Generate the cost and the unbalancing value for a key (`None` if its finger has no home position)
+    pub fn generate_key(
+        &self,
+        key: &Key,
+        home_positions: &HandFingerMap<Option<Position>>,
+    ) -> Option<(f64, f64)> {
+        let home_position = (*home_positions.get(&key.hand, &key.finger))?;
+        let distance = self.reach_distance(key, &home_position);
+
+        let cost = self.finger_factors.get(&key.finger)
+            * (self.base_cost + self.reach_cost * distance.powf(self.exponent));
+        let cost = cost.min(self.max_cost);
+        let unbalancing = (distance - self.unbalancing_threshold).max(0.0);
+
+        Some((cost, unbalancing))
+    }
+
+    /// Generate the costs and the unbalancing values for all keys (in the order of the keys).
+    /// Fails if the finger of a key has no home key.
+    pub fn generate(&self, keys: &[Key]) -> Result<Vec<(f64, f64)>> {
+        let home_positions = self.home_positions(keys);
+
+        keys.iter()
+            .map(|k| match self.generate_key(k, &home_positions) {
+                Some((cost, unbalancing)) => {
+                    Ok((cost.round(), (10.0 * unbalancing).round() / 10.0))
+                }
+                None => Err(anyhow!(
+                    "No home key for finger {:?} of hand {:?} (key at {:?})",
+                    k.finger,
+                    k.hand,
+                    k.matrix_position
+                )),
+            })
+            .collect()
+    }
+}
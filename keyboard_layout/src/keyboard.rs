@@ -1,8 +1,9 @@
 //! This module provides a struct representing a keyboard.
 
-use crate::key::{Finger, Hand, Key, MatrixPosition, Position};
+use crate::key::{Finger, Hand, Key, MatrixPosition, Position, Size};
+use crate::key_cost_generator::{KeyCostGenerator, KeyCostGeneratorYAML};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use serde::Deserialize;
 use std::collections::HashMap;
 
@@ -14,6 +15,7 @@ pub type KeyIndex = u16;
 pub struct Keyboard {
     /// The keys of the keyboard
     pub keys: Vec<Key>,
+    key_cost_generator: Option<KeyCostGenerator>,
     plot_template: String,
     plot_template_short: String,
 }
@@ -25,19 +27,39 @@ pub struct Keyboard {
 pub struct KeyboardYAML {
     matrix_positions: Vec<Vec<MatrixPosition>>,
     positions: Vec<Vec<Position>>,
+    #[serde(default)]
+    key_sizes: Option<Vec<Vec<Size>>>,
     hands: Vec<Vec<Hand>>,
     fingers: Vec<Vec<Finger>>,
-    key_costs: Vec<Vec<f64>>,
+    #[serde(default)]
+    key_costs: Option<Vec<Vec<f64>>>,
     symmetries: Vec<Vec<usize>>,
-    unbalancing_positions: Vec<Vec<f64>>,
+    #[serde(default)]
+    unbalancing_positions: Option<Vec<Vec<f64>>>,
+    #[serde(default)]
+    key_cost_generator: Option<KeyCostGeneratorYAML>,
     plot_template: String,
     plot_template_short: String,
 }
 
 impl Keyboard {
-    /// Generate a `Keyboard` from a `KeyboardYAML` object
-    pub fn from_yaml_object(k: KeyboardYAML) -> Self {
-        let keys = k
+    /// Generate a `Keyboard` from a `KeyboardYAML` object.
+    /// Key costs and unbalancing positions that are not given explicitly are generated with
+    /// the configured `KeyCostGenerator`; without one, they are required.
+    pub fn from_yaml_object(k: KeyboardYAML) -> Result<Self> {
+        let n_keys = k.hands.iter().flatten().count();
+        let has_key_costs = k.key_costs.is_some();
+        let has_unbalancing_positions = k.unbalancing_positions.is_some();
+        let key_costs = k.key_costs.unwrap_or_else(|| vec![vec![0.0; n_keys]]);
+        let unbalancing_positions = k
+            .unbalancing_positions
+            .unwrap_or_else(|| vec![vec![0.0; n_keys]]);
+        let key_sizes: Vec<Size> = match k.key_sizes {
+            Some(sizes) => sizes.into_iter().flatten().collect(),
+            None => vec![Size::default(); n_keys],
+        };
+
+        let mut keys: Vec<Key> = k
             .hands
             .into_iter()
             .flatten()
@@ -45,17 +67,22 @@ impl Keyboard {
             .zip(k.matrix_positions.into_iter().flatten())
             .zip(k.positions.into_iter().flatten())
             .zip(k.symmetries.into_iter().flatten())
-            .zip(k.key_costs.into_iter().flatten())
-            .zip(k.unbalancing_positions.into_iter().flatten())
+            .zip(key_costs.into_iter().flatten())
+            .zip(unbalancing_positions.into_iter().flatten())
+            .zip(key_sizes)
             .map(
                 |(
-                    (((((hand, finger), matrix_position), position), symmetry_index), cost),
-                    unbalancing,
+                    (
+                        (((((hand, finger), matrix_position), position), symmetry_index), cost),
+                        unbalancing,
+                    ),
+                    size,
                 )| Key {
                     hand,
                     finger,
                     matrix_position,
                     position,
+                    size,
                     symmetry_index,
                     cost,
                     unbalancing,
@@ -63,24 +90,59 @@ impl Keyboard {
             )
             .collect();
 
-        Keyboard {
+        let key_cost_generator = k.key_cost_generator.map(KeyCostGenerator::from_yaml_object);
+
+        if !has_key_costs || !has_unbalancing_positions {
+            match &key_cost_generator {
+                Some(generator) => {
+                    generator
+                        .generate(&keys)
+                        .context("Could not generate key costs")?
+                        .into_iter()
+                        .zip(keys.iter_mut())
+                        .for_each(|((cost, unbalancing), key)| {
+                            if !has_key_costs {
+                                key.cost = cost;
+                            }
+                            if !has_unbalancing_positions {
+                                key.unbalancing = unbalancing;
+                            }
+                        });
+                }
+                None => bail!(
+                    "Neither key costs and unbalancing positions nor a key cost generator are given"
+                ),
+            }
+        }
+
+        Ok(Keyboard {
             keys,
+            key_cost_generator,
             plot_template: k.plot_template,
             plot_template_short: k.plot_template_short,
-        }
+        })
     }
 
     /// Generate a `Keyboard` from a YAML file
     pub fn from_yaml_file(filename: &str) -> Result<Self> {
         let f = std::fs::File::open(filename)?;
         let k: KeyboardYAML = serde_yaml::from_reader(f)?;
-        Ok(Keyboard::from_yaml_object(k))
+        Keyboard::from_yaml_object(k)
     }
 
     /// Generate a `Keyboard` from a YAML string
     pub fn from_yaml_str(data: &str) -> Result<Self> {
         let k: KeyboardYAML = serde_yaml::from_str(data)?;
-        Ok(Keyboard::from_yaml_object(k))
+        Keyboard::from_yaml_object(k)
+    }
+
+    /// Generate key costs and unbalancing values (in the order of the keys) with the configured
+    /// `KeyCostGenerator` (if any), regardless of whether they were given explicitly
+    pub fn generated_key_costs(&self) -> Result<Option<Vec<(f64, f64)>>> {
+        self.key_cost_generator
+            .as_ref()
+            .map(|generator| generator.generate(&self.keys))
+            .transpose()
     }
 
     /// Plot a graphical representation of the keyboard with given key labels
@@ -95,6 +157,44 @@ impl Keyboard {
         reg.render_template(&self.plot_template, &labels).unwrap()
     }
 
+    /// Plot a graphical representation of the keyboard with key labels of up to three characters
+    /// (the padding around the labels in the plot template is used for the additional characters)
+    pub fn plot_wide(&self, key_labels: &[&str]) -> String {
+        let mut reg = handlebars::Handlebars::new();
+        reg.register_escape_fn(handlebars::no_escape);
+        let template = self.plot_template.replace(" {{", "{{").replace("}} ", "}}");
+        let labels: HashMap<usize, String> = key_labels
+            .iter()
+            .map(|c| format!("{:>3}", c))
+            .enumerate()
+            .collect();
+        reg.render_template(&template, &labels).unwrap()
+    }
+
+    /// Plot a graphical representation of the keyboard with a value for each key (e.g. its cost),
+    /// rounded to fit into three characters
+    pub fn plot_values(&self, values: &[f64]) -> String {
+        let labels: Vec<String> = values
+            .iter()
+            .map(|value| {
+                let label = if value.abs() >= 9.95 {
+                    format!("{:.0}", value)
+                } else {
+                    format!("{:.1}", value)
+                };
+                // e.g. "-0.5" -> "-.5"
+                if label.len() > 3 {
+                    label.replacen("0.", ".", 1)
+                } else {
+                    label
+                }
+            })
+            .collect();
+        let labels: Vec<&str> = labels.iter().map(|s| s.as_str()).collect();
+
+        self.plot_wide(&labels)
+    }
+
     /// Plot a compact graphical representation of the keyboard with given key labels without borders (compatible with ArneBab's input strings)
     pub fn plot_compact(&self, key_labels: &[&str]) -> String {
         let mut reg = handlebars::Handlebars::new();
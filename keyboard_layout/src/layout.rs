@@ -2,8 +2,9 @@
 //! its relation to the individual keys required to generate the layout's symbols.
 //! These provide the core objects that are evaluated in the `layout_evaluation` crate.
 
-use crate::key::{Hand, Key};
+use crate::key::{Hand, Key, Position};
 use crate::keyboard::{KeyIndex, Keyboard};
+use crate::render::{Keycap, RenderOptions};
 
 use anyhow::Result;
 use rustc_hash::FxHashMap;
@@ -31,10 +32,15 @@ pub struct LayerKey {
     pub symbol: char,
     /// Vec of modifiers required to activate the layer (in terms of a `LayerKeyIndex` for a layout)
     pub modifiers: Vec<LayerKeyIndex>,
+    /// Vec of further keys (in terms of their base-layer `LayerKeyIndex`) that need to be pressed
+    /// simultaneously if the symbol is generated by a combo
+    pub combo_keys: Vec<LayerKeyIndex>,
     /// If the key shall not be permutated for optimization
     pub is_fixed: bool,
     /// If the symbol itself is a modifier
     pub is_modifier: bool,
+    /// If the key to press for the symbol is a dual-role (tap-hold) key
+    pub is_dual_role: bool,
     key_index: KeyIndex, // is used for determining corresponding base layer key
 }
 
@@ -54,8 +60,10 @@ impl LayerKey {
             key_index,
             symbol,
             modifiers,
+            combo_keys: Vec::new(),
             is_fixed,
             is_modifier,
+            is_dual_role: false,
         }
     }
 }
@@ -63,6 +71,7 @@ impl LayerKey {
 /// A layout represents a collection of symbols (chars) that can be generated with a keyboard.
 /// To achieve a higher number of symbols than there are keys on the keyboard, each key can be
 /// associated with several layers. The layers are activated by pressing (combinations of) modifier keys.
+/// Further symbols can be generated by combos, i.e. by pressing multiple keys simultaneously.
 ///
 /// The layout is represented as a Vec of `LayerKey` objects with their indexes in the Vec being
 /// called `LayerKeyIndex`.
@@ -78,6 +87,8 @@ pub struct Layout {
     key_layers: Vec<Vec<LayerKeyIndex>>,
     key_map: FxHashMap<char, LayerKeyIndex>,
     layer_costs: Vec<f64>,
+    combos: Vec<Vec<KeyIndex>>,
+    base_layerkeys: Vec<LayerKeyIndex>,
 }
 
 impl std::fmt::Display for Layout {
@@ -86,6 +97,21 @@ impl std::fmt::Display for Layout {
     }
 }
 
+/// Combine the keys of a combo into a single (virtual) key. Hand, finger, and matrix position are
+/// taken from the first key, the position is averaged and the costs of all keys are summed up.
+fn combo_key(keys: &[&Key]) -> Key {
+    let n = keys.len() as f64;
+    Key {
+        position: Position(
+            keys.iter().map(|k| k.position.0).sum::<f64>() / n,
+            keys.iter().map(|k| k.position.1).sum::<f64>() / n,
+        ),
+        cost: keys.iter().map(|k| k.cost).sum(),
+        unbalancing: keys.iter().map(|k| k.unbalancing).fold(0.0, f64::max),
+        ..keys[0].clone()
+    }
+}
+
 impl Layout {
     pub fn new(
         key_chars: Vec<Vec<char>>,
@@ -93,13 +119,28 @@ impl Layout {
         keyboard: Arc<Keyboard>,
         modifiers: Vec<FxHashMap<Hand, Vec<char>>>,
         layer_costs: Vec<f64>,
+        dual_role_keys: Vec<(KeyIndex, char)>,
+        combos: Vec<Vec<KeyIndex>>,
     ) -> Result<Self> {
+        // combos are represented by additional (virtual) keys following the keyboard's keys
+        let mut combo_keys = Vec::with_capacity(combos.len());
+        for combo in combos.iter() {
+            let keys: Vec<&Key> = combo
+                .iter()
+                .map(|key_index| keyboard.keys.get(*key_index as usize))
+                .collect::<Option<Vec<&Key>>>()
+                .filter(|keys| keys.len() > 1)
+                .ok_or(format!("Combo {:?} does not consist of valid keys", combo))
+                .map_err(anyhow::Error::msg)?;
+            combo_keys.push(combo_key(&keys));
+        }
+
         // generate layer keys
         let mut layerkeys = Vec::new();
         let mut layerkey_index = 0;
         let key_layers: Vec<Vec<LayerKeyIndex>> = key_chars
             .iter()
-            .zip(keyboard.keys.iter())
+            .zip(keyboard.keys.iter().chain(combo_keys.iter()))
             .zip(fixed_keys.iter())
             .enumerate()
             .map(|(key_index, ((layer_chars, key), fixed))| {
@@ -126,7 +167,82 @@ impl Layout {
             })
             .collect();
 
-        let key_map = Self::gen_key_map(&layerkeys, &layer_costs);
+        // generate layer keys for the hold function of dual-role (tap-hold) keys
+        let mut hold_map = FxHashMap::default();
+        for (key_index, hold_char) in dual_role_keys.iter() {
+            let key = keyboard
+                .keys
+                .get(*key_index as usize)
+                .ok_or(format!("Dual-role key '{}' has no valid key", hold_char))
+                .map_err(anyhow::Error::msg)?;
+
+            // flag the tap symbols of the key as dual-role
+            key_layers[*key_index as usize]
+                .iter()
+                .for_each(|idx| layerkeys[*idx as usize].is_dual_role = true);
+
+            let mut layerkey = LayerKey::new(
+                0,
+                key.clone(),
+                *hold_char,
+                Vec::new(),
+                true,
+                true,
+                *key_index,
+            );
+            layerkey.is_dual_role = true;
+            layerkeys.push(layerkey);
+
+            hold_map.insert(*hold_char, layerkey_index);
+            layerkey_index += 1;
+        }
+
+        // resolve the further keys of each combo in terms of their base-layer `LayerKeyIndex`
+        // (combos may be missing if an unfinished layout is generated unchecked)
+        for (combo_index, combo) in combos.iter().enumerate() {
+            let combo_layerkeys = match key_layers.get(keyboard.keys.len() + combo_index) {
+                Some(combo_layerkeys) => combo_layerkeys,
+                None => break,
+            };
+
+            let further_keys: Vec<LayerKeyIndex> = combo
+                .iter()
+                .skip(1)
+                .map(|key_index| {
+                    key_layers
+                        .get(*key_index as usize)
+                        .and_then(|layers| layers.first().cloned())
+                })
+                .collect::<Option<Vec<LayerKeyIndex>>>()
+                .ok_or(format!("Combo {:?} contains keys without symbols", combo))
+                .map_err(anyhow::Error::msg)?;
+
+            combo_layerkeys
+                .iter()
+                .for_each(|idx| layerkeys[*idx as usize].combo_keys = further_keys.clone());
+        }
+
+        // the base-layer symbol for each layer key; base-layer symbols (including the hold function
+        // of dual-role keys) are their own base, combos use the base-layer symbol of their first key
+        let base_layerkeys: Vec<LayerKeyIndex> = layerkeys
+            .iter()
+            .enumerate()
+            .map(|(idx, k)| {
+                let key_index = k.key_index as usize;
+                if key_index >= keyboard.keys.len() {
+                    key_layers[combos[key_index - keyboard.keys.len()][0] as usize][0]
+                } else if k.layer == 0 {
+                    idx as LayerKeyIndex
+                } else {
+                    key_layers[key_index][0]
+                }
+            })
+            .collect();
+
+        let mut key_map = Self::gen_key_map(&layerkeys, &layer_costs);
+
+        // modifiers that can be held on a dual-role key are preferred over dedicated modifier keys
+        key_map.extend(hold_map);
 
         // a map that resolvers the `modifiers` chars to LayerKeyIndex
         let mut mod_map: Vec<FxHashMap<Hand, Vec<LayerKeyIndex>>> = Vec::new();
@@ -154,10 +270,10 @@ impl Layout {
             let mods = if k.layer > 0 && k.layer < modifiers.len() + 1 {
                 mod_map
                     .get(k.layer - 1)
-                    .unwrap()  // can not fail due to above check
+                    .unwrap() // can not fail due to above check
                     .get(&k.key.hand.other())
                     .map(|mods| mods.to_vec())
-                    .unwrap_or_default()  // default is an empty vec
+                    .unwrap_or_default() // default is an empty vec
             } else {
                 Vec::new()
             };
@@ -171,6 +287,8 @@ impl Layout {
             keyboard,
             key_map,
             layer_costs,
+            combos,
+            base_layerkeys,
         })
     }
 
@@ -225,19 +343,49 @@ impl Layout {
     /// Get the index of the "base" symbol (the one on the base layer, e.g. "A" -> "a") for a given `LayerKeyIndex`
     #[inline(always)]
     pub fn get_base_layerkey_index(&self, layerkey_index: &LayerKeyIndex) -> LayerKeyIndex {
-        let layerkey = self.get_layerkey(layerkey_index);
-        self.key_layers[layerkey.key_index as usize][0]
+        self.base_layerkeys[*layerkey_index as usize]
+    }
+
+    /// Get the symbol on the base layer of each of the keyboard's keys (if any). For dual-role keys,
+    /// this is the symbol generated when tapping the key, not the modifier activated when holding it.
+    pub fn base_symbols(&self) -> Vec<Option<char>> {
+        (0..self.keyboard.keys.len())
+            .map(|key_index| {
+                self.key_layers
+                    .get(key_index)
+                    .and_then(|layerkeys| layerkeys.first())
+                    .map(|idx| self.get_layerkey(idx).symbol)
+            })
+            .collect()
     }
 
-    /// Get a list of modifiers required to generate a given `LayerKey` as a Vec of `LayerKey`s
+    /// Get a list of modifiers (and further keys of a combo) required to generate a given `LayerKey` as a Vec of `LayerKey`s
     #[inline(always)]
     pub fn resolve_modifiers(&self, k: &LayerKeyIndex) -> (LayerKeyIndex, Vec<LayerKeyIndex>) {
         let base = self.get_base_layerkey_index(k);
         let k = self.get_layerkey(k);
-        let mods = k.modifiers.to_vec();
+        let mods = k
+            .combo_keys
+            .iter()
+            .chain(k.modifiers.iter())
+            .cloned()
+            .collect();
         (base, mods)
     }
 
+    /// Get the combos of the layout as lists of the `KeyIndex`es to press simultaneously
+    pub fn combos(&self) -> &[Vec<KeyIndex>] {
+        &self.combos
+    }
+
+    /// Get the `KeyIndex`es of the combo generating a given `LayerKey` (if it is generated by a combo)
+    pub fn get_combo(&self, layerkey: &LayerKey) -> Option<&[KeyIndex]> {
+        (layerkey.key_index as usize)
+            .checked_sub(self.keyboard.keys.len())
+            .and_then(|combo_index| self.combos.get(combo_index))
+            .map(|combo| combo.as_slice())
+    }
+
     /// Get the cost that are associated with a layer
     #[inline(always)]
     pub fn get_layer_cost(&self, layer: usize) -> f64 {
@@ -273,7 +421,29 @@ impl Layout {
             .collect();
 
         let keys_str: Vec<&str> = keys_strings.iter().map(|s| s.as_str()).collect();
-        self.keyboard.plot(&keys_str)
+        let mut plot = self.keyboard.plot(&keys_str);
+
+        // combos do not appear in the keyboard's plot template
+        if !self.combos.is_empty() && keys_str.len() == self.keyboard.keys.len() + self.combos.len()
+        {
+            let combos_str: Vec<String> = self
+                .combos
+                .iter()
+                .enumerate()
+                .map(|(combo_index, combo)| {
+                    let combo_labels: Vec<&str> =
+                        combo.iter().map(|idx| keys_str[*idx as usize]).collect();
+                    format!(
+                        "{} → {}",
+                        combo_labels.join("+"),
+                        keys_str[self.keyboard.keys.len() + combo_index]
+                    )
+                })
+                .collect();
+            plot.push_str(&format!("Combos: {}\n", combos_str.join(", ")));
+        }
+
+        plot
     }
 
     /// Plot a graphical representation of the base (first) layer
@@ -281,6 +451,52 @@ impl Layout {
         self.plot_layer(0)
     }
 
+    /// Keycaps showing the symbols of all layers of the keyboard's keys (for rendering the layout)
+    pub fn keycaps(&self) -> Vec<Keycap> {
+        self.key_layers
+            .iter()
+            .take(self.keyboard.keys.len())
+            .map(|layerkeys| {
+                let base = layerkeys.first().map(|idx| self.get_layerkey(idx));
+                let labels = layerkeys
+                    .iter()
+                    .enumerate()
+                    .map(|(layer, idx)| {
+                        let symbol = self.get_layerkey(idx).symbol;
+                        // symbols repeated from the base layer (e.g. modifiers) are not shown again
+                        if layer > 0 && Some(symbol) == base.map(|k| k.symbol) {
+                            return String::new();
+                        }
+                        symbol
+                            .to_string()
+                            .replace('\n', "\u{23ce}")
+                            .replace('\t', "\u{21e5}")
+                            .replace('\u{1b}', "\u{2327}")
+                            .replace('␡', " ")
+                    })
+                    .collect();
+
+                Keycap {
+                    labels,
+                    is_fixed: base.map(|k| k.is_fixed).unwrap_or(false),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// Render the layout as SVG based on the keys' positions and sizes
+    pub fn svg(&self, options: &RenderOptions) -> String {
+        crate::render::svg(&self.keyboard, &self.keycaps(), options)
+    }
+
+    /// Render the layout as PNG image based on the keys' positions and sizes (labels are drawn
+    /// with the given font)
+    #[cfg(feature = "raster")]
+    pub fn png(&self, options: &RenderOptions, font: Option<&[u8]>) -> Result<Vec<u8>> {
+        crate::render::png(&self.keyboard, &self.keycaps(), options, font)
+    }
+
     /// Plot a compact graphical representation (without borders and only non-fixed keys) of the base (first) layer
     pub fn plot_compact(&self) -> String {
         let keys_strings: Vec<String> = self
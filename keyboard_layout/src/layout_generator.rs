@@ -1,8 +1,8 @@
 //! This module provides a layout generator that can generate Neo variant layouts
 //! from given string representations of its base layer.
 
-use crate::key::Hand;
-use crate::keyboard::Keyboard;
+use crate::key::{Hand, MatrixPosition};
+use crate::keyboard::{KeyIndex, Keyboard};
 use crate::layout::Layout;
 
 use anyhow::Result;
@@ -21,6 +21,36 @@ pub enum LayoutError {
     MissingChars(String),
     #[error("Invalid keyboard layout: Unsupported characters: '{0}'")]
     UnsupportedChars(String),
+    #[error("Invalid layout configuration: Combo {0:?} requires at least two keys at valid matrix positions: {1:?}")]
+    InvalidCombo(Vec<String>, Vec<MatrixPosition>),
+    #[error("Invalid layout configuration: No key at matrix position {1:?} for dual-role key '{0}'")]
+    InvalidDualRoleKey(char, MatrixPosition),
+}
+
+/// A dual-role (tap-hold) key that generates its symbols when tapped and acts as a modifier when held,
+/// e.g. for "home row mods".
+///
+/// Corresponds to (parts of) a YAML configuration file.
+#[derive(Deserialize, Debug)]
+pub struct DualRoleKeyYAML {
+    /// Row and column position of the key on the keyboard
+    matrix_position: MatrixPosition,
+    /// Modifier that is activated when holding the key
+    hold: char,
+}
+
+/// A combo that generates symbols when pressing several keys simultaneously.
+///
+/// Corresponds to (parts of) a YAML configuration file.
+#[derive(Deserialize, Debug)]
+pub struct ComboYAML {
+    /// Row and column positions of the keys that need to be pressed simultaneously
+    matrix_positions: Vec<MatrixPosition>,
+    /// Symbols of the combo for each layer (analogous to the entries of `keys`)
+    keys: Vec<String>,
+    /// Whether the combo's symbols shall be excluded from permutations
+    #[serde(default)]
+    fixed: bool,
 }
 
 /// A collection of data (configuration) regarding the Neo layout (and its family)
@@ -34,6 +64,10 @@ pub struct BaseLayoutYAML {
     fixed_layers: Vec<usize>,
     modifiers: Vec<FxHashMap<Hand, Vec<char>>>,
     layer_costs: Vec<f64>,
+    #[serde(default)]
+    dual_role_keys: Vec<DualRoleKeyYAML>,
+    #[serde(default)]
+    combos: Vec<ComboYAML>,
 }
 
 /// Provides functionalities for generating Neo layout variants from given string representations
@@ -46,13 +80,16 @@ pub struct NeoLayoutGenerator {
     fixed_layers: Vec<usize>,
     modifiers: Vec<FxHashMap<Hand, Vec<char>>>,
     layer_costs: Vec<f64>,
+    dual_role_keys: Vec<(KeyIndex, char)>,
+    combos: Vec<Vec<KeyIndex>>,
     keyboard: Arc<Keyboard>,
 }
 
 impl NeoLayoutGenerator {
-    /// Generate a `NeoLayoutGenerator` from a `BaseLayoutYAML` object
-    pub fn from_object(base: BaseLayoutYAML, keyboard: Arc<Keyboard>) -> Self {
-        let keys: Vec<Vec<char>> = base
+    /// Generate a `NeoLayoutGenerator` from a `BaseLayoutYAML` object. Fails if a combo or a
+    /// dual-role key refers to matrix positions without keys.
+    pub fn from_object(base: BaseLayoutYAML, keyboard: Arc<Keyboard>) -> Result<Self, LayoutError> {
+        let mut keys: Vec<Vec<char>> = base
             .keys
             .iter()
             .flatten()
@@ -63,7 +100,42 @@ impl NeoLayoutGenerator {
                     .collect()
             })
             .collect();
-        let fixed_keys: Vec<bool> = base.fixed_keys.iter().flatten().cloned().collect();
+        let mut fixed_keys: Vec<bool> = base.fixed_keys.iter().flatten().cloned().collect();
+
+        // the symbols of combos are appended to the keys (in the order of the combos)
+        let mut combos: Vec<Vec<KeyIndex>> = Vec::new();
+        for combo in base.combos.iter() {
+            let key_indices: Option<Vec<KeyIndex>> = combo
+                .matrix_positions
+                .iter()
+                .map(|matrix_position| {
+                    keyboard
+                        .keys
+                        .iter()
+                        .position(|k| k.matrix_position == *matrix_position)
+                        .map(|i| i as KeyIndex)
+                })
+                .collect();
+            match key_indices {
+                Some(key_indices) if key_indices.len() > 1 => {
+                    keys.push(
+                        combo
+                            .keys
+                            .iter()
+                            .map(|l| l.chars().next().unwrap_or('␡'))
+                            .collect(),
+                    );
+                    fixed_keys.push(combo.fixed);
+                    combos.push(key_indices);
+                }
+                _ => {
+                    return Err(LayoutError::InvalidCombo(
+                        combo.keys.clone(),
+                        combo.matrix_positions.clone(),
+                    ));
+                }
+            }
+        }
 
         let mut permutable_key_map: FxHashMap<char, usize> = FxHashMap::default();
         keys.iter()
@@ -76,28 +148,46 @@ impl NeoLayoutGenerator {
                 }
             });
 
-        NeoLayoutGenerator {
+        let dual_role_keys: Vec<(KeyIndex, char)> = base
+            .dual_role_keys
+            .iter()
+            .map(|dual_role_key| {
+                keyboard
+                    .keys
+                    .iter()
+                    .position(|k| k.matrix_position == dual_role_key.matrix_position)
+                    .map(|i| (i as KeyIndex, dual_role_key.hold))
+                    .ok_or(LayoutError::InvalidDualRoleKey(
+                        dual_role_key.hold,
+                        dual_role_key.matrix_position,
+                    ))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(NeoLayoutGenerator {
             keys,
             fixed_keys,
             permutable_key_map,
             fixed_layers: base.fixed_layers,
             modifiers: base.modifiers,
             layer_costs: base.layer_costs,
+            dual_role_keys,
+            combos,
             keyboard,
-        }
+        })
     }
 
     /// Generate a `NeoLayoutGenerator` from a YAML file
     pub fn from_yaml_file(filename: &str, keyboard: Arc<Keyboard>) -> Result<Self> {
         let f = std::fs::File::open(filename)?;
         let base: BaseLayoutYAML = serde_yaml::from_reader(f)?;
-        Ok(NeoLayoutGenerator::from_object(base, keyboard))
+        Ok(NeoLayoutGenerator::from_object(base, keyboard)?)
     }
 
     /// Generate a `NeoLayoutGenerator` from a YAML string
     pub fn from_yaml_str(data: &str, keyboard: Arc<Keyboard>) -> Result<Self> {
         let base: BaseLayoutYAML = serde_yaml::from_str(data)?;
-        Ok(NeoLayoutGenerator::from_object(base, keyboard))
+        Ok(NeoLayoutGenerator::from_object(base, keyboard)?)
     }
 
     /// Generate a Neo variant `Layout` from given string representation of its base layer.
@@ -153,6 +243,8 @@ impl NeoLayoutGenerator {
             self.keyboard.clone(),
             self.modifiers.clone(),
             self.layer_costs.clone(),
+            self.dual_role_keys.clone(),
+            self.combos.clone(),
         )
     }
 
@@ -7,9 +7,11 @@
 //! and other associated properties.
 
 pub mod key;
+pub mod key_cost_generator;
 pub mod keyboard;
 pub mod layout;
 pub mod layout_generator;
+pub mod render;
 
 #[cfg(test)]
 mod tests {
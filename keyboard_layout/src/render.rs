@@ -0,0 +1,397 @@
+//! This module provides a renderer that draws a keyboard as SVG (or, with the `raster` feature,
+//! as PNG) based on the keys' positions and sizes. In contrast to `Keyboard::plot`, no
+//! keyboard-specific template is required.
+//!
+//! A key is drawn as a rectangle of the key's size centered at the center of a standard key
+//! at the key's position. Each keycap can show the symbols of multiple layers: the base layer in
+//! the center and higher layers in the corners.
+
+use crate::key::Finger;
+use crate::keyboard::Keyboard;
+
+/// A color as RGB values.
+pub type Rgb = [u8; 3];
+
+/// Content of a keycap in a rendering.
+#[derive(Clone, Default, Debug)]
+pub struct Keycap {
+    /// Labels of the layers (starting with the base layer); empty labels are skipped
+    pub labels: Vec<String>,
+    /// Additional small text at the bottom of the keycap
+    pub note: Option<String>,
+    /// Fill color (takes precedence over the finger coloring)
+    pub fill: Option<Rgb>,
+    /// Tooltip of the key (SVG only)
+    pub title: Option<String>,
+    /// If the key is fixed (not permutated in optimizations)
+    pub is_fixed: bool,
+}
+
+/// Options for rendering a keyboard.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Size of a standard key in pixels
+    pub key_size: f64,
+    /// Maximal number of layers to show on each keycap (at most six)
+    pub layers: usize,
+    /// If to color the keys according to the fingers pressing them
+    pub finger_colors: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            key_size: 60.0,
+            layers: 6,
+            finger_colors: true,
+        }
+    }
+}
+
+/// Keycap for keys without a given keycap
+static EMPTY_KEYCAP: Keycap = Keycap {
+    labels: Vec::new(),
+    note: None,
+    fill: None,
+    title: None,
+    is_fixed: false,
+};
+
+/// Margin around the keyboard (in pixels)
+const MARGIN: f64 = 10.0;
+/// Gap between neighboring keys (in pixels)
+const KEY_GAP: f64 = 4.0;
+
+const DEFAULT_FILL: Rgb = [255, 255, 255];
+const FIXED_FILL: Rgb = [230, 230, 230];
+const BORDER: Rgb = [85, 85, 85];
+const TEXT: Rgb = [0, 0, 0];
+const FIXED_TEXT: Rgb = [120, 120, 120];
+const NOTE_TEXT: Rgb = [51, 51, 51];
+
+fn finger_color(finger: &Finger) -> Rgb {
+    match finger {
+        Finger::Pinky => [244, 204, 204],
+        Finger::Ring => [252, 229, 205],
+        Finger::Middle => [255, 242, 204],
+        Finger::Pointer => [217, 234, 211],
+        Finger::Thumb => [207, 226, 243],
+    }
+}
+
+/// Relative position (wrt. the key's width and height) and relative font size (wrt. the size of
+/// a standard key) of the labels of the individual layers
+const LABEL_SLOTS: [(f64, f64, f64); 6] = [
+    (0.5, 0.62, 0.38),
+    (0.22, 0.3, 0.2),
+    (0.78, 0.3, 0.2),
+    (0.22, 0.88, 0.2),
+    (0.78, 0.88, 0.2),
+    (0.5, 0.3, 0.2),
+];
+
+/// A text element of a rendering (positions in pixels, `y` is the baseline)
+struct Text<'a> {
+    x: f64,
+    y: f64,
+    size: f64,
+    color: Rgb,
+    content: &'a str,
+}
+
+/// Geometry and content of a key in a rendering (positions in pixels)
+struct KeyShape<'a> {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    fill: Rgb,
+    is_fixed: bool,
+    title: Option<&'a str>,
+    texts: Vec<Text<'a>>,
+}
+
+/// Compute the shapes of all keys and the total width and height of the rendering.
+fn key_shapes<'a>(
+    keyboard: &Keyboard,
+    keycaps: &'a [Keycap],
+    options: &RenderOptions,
+) -> (Vec<KeyShape<'a>>, f64, f64) {
+    let unit = options.key_size;
+
+    // extents of the keys in units of a standard key
+    let extents: Vec<(f64, f64, f64, f64)> = keyboard
+        .keys
+        .iter()
+        .map(|k| {
+            (
+                k.position.0 + 0.5 - 0.5 * k.size.0,
+                k.position.1 + 0.5 - 0.5 * k.size.1,
+                k.size.0,
+                k.size.1,
+            )
+        })
+        .collect();
+    let min_x = extents.iter().map(|e| e.0).fold(f64::INFINITY, f64::min);
+    let min_y = extents.iter().map(|e| e.1).fold(f64::INFINITY, f64::min);
+    let max_x = extents
+        .iter()
+        .map(|e| e.0 + e.2)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let max_y = extents
+        .iter()
+        .map(|e| e.1 + e.3)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let shapes = keyboard
+        .keys
+        .iter()
+        .zip(extents.iter())
+        .enumerate()
+        .map(|(i, (key, (x, y, w, h)))| {
+            let keycap = keycaps.get(i).unwrap_or(&EMPTY_KEYCAP);
+            let x = MARGIN + (x - min_x) * unit + 0.5 * KEY_GAP;
+            let y = MARGIN + (y - min_y) * unit + 0.5 * KEY_GAP;
+            let width = w * unit - KEY_GAP;
+            let height = h * unit - KEY_GAP;
+
+            let fill = match keycap.fill {
+                Some(fill) => fill,
+                None if options.finger_colors => finger_color(&key.finger),
+                None if keycap.is_fixed => FIXED_FILL,
+                None => DEFAULT_FILL,
+            };
+            let color = if keycap.is_fixed { FIXED_TEXT } else { TEXT };
+
+            let mut texts: Vec<Text> = keycap
+                .labels
+                .iter()
+                .zip(LABEL_SLOTS.iter())
+                .take(options.layers.min(LABEL_SLOTS.len()))
+                .filter(|(label, _)| !label.is_empty())
+                .map(|(label, (rel_x, rel_y, rel_size))| Text {
+                    x: x + rel_x * width,
+                    y: y + rel_y * height,
+                    size: rel_size * unit,
+                    color,
+                    content: label,
+                })
+                .collect();
+            if let Some(note) = &keycap.note {
+                texts.push(Text {
+                    x: x + 0.5 * width,
+                    y: y + 0.9 * height,
+                    size: 0.2 * unit,
+                    color: NOTE_TEXT,
+                    content: note,
+                });
+            }
+
+            KeyShape {
+                x,
+                y,
+                width,
+                height,
+                fill,
+                is_fixed: keycap.is_fixed,
+                title: keycap.title.as_deref(),
+                texts,
+            }
+        })
+        .collect();
+
+    (
+        shapes,
+        (max_x - min_x) * unit + 2.0 * MARGIN,
+        (max_y - min_y) * unit + 2.0 * MARGIN,
+    )
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex(color: &Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Render a keyboard with the given keycaps (in the order of the keyboard's keys) as SVG.
+pub fn svg(keyboard: &Keyboard, keycaps: &[Keycap], options: &RenderOptions) -> String {
+    let (shapes, width, height) = key_shapes(keyboard, keycaps, options);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.0} {h:.0}\" font-family=\"sans-serif\" text-anchor=\"middle\">\n",
+        w = width,
+        h = height,
+    );
+    for shape in shapes.iter() {
+        svg.push_str("  <g>\n");
+        if let Some(title) = shape.title {
+            svg.push_str(&format!("    <title>{}</title>\n", escape_xml(title)));
+        }
+        svg.push_str(&format!(
+            "    <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"4\" fill=\"{}\" stroke=\"{}\"{}/>\n",
+            shape.x,
+            shape.y,
+            shape.width,
+            shape.height,
+            hex(&shape.fill),
+            hex(&BORDER),
+            if shape.is_fixed {
+                " stroke-dasharray=\"4 2\""
+            } else {
+                ""
+            },
+        ));
+        for text in shape.texts.iter() {
+            svg.push_str(&format!(
+                "    <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{:.1}\" fill=\"{}\">{}</text>\n",
+                text.x,
+                text.y,
+                text.size,
+                hex(&text.color),
+                escape_xml(text.content),
+            ));
+        }
+        svg.push_str("  </g>\n");
+    }
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+/// Render a keyboard with the given keycaps (in the order of the keyboard's keys) as PNG image.
+/// Labels are drawn with the given TrueType/OpenType font (without a font, only the keys are
+/// drawn).
+#[cfg(feature = "raster")]
+pub fn png(
+    keyboard: &Keyboard,
+    keycaps: &[Keycap],
+    options: &RenderOptions,
+    font: Option<&[u8]>,
+) -> anyhow::Result<Vec<u8>> {
+    use ab_glyph::{Font, FontRef, ScaleFont};
+
+    let (shapes, width, height) = key_shapes(keyboard, keycaps, options);
+    let font = font.map(FontRef::try_from_slice).transpose()?;
+    let mut canvas = Canvas::new(width.ceil() as usize, height.ceil() as usize);
+
+    for shape in shapes.iter() {
+        canvas.fill_rect(shape.x, shape.y, shape.width, shape.height, &shape.fill);
+        canvas.stroke_rect(shape.x, shape.y, shape.width, shape.height, &BORDER);
+
+        let font = match &font {
+            Some(font) => font,
+            None => continue,
+        };
+        for text in shape.texts.iter() {
+            let scaled = font.as_scaled(text.size as f32);
+            let glyphs: Vec<ab_glyph::Glyph> = text
+                .content
+                .chars()
+                .scan(0.0, |x, c| {
+                    let glyph = scaled.scaled_glyph(c);
+                    let advance = scaled.h_advance(glyph.id);
+                    let glyph = glyph
+                        .id
+                        .with_scale_and_position(text.size as f32, ab_glyph::point(*x, 0.0));
+                    *x += advance;
+                    Some(glyph)
+                })
+                .collect();
+            let text_width = glyphs
+                .last()
+                .map(|g| g.position.x + scaled.h_advance(g.id))
+                .unwrap_or(0.0);
+            let origin_x = text.x as f32 - 0.5 * text_width;
+            let origin_y = text.y as f32;
+
+            for glyph in glyphs {
+                if let Some(outlined) = font.outline_glyph(glyph) {
+                    let bounds = outlined.px_bounds();
+                    outlined.draw(|gx, gy, coverage| {
+                        canvas.blend(
+                            (origin_x + bounds.min.x) as isize + gx as isize,
+                            (origin_y + bounds.min.y) as isize + gy as isize,
+                            &text.color,
+                            coverage as f64,
+                        );
+                    });
+                }
+            }
+        }
+    }
+
+    canvas.encode()
+}
+
+/// An RGB pixel buffer with a white background.
+#[cfg(feature = "raster")]
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+#[cfg(feature = "raster")]
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![255; 3 * width * height],
+        }
+    }
+
+    /// Blend a color into a pixel with the given opacity (pixels outside the canvas are ignored).
+    fn blend(&mut self, x: isize, y: isize, color: &Rgb, alpha: f64) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let offset = 3 * (y as usize * self.width + x as usize);
+        let alpha = alpha.clamp(0.0, 1.0);
+        for (pixel, c) in self.pixels[offset..offset + 3].iter_mut().zip(color.iter()) {
+            *pixel = (alpha * *c as f64 + (1.0 - alpha) * *pixel as f64).round() as u8;
+        }
+    }
+
+    fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: &Rgb) {
+        for py in y.round() as isize..(y + height).round() as isize {
+            for px in x.round() as isize..(x + width).round() as isize {
+                self.blend(px, py, color, 1.0);
+            }
+        }
+    }
+
+    fn stroke_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: &Rgb) {
+        let (x0, y0) = (x.round() as isize, y.round() as isize);
+        let (x1, y1) = (
+            (x + width).round() as isize - 1,
+            (y + height).round() as isize - 1,
+        );
+        for px in x0..=x1 {
+            self.blend(px, y0, color, 1.0);
+            self.blend(px, y1, color, 1.0);
+        }
+        for py in y0..=y1 {
+            self.blend(x0, py, color, 1.0);
+            self.blend(x1, py, color, 1.0);
+        }
+    }
+
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, self.width as u32, self.height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.pixels)?;
+        }
+
+        Ok(data)
+    }
+}
@@ -55,9 +55,13 @@ pub fn evaluate_bench(c: &mut Criterion) {
         &format!("Could not load config file 'standard_keyboard.yml'",),
     );
 
-    let keyboard = Arc::new(Keyboard::from_yaml_object(layout_config.keyboard));
+    let keyboard = Arc::new(
+        Keyboard::from_yaml_object(layout_config.keyboard)
+            .expect("Invalid keyboard config 'standard_keyboard.yml'"),
+    );
 
-    let layout_generator = NeoLayoutGenerator::from_object(layout_config.base_layout, keyboard);
+    let layout_generator = NeoLayoutGenerator::from_object(layout_config.base_layout, keyboard)
+        .expect("Invalid layout config 'standard_keyboard.yml'");
 
     let eval_params = EvaluationParameters::from_yaml(&"../config/evaluation_parameters.yml")
         .expect(&format!(
@@ -86,10 +90,11 @@ pub fn evaluate_bench(c: &mut Criterion) {
     let ngram_mapper_config = eval_params.ngram_mapper.clone();
 
     let ngram_provider =
-        OnDemandNgramMapper::with_ngrams(&unigrams, &bigrams, &trigrams, ngram_mapper_config);
+        OnDemandNgramMapper::with_ngrams(unigrams, bigrams, trigrams, ngram_mapper_config);
 
-    let evaluator =
-        Evaluator::default(Box::new(ngram_provider)).default_metrics(&eval_params.metrics);
+    let evaluator = Evaluator::default(Box::new(ngram_provider))
+        .default_metrics(&eval_params.metrics)
+        .expect("Could not initialize metrics");
 
     let layout = match layout_generator.generate("jduaxphlmwqßctieobnrsgfvüäöyz,.k") {
         Ok(layout) => layout,
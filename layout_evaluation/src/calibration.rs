@@ -0,0 +1,241 @@
+//! The `calibration` module fits metric weights to reference data, e.g. a set of layouts with
+//! known target costs (from typing tests) or with a desired ranking.
+//!
+//! The calibration operates on the weighted (and normalized) costs of each metric as given in an
+//! `EvaluationResult` for the current weights. It determines a non-negative scaling factor for
+//! each metric such that the rescaled total costs match the targets (least squares) or the desired
+//! ranking (squared hinge loss on all pairs of layouts). A regularization term pulls the factors
+//! towards one, i.e. towards the current weights, which keeps the problem well-posed if there are
+//! fewer reference layouts than metrics.
+//!
+//! Note that metrics building upon the bigram metrics (e.g. `irregularity`) are treated as fixed
+//! features, i.e. their costs are not recomputed for the calibrated bigram weights.
+
+use crate::results::EvaluationResult;
+
+/// What the calibrated total costs shall reproduce.
+#[derive(Clone, Debug)]
+pub enum CalibrationTarget {
+    /// A target total cost for each layout (least squares fit).
+    Costs(Vec<f64>),
+    /// The layouts are ordered from best (lowest cost) to worst (ranking fit).
+    Ranking,
+}
+
+/// Parameters of the calibration.
+#[derive(Clone, Debug)]
+pub struct CalibrationParameters {
+    /// Strength of the penalty for deviating from the current weights (relative to the data loss).
+    pub regularization: f64,
+    /// For a ranking fit, a better layout shall be cheaper than a worse one at least by this
+    /// fraction of the mean total cost.
+    pub margin: f64,
+    /// Number of iterations of the projected gradient descent.
+    pub iterations: usize,
+}
+
+impl Default for CalibrationParameters {
+    fn default() -> Self {
+        Self {
+            regularization: 0.001,
+            margin: 0.01,
+            iterations: 20000,
+        }
+    }
+}
+
+/// Collect the weighted cost of each individual metric of an `EvaluationResult`
+/// (in the order in which they appear in the result).
+pub fn metric_costs(result: &EvaluationResult) -> Vec<f64> {
+    result
+        .iter()
+        .flat_map(|results| results.metric_costs.iter().map(|mc| mc.weighted_cost))
+        .collect()
+}
+
+/// Total cost of a layout with the given metric costs and scaling factors.
+pub fn total_cost(costs: &[f64], scales: &[f64]) -> f64 {
+    costs.iter().zip(scales.iter()).map(|(c, s)| c * s).sum()
+}
+
+/// Fraction of pairs of layouts whose total costs are in the order given by the target
+/// (the order of the target costs or, for a ranking, the order of the layouts).
+pub fn pairwise_agreement(total_costs: &[f64], target: &CalibrationTarget) -> f64 {
+    let pairs = ordered_pairs(total_costs.len(), target);
+    if pairs.is_empty() {
+        return 1.0;
+    }
+
+    let agreeing = pairs
+        .iter()
+        .filter(|(better, worse)| total_costs[*better] < total_costs[*worse])
+        .count();
+
+    agreeing as f64 / pairs.len() as f64
+}
+
+/// All pairs of layout indices `(better, worse)` as implied by the target.
+fn ordered_pairs(n: usize, target: &CalibrationTarget) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for i in 0..n {
+        for j in (i + 1)..n {
+            match target {
+                CalibrationTarget::Ranking => pairs.push((i, j)),
+                CalibrationTarget::Costs(costs) => {
+                    if costs[i] < costs[j] {
+                        pairs.push((i, j));
+                    } else if costs[j] < costs[i] {
+                        pairs.push((j, i));
+                    }
+                }
+            }
+        }
+    }
+
+    pairs
+}
+
+fn squared_norm(v: impl Iterator<Item = f64>) -> f64 {
+    v.map(|x| x * x).sum()
+}
+
+/// Computes the value and gradient (with respect to the scaling factors) of the data loss.
+struct Objective<'a> {
+    costs: &'a [Vec<f64>],
+    target: &'a CalibrationTarget,
+    pairs: Vec<(usize, usize)>,
+    /// Typical magnitude of the total costs (makes the loss independent of their scale)
+    scale: f64,
+    margin: f64,
+}
+
+impl<'a> Objective<'a> {
+    fn new(costs: &'a [Vec<f64>], target: &'a CalibrationTarget, margin: f64) -> Self {
+        let n = costs.len().max(1) as f64;
+        let scale = match target {
+            CalibrationTarget::Costs(t) => t.iter().map(|t| t.abs()).sum::<f64>() / n,
+            CalibrationTarget::Ranking => {
+                costs.iter().map(|c| c.iter().sum::<f64>()).sum::<f64>() / n
+            }
+        };
+        let scale = if scale > 0.0 { scale } else { 1.0 };
+
+        Self {
+            costs,
+            target,
+            pairs: ordered_pairs(costs.len(), target),
+            scale,
+            margin: margin * scale,
+        }
+    }
+
+    /// Upper bound for the Lipschitz constant of the loss' gradient.
+    fn lipschitz_bound(&self) -> f64 {
+        let bound = match self.target {
+            CalibrationTarget::Costs(_) => {
+                self.costs
+                    .iter()
+                    .map(|c| squared_norm(c.iter().cloned()))
+                    .sum::<f64>()
+                    / self.costs.len().max(1) as f64
+            }
+            CalibrationTarget::Ranking => {
+                self.pairs
+                    .iter()
+                    .map(|(a, b)| {
+                        let diff = self.costs[*a]
+                            .iter()
+                            .zip(self.costs[*b].iter())
+                            .map(|(ca, cb)| ca - cb);
+                        squared_norm(diff)
+                    })
+                    .sum::<f64>()
+                    / self.pairs.len().max(1) as f64
+            }
+        };
+
+        2.0 * bound / (self.scale * self.scale)
+    }
+
+    fn loss_and_gradient(&self, scales: &[f64]) -> (f64, Vec<f64>) {
+        let mut gradient = vec![0.0; scales.len()];
+        let mut loss = 0.0;
+        let norm = self.scale * self.scale;
+
+        match self.target {
+            CalibrationTarget::Costs(targets) => {
+                let n = self.costs.len().max(1) as f64;
+                for (c, t) in self.costs.iter().zip(targets.iter()) {
+                    let residual = total_cost(c, scales) - t;
+                    loss += residual * residual / (n * norm);
+                    gradient
+                        .iter_mut()
+                        .zip(c.iter())
+                        .for_each(|(g, ci)| *g += 2.0 * residual * ci / (n * norm));
+                }
+            }
+            CalibrationTarget::Ranking => {
+                let n = self.pairs.len().max(1) as f64;
+                for (a, b) in self.pairs.iter() {
+                    let violation = total_cost(&self.costs[*a], scales)
+                        - total_cost(&self.costs[*b], scales)
+                        + self.margin;
+                    if violation <= 0.0 {
+                        continue;
+                    }
+                    loss += violation * violation / (n * norm);
+                    gradient
+                        .iter_mut()
+                        .zip(self.costs[*a].iter().zip(self.costs[*b].iter()))
+                        .for_each(|(g, (ca, cb))| *g += 2.0 * violation * (ca - cb) / (n * norm));
+                }
+            }
+        }
+
+        (loss, gradient)
+    }
+}
+
+/// The result of a calibration.
+#[derive(Clone, Debug)]
+pub struct Calibration {
+    /// Factor for each metric that its current weight shall be multiplied with.
+    pub scales: Vec<f64>,
+    /// Data loss (without regularization) for the current weights.
+    pub initial_loss: f64,
+    /// Data loss (without regularization) for the calibrated weights.
+    pub final_loss: f64,
+}
+
+/// Fit scaling factors for the metrics' weights.
+///
+/// `costs` contains for each reference layout the weighted cost of each metric (see `metric_costs`).
+pub fn calibrate(
+    costs: &[Vec<f64>],
+    target: &CalibrationTarget,
+    params: &CalibrationParameters,
+) -> Calibration {
+    let n_metrics = costs.first().map(|c| c.len()).unwrap_or(0);
+    let objective = Objective::new(costs, target, params.margin);
+
+    let step = 1.0 / (objective.lipschitz_bound() + 2.0 * params.regularization).max(1e-12);
+    let mut scales = vec![1.0; n_metrics];
+    let (initial_loss, _) = objective.loss_and_gradient(&scales);
+
+    for _ in 0..params.iterations {
+        let (_, gradient) = objective.loss_and_gradient(&scales);
+        scales.iter_mut().zip(gradient.iter()).for_each(|(s, g)| {
+            let g = g + 2.0 * params.regularization * (*s - 1.0);
+            // project onto non-negative weights
+            *s = (*s - step * g).max(0.0);
+        });
+    }
+
+    let (final_loss, _) = objective.loss_and_gradient(&scales);
+
+    Calibration {
+        scales,
+        initial_loss,
+        final_loss,
+    }
+}
@@ -0,0 +1,90 @@
+//! The `clustering` module groups layouts into families based on their pairwise distances (see
+//! the `layout_distance` module).
+//!
+//! The clustering is agglomerative with average linkage: starting with each layout in its own
+//! cluster, the two clusters with the smallest average distance between their members are merged
+//! until this distance exceeds a threshold.
+
+use serde::Serialize;
+
+/// A group of layouts (in terms of their indices).
+#[derive(Clone, Debug, Serialize)]
+pub struct Cluster {
+    /// Indices of the member layouts.
+    pub members: Vec<usize>,
+    /// Index of the member with the smallest total distance to all other members.
+    pub medoid: usize,
+    /// Mean distance of the members to the medoid.
+    pub mean_distance: f64,
+}
+
+/// Cluster items given their pairwise distances. Clusters are merged as long as the average
+/// distance between their members does not exceed `threshold`. The clusters are sorted by
+/// decreasing size.
+pub fn cluster(distances: &[Vec<f64>], threshold: f64) -> Vec<Cluster> {
+    let n = distances.len();
+    let mut clusters: Vec<Option<Vec<usize>>> = (0..n).map(|i| Some(vec![i])).collect();
+    // average distances between clusters (updated with the Lance-Williams formula)
+    let mut linkage: Vec<Vec<f64>> = distances.to_vec();
+
+    loop {
+        let mut closest: Option<(usize, usize, f64)> = None;
+        for i in 0..n {
+            if clusters[i].is_none() {
+                continue;
+            }
+            for j in (i + 1)..n {
+                if clusters[j].is_none() {
+                    continue;
+                }
+                if closest.map(|(_, _, d)| linkage[i][j] < d).unwrap_or(true) {
+                    closest = Some((i, j, linkage[i][j]));
+                }
+            }
+        }
+
+        let (i, j) = match closest {
+            Some((i, j, d)) if d <= threshold => (i, j),
+            _ => break,
+        };
+
+        let members_j = clusters[j].take().unwrap();
+        let size_i = clusters[i].as_ref().unwrap().len() as f64;
+        let size_j = members_j.len() as f64;
+        for k in 0..n {
+            if k == i || clusters[k].is_none() {
+                continue;
+            }
+            let d = (size_i * linkage[i][k] + size_j * linkage[j][k]) / (size_i + size_j);
+            linkage[i][k] = d;
+            linkage[k][i] = d;
+        }
+        clusters[i].as_mut().unwrap().extend(members_j);
+    }
+
+    let mut clusters: Vec<Cluster> = clusters
+        .into_iter()
+        .flatten()
+        .map(|members| {
+            let (medoid, total_distance) = members
+                .iter()
+                .map(|m| (*m, members.iter().map(|o| distances[*m][*o]).sum::<f64>()))
+                .min_by(|(_, d1), (_, d2)| d1.partial_cmp(d2).unwrap())
+                .unwrap();
+            let mean_distance = if members.len() > 1 {
+                total_distance / (members.len() - 1) as f64
+            } else {
+                0.0
+            };
+
+            Cluster {
+                members,
+                medoid,
+                mean_distance,
+            }
+        })
+        .collect();
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.members.len()));
+
+    clusters
+}
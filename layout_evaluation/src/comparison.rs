@@ -0,0 +1,274 @@
+//! The `comparison` module provides a comparison of multiple layouts: which keys moved with
+//! respect to a reference layout (the first one), how the costs of the individual metrics differ,
+//! and which bigrams changed their costs the most.
+
+use crate::evaluation::Evaluator;
+use crate::load_report::printable_symbol;
+use crate::results::{EvaluationResult, MetricType};
+
+use keyboard_layout::key::MatrixPosition;
+use keyboard_layout::layout::Layout;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+
+/// A base-layer symbol that is located on a different key than in the reference layout.
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyChange {
+    pub symbol: char,
+    /// Matrix position of the symbol in the reference layout.
+    pub from: MatrixPosition,
+    /// Matrix position of the symbol in the compared layout.
+    pub to: MatrixPosition,
+}
+
+/// Weighted costs of a metric for all compared layouts.
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricComparison {
+    pub name: String,
+    pub metric_type: MetricType,
+    /// Weighted cost for each layout.
+    pub costs: Vec<f64>,
+}
+
+/// Contributions of a bigram to the total cost for all compared layouts.
+#[derive(Clone, Debug, Serialize)]
+pub struct BigramComparison {
+    /// Symbols of the bigram (modifiers appear as individual symbols).
+    pub bigram: String,
+    /// Contribution to the total cost for each layout.
+    pub costs: Vec<f64>,
+}
+
+impl BigramComparison {
+    /// Largest absolute difference of a layout's cost to the reference layout's cost.
+    fn max_abs_delta(&self) -> f64 {
+        self.costs
+            .iter()
+            .map(|c| (c - self.costs[0]).abs())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Comparison of multiple layouts with respect to the first (reference) layout.
+#[derive(Clone, Debug, Serialize)]
+pub struct LayoutComparison {
+    /// The compared layouts (as text).
+    pub layouts: Vec<String>,
+    /// Total cost of each layout.
+    pub total_costs: Vec<f64>,
+    /// Symbols that moved compared to the reference layout (for each layout).
+    pub moved_keys: Vec<Vec<KeyChange>>,
+    /// Plots of the layouts' base layers with moved symbols in brackets.
+    pub plots: Vec<String>,
+    /// Costs of all metrics.
+    pub metrics: Vec<MetricComparison>,
+    /// Bigrams whose costs changed the most (in any of the layouts).
+    pub bigrams: Vec<BigramComparison>,
+}
+
+/// Matrix positions of the base-layer symbols that appear on exactly one key (placeholders for
+/// unused keys may appear multiple times).
+fn unique_symbol_positions(layout: &Layout) -> FxHashMap<char, MatrixPosition> {
+    let mut positions: FxHashMap<char, Option<MatrixPosition>> = FxHashMap::default();
+    layout
+        .base_symbols()
+        .into_iter()
+        .zip(layout.keyboard.keys.iter())
+        .for_each(|(symbol, key)| {
+            if let Some(s) = symbol {
+                positions
+                    .entry(s)
+                    .and_modify(|p| *p = None)
+                    .or_insert(Some(key.matrix_position));
+            }
+        });
+
+    positions
+        .into_iter()
+        .filter_map(|(s, p)| p.map(|p| (s, p)))
+        .collect()
+}
+
+/// Symbols of the base layer that are located on different keys in the two layouts.
+pub fn moved_keys(reference: &Layout, layout: &Layout) -> Vec<KeyChange> {
+    let reference_positions = unique_symbol_positions(reference);
+    let positions = unique_symbol_positions(layout);
+
+    layout
+        .base_symbols()
+        .into_iter()
+        .zip(layout.keyboard.keys.iter())
+        .filter_map(|(symbol, key)| {
+            let symbol = symbol?;
+            positions.get(&symbol)?;
+            let from = *reference_positions.get(&symbol)?;
+            if from == key.matrix_position {
+                return None;
+            }
+
+            Some(KeyChange {
+                symbol,
+                from,
+                to: key.matrix_position,
+            })
+        })
+        .collect()
+}
+
+/// Plot the base layer of a layout with symbols that moved compared to the reference layout in
+/// brackets.
+pub fn plot_diff(reference: &Layout, layout: &Layout) -> String {
+    let moved: FxHashSet<char> = moved_keys(reference, layout)
+        .iter()
+        .map(|c| c.symbol)
+        .collect();
+    let labels: Vec<String> = layout
+        .base_symbols()
+        .into_iter()
+        .map(|symbol| match symbol {
+            Some(s) if moved.contains(&s) => format!("[{}]", printable_symbol(s)),
+            Some(s) => format!("{} ", printable_symbol(s)),
+            None => String::new(),
+        })
+        .collect();
+    let labels: Vec<&str> = labels.iter().map(|s| s.as_str()).collect();
+
+    layout.keyboard.plot_wide(&labels)
+}
+
+impl LayoutComparison {
+    /// Compare the layouts (at least one) with the first one. The `n_bigrams` bigrams whose
+    /// costs differ the most from the reference layout are included.
+    pub fn new(evaluator: &Evaluator, layouts: &[Layout], n_bigrams: usize) -> Self {
+        let reference = &layouts[0];
+        let results: Vec<EvaluationResult> = layouts
+            .iter()
+            .map(|l| evaluator.evaluate_layout(l))
+            .collect();
+
+        let mut metrics: Vec<MetricComparison> = results[0]
+            .iter()
+            .flat_map(|mr| {
+                mr.metric_costs.iter().map(move |mc| MetricComparison {
+                    name: mc.core.name.clone(),
+                    metric_type: mr.metric_type.clone(),
+                    costs: Vec::with_capacity(layouts.len()),
+                })
+            })
+            .collect();
+        for result in results.iter() {
+            result
+                .iter()
+                .flat_map(|mr| mr.metric_costs.iter())
+                .zip(metrics.iter_mut())
+                .for_each(|(mc, m)| m.costs.push(mc.weighted_cost));
+        }
+
+        let bigram_costs: Vec<FxHashMap<(char, char), f64>> =
+            layouts.iter().map(|l| evaluator.bigram_costs(l)).collect();
+        let all_bigrams: FxHashSet<(char, char)> = bigram_costs
+            .iter()
+            .flat_map(|costs| costs.keys().cloned())
+            .collect();
+        let mut bigrams: Vec<BigramComparison> = all_bigrams
+            .into_iter()
+            .map(|(s1, s2)| BigramComparison {
+                bigram: format!("{}{}", s1, s2),
+                costs: bigram_costs
+                    .iter()
+                    .map(|costs| *costs.get(&(s1, s2)).unwrap_or(&0.0))
+                    .collect(),
+            })
+            .collect();
+        bigrams.sort_by(|b1, b2| {
+            b2.max_abs_delta()
+                .partial_cmp(&b1.max_abs_delta())
+                .unwrap()
+                .then_with(|| b1.bigram.cmp(&b2.bigram))
+        });
+        bigrams.truncate(n_bigrams);
+
+        Self {
+            layouts: layouts.iter().map(|l| l.as_text()).collect(),
+            total_costs: results.iter().map(|r| r.total_cost()).collect(),
+            moved_keys: layouts.iter().map(|l| moved_keys(reference, l)).collect(),
+            plots: layouts.iter().map(|l| plot_diff(reference, l)).collect(),
+            metrics,
+            bigrams,
+        }
+    }
+}
+
+/// Format a cost and (except for the reference layout) its difference to the reference's cost.
+fn format_cost(costs: &[f64], i: usize) -> String {
+    if i == 0 {
+        format!("{:>9.4}", costs[i])
+    } else {
+        format!("{:>9.4} ({:>+8.4})", costs[i], costs[i] - costs[0])
+    }
+}
+
+impl std::fmt::Display for LayoutComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, layout) in self.layouts.iter().enumerate() {
+            if i == 0 {
+                writeln!(f, "Layout 1 (reference): {}", layout)?;
+            } else {
+                let moved: String = self.moved_keys[i]
+                    .iter()
+                    .map(|c| printable_symbol(c.symbol))
+                    .collect();
+                writeln!(
+                    f,
+                    "Layout {}: {} ({} moved symbols: {})",
+                    i + 1,
+                    layout,
+                    self.moved_keys[i].len(),
+                    moved
+                )?;
+            }
+            writeln!(f, "{}", self.plots[i])?;
+        }
+
+        let columns: Vec<String> = (0..self.layouts.len())
+            .map(|i| {
+                let header = format!("Layout {}", i + 1);
+                if i == 0 {
+                    format!("{:>9}", header)
+                } else {
+                    format!("{:>20}", header)
+                }
+            })
+            .collect();
+        writeln!(f, "{:<50} {}", "Metric", columns.join(" "))?;
+        for m in self.metrics.iter() {
+            let costs: Vec<String> = (0..m.costs.len())
+                .map(|i| format_cost(&m.costs, i))
+                .collect();
+            writeln!(
+                f,
+                "{:<50} {}",
+                format!("{} ({:?})", m.name, m.metric_type),
+                costs.join(" ")
+            )?;
+        }
+        let totals: Vec<String> = (0..self.total_costs.len())
+            .map(|i| format_cost(&self.total_costs, i))
+            .collect();
+        writeln!(f, "{:<50} {}", "Total cost", totals.join(" "))?;
+
+        if !self.bigrams.is_empty() {
+            writeln!(f, "\nBigrams with the largest cost differences:")?;
+            for b in self.bigrams.iter() {
+                let bigram: String = b.bigram.chars().map(printable_symbol).collect();
+                let costs: Vec<String> = (0..b.costs.len())
+                    .map(|i| format_cost(&b.costs, i))
+                    .collect();
+                writeln!(f, "{:<50} {}", bigram, costs.join(" "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
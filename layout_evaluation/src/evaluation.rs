@@ -7,6 +7,8 @@
 //! The ngram mapper is responsible for mapping char-based ngrams (as read from input data)
 //! to singles, pairs, and triplets of `LayerKey`s that can then be analysed by the individual metrics.
 
+use crate::load_report::LoadReport;
+use crate::metric_registry::{MetricConstructor, MetricRegistry, WeightedBigramMetric};
 use crate::results::{
     EvaluationResult, MetricResult, MetricResults, MetricType, NormalizationType,
 };
@@ -14,13 +16,19 @@ use crate::{metrics::*, ngram_mapper::NgramMapper};
 
 use keyboard_layout::layout::{LayerKey, Layout};
 
-use serde::Deserialize;
+use rustc_hash::FxHashMap;
+use serde::de::Error;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-/// A wrapper around individuals metric's parameters (`T`) specifying
-/// additional generic attributes. This mostly facilitates configuration of
+/// Configuration of an individual metric instance. This mostly facilitates configuration of
 /// metrics in a config file.
-#[derive(Clone, Deserialize, Debug)]
-pub struct WeightedParams<T> {
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct MetricConfig {
+    /// The name of the metric as registered in the `MetricRegistry`. Defaults to the name of the
+    /// configuration entry and is only required for multiple instances of the same metric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<String>,
     /// Wether the metric is to be evaluated.
     pub enabled: bool,
     /// The weight to use when aggregating all metrics.
@@ -28,64 +36,102 @@ pub struct WeightedParams<T> {
     /// The normalization strategy to use.
     pub normalization: NormalizationType,
     /// The metric's individual parameters.
-    pub params: T,
+    #[serde(default)]
+    pub params: serde_yaml::Value,
 }
 
-/// Compiles configuration parameters for all "default" metrics available.
-/// This is usually read from a config file.
-#[derive(Clone, Deserialize, Debug)]
-pub struct MetricParameters {
-    pub asymmetric_keys: WeightedParams<layout_metrics::asymmetric_keys::Parameters>,
-    pub shortcut_keys: WeightedParams<layout_metrics::shortcut_keys::Parameters>,
-
-    pub finger_balance: WeightedParams<unigram_metrics::finger_balance::Parameters>,
-    pub hand_disbalance: WeightedParams<unigram_metrics::hand_disbalance::Parameters>,
-    pub key_costs: WeightedParams<unigram_metrics::key_costs::Parameters>,
-
-    pub asymmetric_bigrams: WeightedParams<bigram_metrics::asymmetric_bigrams::Parameters>,
-    pub finger_repeats: WeightedParams<bigram_metrics::finger_repeats::Parameters>,
-    pub finger_repeats_lateral: WeightedParams<bigram_metrics::finger_repeats_lateral::Parameters>,
-    pub finger_repeats_top_bottom:
-        WeightedParams<bigram_metrics::finger_repeats_top_bottom::Parameters>,
-    pub line_changes: WeightedParams<bigram_metrics::line_changes::Parameters>,
-    pub manual_bigram_penalty: WeightedParams<bigram_metrics::manual_bigram_penalty::Parameters>,
-    pub movement_pattern: WeightedParams<bigram_metrics::movement_pattern::Parameters>,
-    pub no_handswitch_after_unbalancing_key:
-        WeightedParams<bigram_metrics::no_handswitch_after_unbalancing_key::Parameters>,
-    pub unbalancing_after_neighboring:
-        WeightedParams<bigram_metrics::unbalancing_after_neighboring::Parameters>,
-
-    pub irregularity: WeightedParams<trigram_metrics::irregularity::Parameters>,
-    pub no_handswitch_in_trigram:
-        WeightedParams<trigram_metrics::no_handswitch_in_trigram::Parameters>,
-    pub secondary_bigrams: WeightedParams<trigram_metrics::secondary_bigrams::Parameters>,
-    pub trigram_finger_repeats: WeightedParams<trigram_metrics::trigram_finger_repeats::Parameters>,
+/// Compiles configuration parameters for all metrics to evaluate as a list of named metric
+/// instances (in the order of their appearance). This is usually read from a config file.
+#[derive(Clone, Default, Debug)]
+pub struct MetricParameters(pub Vec<(String, MetricConfig)>);
+
+impl<'de> Deserialize<'de> for MetricParameters {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mapping = serde_yaml::Mapping::deserialize(deserializer)?;
+        mapping
+            .into_iter()
+            .map(|(key, value)| {
+                let name = key
+                    .as_str()
+                    .ok_or_else(|| D::Error::custom(format!("Invalid metric name: {:?}", key)))?
+                    .to_string();
+                let config = serde_yaml::from_value(value).map_err(|e| {
+                    D::Error::custom(format!("Invalid configuration of metric '{}': {}", name, e))
+                })?;
+                Ok((name, config))
+            })
+            .collect::<Result<Vec<(String, MetricConfig)>, D::Error>>()
+            .map(MetricParameters)
+    }
+}
+
+impl Serialize for MetricParameters {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, config) in self.0.iter() {
+            map.serialize_entry(name, config)?;
+        }
+        map.end()
+    }
 }
 
+impl MetricParameters {
+    /// Names of the enabled configuration entries together with the type of their metrics, in the
+    /// order in which their results appear in an `EvaluationResult`.
+    pub fn enabled_entries(
+        &self,
+        registry: &MetricRegistry,
+    ) -> anyhow::Result<Vec<(MetricType, &str)>> {
+        let mut entries = Vec::new();
+        for (name, config) in self.0.iter().filter(|(_, config)| config.enabled) {
+            let metric_type = match registry.get(config.metric.as_deref().unwrap_or(name))? {
+                MetricConstructor::Layout(_) => MetricType::Layout,
+                MetricConstructor::Unigram(_) => MetricType::Unigram,
+                MetricConstructor::Bigram(_) => MetricType::Bigram,
+                MetricConstructor::Trigram(_) => MetricType::Trigram,
+            };
+            entries.push((metric_type, name.as_str()));
+        }
+
+        let order = |t: &MetricType| match t {
+            MetricType::Layout => 0,
+            MetricType::Unigram => 1,
+            MetricType::Bigram => 2,
+            MetricType::Trigram => 3,
+        };
+        // stable sort keeps the configuration order within each type
+        entries.sort_by_key(|(t, _)| order(t));
+
+        Ok(entries)
+    }
+}
+
+/// The name of a metric to show in the results. Additional instances of a metric are
+/// distinguished by the name of their configuration entry.
+fn display_name(name: &str, label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("{} ({})", name, label),
+        None => name.to_string(),
+    }
+}
+
+/// A metric together with its weight, normalization, and (optional) label of its configuration entry.
+type MetricEntry<M> = (f64, NormalizationType, Box<M>, Option<String>);
+
 /// The `Evaluator` object is responsible for evaluating multiple metrics with respect to given ngram data.
 /// The metrics are handled as dynamically dispatched trait objects for the metric traits in the `metrics` module.
 #[derive(Clone, Debug)]
 pub struct Evaluator {
-    layout_metrics: Vec<(
-        f64,
-        NormalizationType,
-        Box<dyn layout_metrics::LayoutMetric>,
-    )>,
-    unigram_metrics: Vec<(
-        f64,
-        NormalizationType,
-        Box<dyn unigram_metrics::UnigramMetric>,
-    )>,
-    bigram_metrics: Vec<(
-        f64,
-        NormalizationType,
-        Box<dyn bigram_metrics::BigramMetric>,
-    )>,
-    trigram_metrics: Vec<(
-        f64,
-        NormalizationType,
-        Box<dyn trigram_metrics::TrigramMetric>,
-    )>,
+    layout_metrics: Vec<MetricEntry<dyn layout_metrics::LayoutMetric>>,
+    unigram_metrics: Vec<MetricEntry<dyn unigram_metrics::UnigramMetric>>,
+    bigram_metrics: Vec<MetricEntry<dyn bigram_metrics::BigramMetric>>,
+    trigram_metrics: Vec<MetricEntry<dyn trigram_metrics::TrigramMetric>>,
     ngram_mapper: Box<dyn NgramMapper>,
 }
 
@@ -101,180 +147,75 @@ impl Evaluator {
         }
     }
 
-    /// Add all "default" metrics to the evaluator.
-    pub fn default_metrics(mut self, params: &MetricParameters) -> Self {
-        // layout metrics
-        self.layout_metric(
-            Box::new(layout_metrics::asymmetric_keys::AsymmetricKeys::new(
-                &params.asymmetric_keys.params,
-            )),
-            params.asymmetric_keys.weight,
-            params.asymmetric_keys.normalization.clone(),
-            params.asymmetric_keys.enabled,
-        );
-        self.layout_metric(
-            Box::new(layout_metrics::shortcut_keys::ShortcutKeys::new(
-                &params.shortcut_keys.params,
-            )),
-            params.shortcut_keys.weight,
-            params.shortcut_keys.normalization.clone(),
-            params.shortcut_keys.enabled,
-        );
-
-        // unigram metrics
-        self.unigram_metric(
-            Box::new(unigram_metrics::finger_balance::FingerBalance::new(
-                &params.finger_balance.params,
-            )),
-            params.finger_balance.weight,
-            params.finger_balance.normalization.clone(),
-            params.finger_balance.enabled,
-        );
-        self.unigram_metric(
-            Box::new(unigram_metrics::hand_disbalance::HandDisbalance::new(
-                &params.hand_disbalance.params,
-            )),
-            params.hand_disbalance.weight,
-            params.hand_disbalance.normalization.clone(),
-            params.hand_disbalance.enabled,
-        );
-        self.unigram_metric(
-            Box::new(unigram_metrics::key_costs::KeyCost::new(
-                &params.key_costs.params,
-            )),
-            params.key_costs.weight,
-            params.key_costs.normalization.clone(),
-            params.key_costs.enabled,
-        );
+    /// Add all configured metrics to the evaluator using the "default" `MetricRegistry`
+    /// containing all metrics of this crate.
+    pub fn default_metrics(self, params: &MetricParameters) -> anyhow::Result<Self> {
+        self.metrics_from_registry(&MetricRegistry::default(), params)
+    }
 
-        // bigram metrics
-        self.bigram_metric(
-            Box::new(bigram_metrics::asymmetric_bigrams::AsymmetricBigrams::new(
-                &params.asymmetric_bigrams.params,
-            )),
-            params.asymmetric_bigrams.weight,
-            params.asymmetric_bigrams.normalization.clone(),
-            params.asymmetric_bigrams.enabled,
-        );
-        self.bigram_metric(
-            Box::new(bigram_metrics::finger_repeats::FingerRepeats::new(
-                &params.finger_repeats.params,
-            )),
-            params.finger_repeats.weight,
-            params.finger_repeats.normalization.clone(),
-            params.finger_repeats.enabled,
-        );
-        self.bigram_metric(
-            Box::new(
-                bigram_metrics::finger_repeats_lateral::FingerRepeatsLateral::new(
-                    &params.finger_repeats_lateral.params,
-                ),
-            ),
-            params.finger_repeats_lateral.weight,
-            params.finger_repeats_lateral.normalization.clone(),
-            params.finger_repeats_lateral.enabled,
-        );
-        self.bigram_metric(
-            Box::new(
-                bigram_metrics::finger_repeats_top_bottom::FingerRepeatsTopBottom::new(
-                    &params.finger_repeats_top_bottom.params,
-                ),
-            ),
-            params.finger_repeats_top_bottom.weight,
-            params.finger_repeats_top_bottom.normalization.clone(),
-            params.finger_repeats_top_bottom.enabled,
-        );
-        self.bigram_metric(
-            Box::new(bigram_metrics::line_changes::LineChanges::new(
-                &params.line_changes.params,
-            )),
-            params.line_changes.weight,
-            params.line_changes.normalization.clone(),
-            params.line_changes.enabled,
-        );
-        self.bigram_metric(
-            Box::new(
-                bigram_metrics::manual_bigram_penalty::ManualBigramPenalty::new(
-                    &params.manual_bigram_penalty.params,
-                ),
-            ),
-            params.manual_bigram_penalty.weight,
-            params.manual_bigram_penalty.normalization.clone(),
-            params.manual_bigram_penalty.enabled,
-        );
-        self.bigram_metric(
-            Box::new(bigram_metrics::movement_pattern::MovementPattern::new(
-                &params.movement_pattern.params,
-            )),
-            params.movement_pattern.weight,
-            params.movement_pattern.normalization.clone(),
-            params.movement_pattern.enabled,
-        );
-        self.bigram_metric(
-            Box::new(
-                bigram_metrics::no_handswitch_after_unbalancing_key::NoHandSwitchAfterUnbalancingKey::new(
-                    &params.no_handswitch_after_unbalancing_key.params,
-                ),
-            ),
-            params.no_handswitch_after_unbalancing_key.weight,
-            params
-                .no_handswitch_after_unbalancing_key
-                .normalization
-                .clone(),
-            params.no_handswitch_after_unbalancing_key.enabled,
-        );
-        self.bigram_metric(
-            Box::new(
-                bigram_metrics::unbalancing_after_neighboring::UnbalancingAfterNeighboring::new(
-                    &params.unbalancing_after_neighboring.params,
-                ),
-            ),
-            params.unbalancing_after_neighboring.weight,
-            params.unbalancing_after_neighboring.normalization.clone(),
-            params.unbalancing_after_neighboring.enabled,
-        );
+    /// Add all configured metrics to the evaluator using the given `MetricRegistry`.
+    pub fn metrics_from_registry(
+        mut self,
+        registry: &MetricRegistry,
+        params: &MetricParameters,
+    ) -> anyhow::Result<Self> {
+        // trigram metrics may build upon the bigram metrics, so they are constructed last
+        let mut trigram_metrics = Vec::new();
+
+        for (name, config) in params.0.iter() {
+            let metric_name = config.metric.as_deref().unwrap_or(name);
+            let label = if metric_name != name {
+                Some(name.to_string())
+            } else {
+                None
+            };
+            let weight = config.weight;
+            let normalization = config.normalization.clone();
+
+            // unknown metrics are reported even if disabled, but disabled metrics are not
+            // constructed (their parameters may refer to data that is not available)
+            let constructor = registry.get(metric_name)?;
+            if !config.enabled {
+                continue;
+            }
+
+            match constructor {
+                MetricConstructor::Layout(constructor) => {
+                    let metric = constructor(&config.params)?;
+                    self.layout_metrics
+                        .push((weight, normalization, metric, label));
+                }
+                MetricConstructor::Unigram(constructor) => {
+                    let metric = constructor(&config.params)?;
+                    self.unigram_metrics
+                        .push((weight, normalization, metric, label));
+                }
+                MetricConstructor::Bigram(constructor) => {
+                    let metric = constructor(&config.params)?;
+                    self.bigram_metrics
+                        .push((weight, normalization, metric, label));
+                }
+                MetricConstructor::Trigram(constructor) => {
+                    trigram_metrics.push((constructor, config, label));
+                }
+            }
+        }
 
-        // trigram_metrics
-        self.trigram_metric(
-            Box::new(trigram_metrics::irregularity::Irregularity::new(
-                self.bigram_metrics.clone(),
-                &params.irregularity.params,
-            )),
-            params.irregularity.weight,
-            params.irregularity.normalization.clone(),
-            params.irregularity.enabled,
-        );
-        self.trigram_metric(
-            Box::new(
-                trigram_metrics::no_handswitch_in_trigram::NoHandswitchInTrigram::new(
-                    &params.no_handswitch_in_trigram.params,
-                ),
-            ),
-            params.no_handswitch_in_trigram.weight,
-            params.no_handswitch_in_trigram.normalization.clone(),
-            params.no_handswitch_in_trigram.enabled,
-        );
-        self.trigram_metric(
-            Box::new(trigram_metrics::secondary_bigrams::SecondaryBigrams::new(
-                self.bigram_metrics.clone(),
-                &params.secondary_bigrams.params,
-            )),
-            params.secondary_bigrams.weight,
-            params.secondary_bigrams.normalization.clone(),
-            params.secondary_bigrams.enabled,
-        );
-        self.trigram_metric(
-            Box::new(
-                trigram_metrics::trigram_finger_repeats::TrigramFingerRepeats::new(
-                    &params.trigram_finger_repeats.params,
-                ),
-            ),
-            params.trigram_finger_repeats.weight,
-            params.trigram_finger_repeats.normalization.clone(),
-            params.trigram_finger_repeats.enabled,
-        );
+        let bigram_metrics: Vec<WeightedBigramMetric> = self
+            .bigram_metrics
+            .iter()
+            .map(|(weight, normalization, metric, _)| {
+                (*weight, normalization.clone(), metric.clone())
+            })
+            .collect();
+
+        for (constructor, config, label) in trigram_metrics {
+            let metric = constructor(&config.params, &bigram_metrics)?;
+            self.trigram_metrics
+                .push((config.weight, config.normalization.clone(), metric, label));
+        }
 
-        self
+        Ok(self)
     }
 
     /// Add a metric that operates only on the layout itself ("layout metric").
@@ -286,7 +227,8 @@ impl Evaluator {
         enabled: bool,
     ) {
         if enabled {
-            self.layout_metrics.push((weight, normalization, metric));
+            self.layout_metrics
+                .push((weight, normalization, metric, None));
         }
     }
 
@@ -299,7 +241,8 @@ impl Evaluator {
         enabled: bool,
     ) {
         if enabled {
-            self.unigram_metrics.push((weight, normalization, metric));
+            self.unigram_metrics
+                .push((weight, normalization, metric, None));
         }
     }
 
@@ -312,7 +255,8 @@ impl Evaluator {
         enabled: bool,
     ) {
         if enabled {
-            self.bigram_metrics.push((weight, normalization, metric));
+            self.bigram_metrics
+                .push((weight, normalization, metric, None));
         }
     }
 
@@ -325,7 +269,8 @@ impl Evaluator {
         enabled: bool,
     ) {
         if enabled {
-            self.trigram_metrics.push((weight, normalization, metric));
+            self.trigram_metrics
+                .push((weight, normalization, metric, None));
         }
     }
 
@@ -336,10 +281,10 @@ impl Evaluator {
         }
 
         let mut metric_costs: Vec<MetricResult> = Vec::new();
-        for (weight, normalization, metric) in self.layout_metrics.iter() {
+        for (weight, normalization, metric, label) in self.layout_metrics.iter() {
             let (cost, message) = metric.total_cost(layout);
             metric_costs.push(MetricResult {
-                name: metric.name().to_string(),
+                name: display_name(metric.name(), label),
                 cost,
                 weight: *weight,
                 normalization: normalization.clone(),
@@ -350,22 +295,61 @@ impl Evaluator {
         metric_costs
     }
 
+    /// Names of all metrics as shown in the evaluation results.
+    pub fn metric_names(&self) -> Vec<String> {
+        let layout_names = self
+            .layout_metrics
+            .iter()
+            .map(|(_, _, metric, label)| display_name(metric.name(), label));
+        let unigram_names = self
+            .unigram_metrics
+            .iter()
+            .map(|(_, _, metric, label)| display_name(metric.name(), label));
+        let bigram_names = self
+            .bigram_metrics
+            .iter()
+            .map(|(_, _, metric, label)| display_name(metric.name(), label));
+        let trigram_names = self
+            .trigram_metrics
+            .iter()
+            .map(|(_, _, metric, label)| display_name(metric.name(), label));
+
+        layout_names
+            .chain(unigram_names)
+            .chain(bigram_names)
+            .chain(trigram_names)
+            .collect()
+    }
+
+    /// Whether any unigram metric requires the unsplit unigrams.
+    fn requires_unsplit_unigrams(&self) -> bool {
+        self.unigram_metrics
+            .iter()
+            .any(|(_, _, metric, _)| metric.requires_unsplit_unigrams())
+    }
+
     /// Evaluate all unigram metrics for a layout.
     fn evaluate_unigram_metrics(
         &self,
         layout: &Layout,
         keys: &[(&LayerKey, f64)],
+        unsplit_keys: &[(&LayerKey, f64)],
     ) -> Vec<MetricResult> {
         if self.unigram_metrics.is_empty() {
             return Vec::new();
         }
 
         let total_weight = keys.iter().map(|(_, w)| w).sum();
+        let unsplit_total_weight = unsplit_keys.iter().map(|(_, w)| w).sum();
         let mut metric_costs: Vec<MetricResult> = Vec::new();
-        for (weight, normalization, metric) in self.unigram_metrics.iter() {
-            let (cost, message) = metric.total_cost(keys, Some(total_weight), layout);
+        for (weight, normalization, metric, label) in self.unigram_metrics.iter() {
+            let (cost, message) = if metric.requires_unsplit_unigrams() {
+                metric.total_cost(unsplit_keys, Some(unsplit_total_weight), layout)
+            } else {
+                metric.total_cost(keys, Some(total_weight), layout)
+            };
             metric_costs.push(MetricResult {
-                name: metric.name().to_string(),
+                name: display_name(metric.name(), label),
                 cost,
                 weight: *weight,
                 normalization: normalization.clone(),
@@ -388,10 +372,10 @@ impl Evaluator {
 
         let total_weight = keys.iter().map(|(_, w)| w).sum();
         let mut metric_costs: Vec<MetricResult> = Vec::new();
-        for (weight, normalization, metric) in self.bigram_metrics.iter() {
+        for (weight, normalization, metric, label) in self.bigram_metrics.iter() {
             let (cost, message) = metric.total_cost(keys, Some(total_weight), layout);
             metric_costs.push(MetricResult {
-                name: metric.name().to_string(),
+                name: display_name(metric.name(), label),
                 cost,
                 weight: *weight,
                 normalization: normalization.clone(),
@@ -414,10 +398,10 @@ impl Evaluator {
 
         let total_weight = keys.iter().map(|(_, w)| w).sum();
         let mut metric_costs: Vec<MetricResult> = Vec::new();
-        for (weight, normalization, metric) in self.trigram_metrics.iter() {
+        for (weight, normalization, metric, label) in self.trigram_metrics.iter() {
             let (cost, message) = metric.total_cost(keys, Some(total_weight), layout);
             metric_costs.push(MetricResult {
-                name: metric.name().to_string(),
+                name: display_name(metric.name(), label),
                 cost,
                 weight: *weight,
                 normalization: normalization.clone(),
@@ -428,9 +412,56 @@ impl Evaluator {
         metric_costs
     }
 
+    /// Compute the contribution of each bigram to the total cost (weighted and normalized, summed
+    /// over all bigram metrics). The bigrams are given in terms of the symbols of the mapped
+    /// bigrams, i.e. modifiers appear as individual symbols. Bigram metrics that do not provide
+    /// costs for individual bigrams are not included.
+    pub fn bigram_costs(&self, layout: &Layout) -> FxHashMap<(char, char), f64> {
+        let mapped_ngrams = self.ngram_mapper.mapped_ngrams(layout, false);
+        let bigram_results = MetricResults::new(
+            MetricType::Bigram,
+            mapped_ngrams.bigrams_found,
+            mapped_ngrams.bigrams_not_found,
+        );
+        let total_weight: f64 = mapped_ngrams.bigrams.iter().map(|(_, w)| w).sum();
+
+        let mut costs: FxHashMap<(char, char), f64> = FxHashMap::default();
+        for (weight, normalization, metric, _) in self.bigram_metrics.iter() {
+            for ((k1, k2), w) in mapped_ngrams.bigrams.iter() {
+                if let Some(cost) = metric.individual_cost(k1, k2, *w, total_weight, layout) {
+                    *costs.entry((k1.symbol, k2.symbol)).or_insert(0.0) +=
+                        bigram_results.normalize_value(weight * cost, normalization);
+                }
+            }
+        }
+
+        costs
+    }
+
+    /// Frequencies of the symbols that can be generated by the layout (as used for the unigram
+    /// metrics, but without splitting higher-layer symbols into base keys and modifiers).
+    pub fn symbol_frequencies(&self, layout: &Layout) -> FxHashMap<char, f64> {
+        let mapped_ngrams = self.ngram_mapper.mapped_ngrams(layout, true);
+        let mut frequencies: FxHashMap<char, f64> = FxHashMap::default();
+        mapped_ngrams
+            .unsplit_unigrams
+            .iter()
+            .for_each(|(k, w)| *frequencies.entry(k.symbol).or_insert(0.0) += w);
+
+        frequencies
+    }
+
+    /// Compute the load of each key, finger, and hand of a layout (including modifier presses).
+    pub fn load_report(&self, layout: &Layout) -> LoadReport {
+        let mapped_ngrams = self.ngram_mapper.mapped_ngrams(layout, false);
+        LoadReport::from_unigrams(&mapped_ngrams.unigrams, layout)
+    }
+
     /// Evaluate all metrics for a layout.
     pub fn evaluate_layout(&self, layout: &Layout) -> EvaluationResult {
-        let mapped_ngrams = self.ngram_mapper.mapped_ngrams(layout);
+        let mapped_ngrams = self
+            .ngram_mapper
+            .mapped_ngrams(layout, self.requires_unsplit_unigrams());
 
         // Layout metrics
         let metric_costs = self.evaluate_layout_metrics(layout);
@@ -440,7 +471,11 @@ impl Evaluator {
             .for_each(|mc| layout_costs.add_result(mc));
 
         // Unigram metrics
-        let metric_costs = self.evaluate_unigram_metrics(layout, &mapped_ngrams.unigrams);
+        let metric_costs = self.evaluate_unigram_metrics(
+            layout,
+            &mapped_ngrams.unigrams,
+            &mapped_ngrams.unsplit_unigrams,
+        );
         let mut unigram_costs = MetricResults::new(
             MetricType::Unigram,
             mapped_ngrams.unigrams_found,
@@ -0,0 +1,791 @@
+//! The `formula` module provides a small expression language that allows defining the cost of
+//! unigrams, bigrams, and trigrams directly in the configuration (see the "formula" metrics).
+//!
+//! A formula is an expression over attributes of the ngram's keys. The keys are referred to as
+//! `k1`, `k2`, and `k3` (`k` is a shorthand for `k1`), their attributes are accessed with a dot:
+//! - `hand`: `"left"` or `"right"`
+//! - `finger`: `"thumb"`, `"pointer"`, `"middle"`, `"ring"`, or `"pinky"`
+//! - `column`, `row`: matrix position of the key
+//! - `x`, `y`: (physical) position of the key
+//! - `cost`, `unbalancing`: the key's cost and unbalancing value as configured for the keyboard
+//! - `symbol`: the symbol (compare with strings of length one, e.g. `k1.symbol == "e"`)
+//! - `layer`: the layer of the symbol
+//! - `modifiers`: number of modifiers required for the symbol
+//!
+//! Additionally, `weight` and `total_weight` hold the ngram's weight and the total weight of all
+//! ngrams. Supported are numbers, strings (in double or single quotes), `true` and `false`,
+//! the operators `+ - * / % ^ == != < <= > >= && || !`, parentheses, and the functions
+//! `if(condition, then, else)`, `abs`, `sqrt`, `exp`, `ln`, `min`, and `max`.
+//!
+//! Formulas are type checked when they are parsed, so evaluating them can not fail.
+
+use keyboard_layout::key::{Finger, Hand};
+use keyboard_layout::layout::LayerKey;
+
+use anyhow::{anyhow, bail, Result};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Type {
+    Number,
+    Bool,
+    Text,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Attribute {
+    Hand,
+    Finger,
+    Column,
+    Row,
+    X,
+    Y,
+    Cost,
+    Unbalancing,
+    Symbol,
+    Layer,
+    Modifiers,
+}
+
+impl Attribute {
+    fn from_name(name: &str) -> Option<Self> {
+        let attribute = match name {
+            "hand" => Attribute::Hand,
+            "finger" => Attribute::Finger,
+            "column" => Attribute::Column,
+            "row" => Attribute::Row,
+            "x" => Attribute::X,
+            "y" => Attribute::Y,
+            "cost" => Attribute::Cost,
+            "unbalancing" => Attribute::Unbalancing,
+            "symbol" => Attribute::Symbol,
+            "layer" => Attribute::Layer,
+            "modifiers" => Attribute::Modifiers,
+            _ => return None,
+        };
+
+        Some(attribute)
+    }
+
+    fn value_type(&self) -> Type {
+        match self {
+            Attribute::Hand | Attribute::Finger | Attribute::Symbol => Type::Text,
+            _ => Type::Number,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Function {
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Min,
+    Max,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Number(f64),
+    Bool(bool),
+    Text(String),
+    Key(usize, Attribute),
+    Weight,
+    TotalWeight,
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    Function(Function, Vec<Expr>),
+}
+
+/// A text value that is either borrowed from the formula or a key's symbol.
+#[derive(Clone, Copy, Debug)]
+enum Text<'a> {
+    Str(&'a str),
+    Char(char),
+}
+
+impl PartialEq for Text<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Text::Str(s1), Text::Str(s2)) => s1 == s2,
+            (Text::Char(c1), Text::Char(c2)) => c1 == c2,
+            (Text::Str(s), Text::Char(c)) | (Text::Char(c), Text::Str(s)) => {
+                let mut chars = s.chars();
+                chars.next() == Some(*c) && chars.next().is_none()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Value<'a> {
+    Number(f64),
+    Bool(bool),
+    Text(Text<'a>),
+}
+
+struct Context<'a> {
+    keys: &'a [&'a LayerKey],
+    weight: f64,
+    total_weight: f64,
+}
+
+impl Expr {
+    fn eval<'a>(&'a self, ctx: &Context<'a>) -> Value<'a> {
+        match self {
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Text(s) => Value::Text(Text::Str(s)),
+            Expr::Key(idx, attribute) => key_attribute(ctx.keys[*idx], attribute),
+            Expr::Weight => Value::Number(ctx.weight),
+            Expr::TotalWeight => Value::Number(ctx.total_weight),
+            Expr::Neg(e) => Value::Number(-e.number(ctx)),
+            Expr::Not(e) => Value::Bool(!e.bool(ctx)),
+            Expr::Binary(op, e1, e2) => match op {
+                BinaryOp::Add => Value::Number(e1.number(ctx) + e2.number(ctx)),
+                BinaryOp::Sub => Value::Number(e1.number(ctx) - e2.number(ctx)),
+                BinaryOp::Mul => Value::Number(e1.number(ctx) * e2.number(ctx)),
+                BinaryOp::Div => Value::Number(e1.number(ctx) / e2.number(ctx)),
+                BinaryOp::Rem => Value::Number(e1.number(ctx) % e2.number(ctx)),
+                BinaryOp::Pow => Value::Number(e1.number(ctx).powf(e2.number(ctx))),
+                BinaryOp::Eq => Value::Bool(e1.eval(ctx) == e2.eval(ctx)),
+                BinaryOp::Ne => Value::Bool(e1.eval(ctx) != e2.eval(ctx)),
+                BinaryOp::Lt => Value::Bool(e1.number(ctx) < e2.number(ctx)),
+                BinaryOp::Le => Value::Bool(e1.number(ctx) <= e2.number(ctx)),
+                BinaryOp::Gt => Value::Bool(e1.number(ctx) > e2.number(ctx)),
+                BinaryOp::Ge => Value::Bool(e1.number(ctx) >= e2.number(ctx)),
+                BinaryOp::And => Value::Bool(e1.bool(ctx) && e2.bool(ctx)),
+                BinaryOp::Or => Value::Bool(e1.bool(ctx) || e2.bool(ctx)),
+            },
+            Expr::If(condition, e1, e2) => {
+                if condition.bool(ctx) {
+                    e1.eval(ctx)
+                } else {
+                    e2.eval(ctx)
+                }
+            }
+            Expr::Function(function, args) => {
+                let mut values = args.iter().map(|e| e.number(ctx));
+                let first = values.next().unwrap_or(f64::NAN);
+                Value::Number(match function {
+                    Function::Abs => first.abs(),
+                    Function::Sqrt => first.sqrt(),
+                    Function::Exp => first.exp(),
+                    Function::Ln => first.ln(),
+                    Function::Min => values.fold(first, f64::min),
+                    Function::Max => values.fold(first, f64::max),
+                })
+            }
+        }
+    }
+
+    fn number(&self, ctx: &Context) -> f64 {
+        match self.eval(ctx) {
+            Value::Number(n) => n,
+            v => unreachable!("Formula is type checked, expected a number, got {:?}", v),
+        }
+    }
+
+    fn bool(&self, ctx: &Context) -> bool {
+        match self.eval(ctx) {
+            Value::Bool(b) => b,
+            v => unreachable!("Formula is type checked, expected a boolean, got {:?}", v),
+        }
+    }
+}
+
+fn key_attribute<'a>(k: &LayerKey, attribute: &Attribute) -> Value<'a> {
+    match attribute {
+        Attribute::Hand => Value::Text(Text::Str(match k.key.hand {
+            Hand::Left => "left",
+            Hand::Right => "right",
+        })),
+        Attribute::Finger => Value::Text(Text::Str(match k.key.finger {
+            Finger::Thumb => "thumb",
+            Finger::Pointer => "pointer",
+            Finger::Middle => "middle",
+            Finger::Ring => "ring",
+            Finger::Pinky => "pinky",
+        })),
+        Attribute::Column => Value::Number(k.key.matrix_position.0 as f64),
+        Attribute::Row => Value::Number(k.key.matrix_position.1 as f64),
+        Attribute::X => Value::Number(k.key.position.0),
+        Attribute::Y => Value::Number(k.key.position.1),
+        Attribute::Cost => Value::Number(k.key.cost),
+        Attribute::Unbalancing => Value::Number(k.key.unbalancing),
+        Attribute::Symbol => Value::Text(Text::Char(k.symbol)),
+        Attribute::Layer => Value::Number(k.layer as f64),
+        Attribute::Modifiers => Value::Number(k.modifiers.len() as f64),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+    Dot,
+}
+
+const OPERATORS: [&str; 15] = [
+    "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "^", "<", ">", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let number = number
+                .parse()
+                .map_err(|_| anyhow!("Invalid number '{}'", number))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '"' || c == '\'' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            if i == chars.len() {
+                bail!("Unterminated string");
+            }
+            tokens.push(Token::Text(chars[start..i].iter().collect()));
+            i += 1;
+        } else {
+            let token = match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                '.' => Token::Dot,
+                _ => {
+                    let rest: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+                    let op = OPERATORS
+                        .iter()
+                        .find(|op| rest.starts_with(*op))
+                        .ok_or_else(|| anyhow!("Unexpected character '{}'", c))?;
+                    i += op.chars().count() - 1;
+                    Token::Op(op)
+                }
+            };
+            tokens.push(token);
+            i += 1;
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn expect_type(found: Type, expected: Type, context: &str) -> Result<()> {
+    if found != expected {
+        bail!("Expected {:?} for {}, found {:?}", expected, context, found);
+    }
+
+    Ok(())
+}
+
+/// A recursive descent parser that resolves and type checks the expression while parsing.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    n_keys: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn accept_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<()> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            Some(t) => bail!("Expected {:?}, found {:?}", token, t),
+            None => bail!("Expected {:?}, found end of formula", token),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<(Expr, Type)> {
+        let (mut expr, mut t) = self.parse_and()?;
+        while self.accept_op(&["||"]).is_some() {
+            let (rhs, rhs_t) = self.parse_and()?;
+            expect_type(t, Type::Bool, "'||'")?;
+            expect_type(rhs_t, Type::Bool, "'||'")?;
+            expr = Expr::Binary(BinaryOp::Or, Box::new(expr), Box::new(rhs));
+            t = Type::Bool;
+        }
+
+        Ok((expr, t))
+    }
+
+    fn parse_and(&mut self) -> Result<(Expr, Type)> {
+        let (mut expr, mut t) = self.parse_comparison()?;
+        while self.accept_op(&["&&"]).is_some() {
+            let (rhs, rhs_t) = self.parse_comparison()?;
+            expect_type(t, Type::Bool, "'&&'")?;
+            expect_type(rhs_t, Type::Bool, "'&&'")?;
+            expr = Expr::Binary(BinaryOp::And, Box::new(expr), Box::new(rhs));
+            t = Type::Bool;
+        }
+
+        Ok((expr, t))
+    }
+
+    fn parse_comparison(&mut self) -> Result<(Expr, Type)> {
+        let (expr, t) = self.parse_sum()?;
+        let op = match self.accept_op(&["==", "!=", "<", "<=", ">", ">="]) {
+            Some(op) => op,
+            None => return Ok((expr, t)),
+        };
+        let (rhs, rhs_t) = self.parse_sum()?;
+
+        let context = format!("'{}'", op);
+        let op = match op {
+            "==" | "!=" => {
+                expect_type(rhs_t, t, &context)?;
+                if op == "==" {
+                    BinaryOp::Eq
+                } else {
+                    BinaryOp::Ne
+                }
+            }
+            _ => {
+                expect_type(t, Type::Number, &context)?;
+                expect_type(rhs_t, Type::Number, &context)?;
+                match op {
+                    "<" => BinaryOp::Lt,
+                    "<=" => BinaryOp::Le,
+                    ">" => BinaryOp::Gt,
+                    _ => BinaryOp::Ge,
+                }
+            }
+        };
+
+        Ok((Expr::Binary(op, Box::new(expr), Box::new(rhs)), Type::Bool))
+    }
+
+    fn parse_sum(&mut self) -> Result<(Expr, Type)> {
+        let (mut expr, t) = self.parse_product()?;
+        while let Some(op) = self.accept_op(&["+", "-"]) {
+            let (rhs, rhs_t) = self.parse_product()?;
+            let context = format!("'{}'", op);
+            expect_type(t, Type::Number, &context)?;
+            expect_type(rhs_t, Type::Number, &context)?;
+            let op = if op == "+" {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok((expr, t))
+    }
+
+    fn parse_product(&mut self) -> Result<(Expr, Type)> {
+        let (mut expr, t) = self.parse_unary()?;
+        while let Some(op) = self.accept_op(&["*", "/", "%"]) {
+            let (rhs, rhs_t) = self.parse_unary()?;
+            let context = format!("'{}'", op);
+            expect_type(t, Type::Number, &context)?;
+            expect_type(rhs_t, Type::Number, &context)?;
+            let op = match op {
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok((expr, t))
+    }
+
+    fn parse_unary(&mut self) -> Result<(Expr, Type)> {
+        match self.accept_op(&["-", "!"]) {
+            Some("-") => {
+                let (expr, t) = self.parse_unary()?;
+                expect_type(t, Type::Number, "'-'")?;
+                Ok((Expr::Neg(Box::new(expr)), Type::Number))
+            }
+            Some(_) => {
+                let (expr, t) = self.parse_unary()?;
+                expect_type(t, Type::Bool, "'!'")?;
+                Ok((Expr::Not(Box::new(expr)), Type::Bool))
+            }
+            None => self.parse_power(),
+        }
+    }
+
+    fn parse_power(&mut self) -> Result<(Expr, Type)> {
+        let (expr, t) = self.parse_primary()?;
+        if self.accept_op(&["^"]).is_none() {
+            return Ok((expr, t));
+        }
+
+        // right-associative, binds stronger than unary operators on its left
+        let (rhs, rhs_t) = self.parse_unary()?;
+        expect_type(t, Type::Number, "'^'")?;
+        expect_type(rhs_t, Type::Number, "'^'")?;
+
+        Ok((
+            Expr::Binary(BinaryOp::Pow, Box::new(expr), Box::new(rhs)),
+            Type::Number,
+        ))
+    }
+
+    fn parse_primary(&mut self) -> Result<(Expr, Type)> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok((Expr::Number(n), Type::Number)),
+            Some(Token::Text(s)) => Ok((Expr::Text(s), Type::Text)),
+            Some(Token::LParen) => {
+                let res = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(res)
+            }
+            Some(Token::Ident(name)) => match self.peek() {
+                Some(Token::LParen) => {
+                    self.pos += 1;
+                    self.parse_function(&name)
+                }
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    self.parse_key_attribute(&name)
+                }
+                _ => match name.as_str() {
+                    "true" => Ok((Expr::Bool(true), Type::Bool)),
+                    "false" => Ok((Expr::Bool(false), Type::Bool)),
+                    "weight" => Ok((Expr::Weight, Type::Number)),
+                    "total_weight" => Ok((Expr::TotalWeight, Type::Number)),
+                    _ => bail!("Unknown variable '{}'", name),
+                },
+            },
+            Some(t) => bail!("Unexpected {:?}", t),
+            None => bail!("Unexpected end of formula"),
+        }
+    }
+
+    fn parse_key_attribute(&mut self, key_name: &str) -> Result<(Expr, Type)> {
+        let idx = match key_name {
+            "k" => 1,
+            _ => key_name
+                .strip_prefix('k')
+                .and_then(|n| n.parse::<usize>().ok())
+                .ok_or_else(|| anyhow!("Unknown key '{}'", key_name))?,
+        };
+        if idx < 1 || idx > self.n_keys {
+            bail!(
+                "Unknown key '{}' (available: k1 to k{})",
+                key_name,
+                self.n_keys
+            );
+        }
+
+        let attribute = match self.next() {
+            Some(Token::Ident(name)) => Attribute::from_name(&name)
+                .ok_or_else(|| anyhow!("Unknown key attribute '{}'", name))?,
+            t => bail!(
+                "Expected key attribute after '{}.', found {:?}",
+                key_name,
+                t
+            ),
+        };
+
+        Ok((Expr::Key(idx - 1, attribute), attribute.value_type()))
+    }
+
+    fn parse_function(&mut self, name: &str) -> Result<(Expr, Type)> {
+        let mut args = Vec::new();
+        if self.peek() != Some(&Token::RParen) {
+            loop {
+                args.push(self.parse_or()?);
+                if self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(Token::RParen)?;
+
+        let context = format!("argument of '{}'", name);
+        if name == "if" {
+            if args.len() != 3 {
+                bail!("Function 'if' requires three arguments");
+            }
+            let mut args = args.into_iter();
+            let (condition, condition_t) = args.next().unwrap();
+            let (e1, t1) = args.next().unwrap();
+            let (e2, t2) = args.next().unwrap();
+            expect_type(condition_t, Type::Bool, &context)?;
+            expect_type(t2, t1, &context)?;
+
+            return Ok((
+                Expr::If(Box::new(condition), Box::new(e1), Box::new(e2)),
+                t1,
+            ));
+        }
+
+        let (function, variadic) = match name {
+            "abs" => (Function::Abs, false),
+            "sqrt" => (Function::Sqrt, false),
+            "exp" => (Function::Exp, false),
+            "ln" => (Function::Ln, false),
+            "min" => (Function::Min, true),
+            "max" => (Function::Max, true),
+            _ => bail!("Unknown function '{}'", name),
+        };
+        if args.is_empty() || (!variadic && args.len() != 1) {
+            bail!("Wrong number of arguments for function '{}'", name);
+        }
+
+        let args = args
+            .into_iter()
+            .map(|(e, t)| expect_type(t, Type::Number, &context).map(|_| e))
+            .collect::<Result<Vec<Expr>>>()?;
+
+        Ok((Expr::Function(function, args), Type::Number))
+    }
+}
+
+/// A parsed and type checked formula computing a number from the keys of an ngram.
+#[derive(Clone, Debug)]
+pub struct Formula {
+    source: String,
+    n_keys: usize,
+    expr: Expr,
+}
+
+impl Formula {
+    /// Parse a formula for ngrams consisting of `n_keys` keys.
+    pub fn parse(source: &str, n_keys: usize) -> Result<Self> {
+        let parse = || -> Result<Expr> {
+            let mut parser = Parser {
+                tokens: tokenize(source)?,
+                pos: 0,
+                n_keys,
+            };
+            let (expr, t) = parser.parse_or()?;
+            if let Some(token) = parser.peek() {
+                bail!("Unexpected {:?}", token);
+            }
+            expect_type(t, Type::Number, "the result")?;
+
+            Ok(expr)
+        };
+
+        let expr = parse().map_err(|e| anyhow!("Invalid formula '{}': {}", source, e))?;
+
+        Ok(Self {
+            source: source.to_string(),
+            n_keys,
+            expr,
+        })
+    }
+
+    /// The formula's source text.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluate the formula for the given keys (whose number must match the one used for parsing).
+    #[inline(always)]
+    pub fn eval(&self, keys: &[&LayerKey], weight: f64, total_weight: f64) -> f64 {
+        debug_assert_eq!(keys.len(), self.n_keys);
+        let ctx = Context {
+            keys,
+            weight,
+            total_weight,
+        };
+
+        self.expr.number(&ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keyboard_layout::key::{Key, MatrixPosition, Position, Size};
+
+    fn layerkey(hand: Hand, finger: Finger, symbol: char, layer: usize) -> LayerKey {
+        let key = Key {
+            hand,
+            finger,
+            matrix_position: MatrixPosition(3, 2),
+            position: Position(3.5, 2.0),
+            size: Size::default(),
+            symmetry_index: 0,
+            cost: 7.0,
+            unbalancing: 0.5,
+        };
+        LayerKey::new(layer, key, symbol, vec![0, 1], false, false, 0)
+    }
+
+    fn eval_keys(source: &str, keys: &[&LayerKey]) -> f64 {
+        Formula::parse(source, keys.len())
+            .unwrap()
+            .eval(keys, 2.0, 10.0)
+    }
+
+    fn eval(source: &str) -> f64 {
+        eval_keys(source, &[])
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("2 * 3 ^ 2"), 18.0);
+        assert_eq!(eval("-2 ^ 2"), -4.0);
+        assert_eq!(eval("2 ^ -1"), 0.5);
+        assert_eq!(eval("7 % 4 * 2"), 6.0);
+        assert_eq!(eval("if(1 + 1 == 2 && !false || false, 1, 0)"), 1.0);
+        assert_eq!(eval("if(true || false && false, 1, 0)"), 1.0);
+        assert_eq!(eval("if((1 < 2) == true, 1, 0)"), 1.0);
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(eval("10 - 4 - 3"), 3.0);
+        assert_eq!(eval("16 / 4 / 2"), 2.0);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("abs(-3) + sqrt(16) + ln(exp(2))"), 9.0);
+        assert_eq!(eval("min(3, 1, 2) + max(3, 1, 2)"), 4.0);
+        assert_eq!(eval("if(if(2 > 1, 'yes', 'no') == \"yes\", 1, 0)"), 1.0);
+    }
+
+    #[test]
+    fn key_attributes() {
+        let k1 = layerkey(Hand::Left, Finger::Ring, 'e', 0);
+        let k2 = layerkey(Hand::Right, Finger::Thumb, 'ß', 2);
+        let keys = [&k1, &k2];
+        let check = |condition: &str| eval_keys(&format!("if({}, 1, 0)", condition), &keys);
+
+        assert_eq!(check("k.hand == 'left' && k2.hand == 'right'"), 1.0);
+        assert_eq!(check("k1.finger == 'ring' && k2.finger == 'thumb'"), 1.0);
+        assert_eq!(check("k1.column == 3 && k1.row == 2"), 1.0);
+        assert_eq!(check("k1.x == 3.5 && k1.y == 2"), 1.0);
+        assert_eq!(check("k1.cost == 7 && k1.unbalancing == 0.5"), 1.0);
+        assert_eq!(check("k1.symbol == 'e' && k2.symbol == 'ß'"), 1.0);
+        assert_eq!(check("k1.symbol != 'ee' && k1.symbol != ''"), 1.0);
+        assert_eq!(check("k1.symbol != k2.symbol && k1.hand != k2.hand"), 1.0);
+        assert_eq!(check("k1.layer == 0 && k2.layer == 2"), 1.0);
+        assert_eq!(check("k2.modifiers == 2"), 1.0);
+        assert_eq!(eval_keys("weight / total_weight", &keys), 0.2);
+    }
+
+    #[test]
+    fn key_beyond_arity() {
+        assert!(Formula::parse("k2.cost", 1).is_err());
+        assert!(Formula::parse("k3.cost", 2).is_err());
+        assert!(Formula::parse("k0.cost", 2).is_err());
+        assert!(Formula::parse("k3.cost", 3).is_ok());
+    }
+
+    #[test]
+    fn type_errors() {
+        for source in [
+            "k1.hand",
+            "k1.hand + 1",
+            "k1.cost == 'left'",
+            "true + 1",
+            "!1",
+            "-true",
+            "1 && true",
+            "k1.hand < 'right'",
+            "if(1, 2, 3)",
+            "if(true, 1, 'a')",
+            "if(true, 'a', 'b')",
+            "abs(true)",
+            "min(1, 'a')",
+            "1 == 1",
+        ] {
+            assert!(Formula::parse(source, 1).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for source in [
+            "",
+            "1 +",
+            "(1",
+            "1)",
+            "1 2",
+            "1 < 2 < 3",
+            "if(1 < 2 == true, 1, 0)",
+            "'open",
+            "1.2.3",
+            "1 # 2",
+            "k1.",
+            "k1",
+            "k1.color",
+            "key.cost",
+            "foo",
+            "foo(1)",
+            "abs()",
+            "abs(1, 2)",
+            "if(true, 1)",
+        ] {
+            assert!(Formula::parse(source, 1).is_err(), "{}", source);
+        }
+    }
+}
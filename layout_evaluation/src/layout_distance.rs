@@ -0,0 +1,153 @@
+//! The `layout_distance` module provides distance measures between layouts of the same keyboard.
+//!
+//! The unweighted measures count the symbols of the (reference layout's) base layer that are
+//! located on a different key or that are typed by a different finger. The weighted measures
+//! consider the symbols of all layers and sum up their (relative) frequencies, so the distance is
+//! the fraction of typed symbols that are affected by the differences.
+
+use keyboard_layout::key::{Finger, Hand, MatrixPosition};
+use keyboard_layout::layout::Layout;
+
+use anyhow::{anyhow, Result};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// The available distance measures.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMeasure {
+    /// Number of base-layer symbols on different keys.
+    KeyDisplacement,
+    /// Number of base-layer symbols typed by a different finger.
+    FingerAssignment,
+    /// Frequency share of the symbols on different keys.
+    WeightedKeyDisplacement,
+    /// Frequency share of the symbols typed by a different finger.
+    WeightedFingerAssignment,
+}
+
+impl std::str::FromStr for DistanceMeasure {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "key_displacement" => Ok(Self::KeyDisplacement),
+            "finger_assignment" => Ok(Self::FingerAssignment),
+            "weighted_key_displacement" => Ok(Self::WeightedKeyDisplacement),
+            "weighted_finger_assignment" => Ok(Self::WeightedFingerAssignment),
+            _ => Err(anyhow!(
+                "Unknown distance measure '{}' (expected key_displacement, finger_assignment, \
+                 weighted_key_displacement, or weighted_finger_assignment)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SymbolLocation {
+    layer: usize,
+    position: MatrixPosition,
+    hand: Hand,
+    finger: Finger,
+}
+
+/// The locations of a layout's symbols as required for computing distances.
+#[derive(Clone, Debug)]
+pub struct LayoutSignature {
+    locations: FxHashMap<char, SymbolLocation>,
+}
+
+impl LayoutSignature {
+    /// Collect the locations of all symbols that appear on exactly one key of the layout
+    /// (symbols on multiple keys, e.g. placeholders for unused keys, are ignored).
+    pub fn new(layout: &Layout) -> Self {
+        let mut locations: FxHashMap<char, Option<SymbolLocation>> = FxHashMap::default();
+        layout
+            .layerkeys
+            .iter()
+            .filter(|k| k.combo_keys.is_empty())
+            .for_each(|k| {
+                let location = SymbolLocation {
+                    layer: k.layer,
+                    position: k.key.matrix_position,
+                    hand: k.key.hand,
+                    finger: k.key.finger,
+                };
+                locations
+                    .entry(k.symbol)
+                    .and_modify(|l| *l = None)
+                    .or_insert(Some(location));
+            });
+
+        Self {
+            locations: locations
+                .into_iter()
+                .filter_map(|(s, l)| l.map(|l| (s, l)))
+                .collect(),
+        }
+    }
+}
+
+/// Computes distances between layouts with a given measure.
+#[derive(Clone, Debug)]
+pub struct LayoutDistance {
+    measure: DistanceMeasure,
+    frequencies: FxHashMap<char, f64>,
+}
+
+impl LayoutDistance {
+    /// Generate a `LayoutDistance` for a measure. The symbol frequencies are only used by the
+    /// weighted measures (they do not need to be normalized).
+    pub fn new(measure: DistanceMeasure, frequencies: &FxHashMap<char, f64>) -> Self {
+        let total: f64 = frequencies.values().sum();
+        let total = if total > 0.0 { total } else { 1.0 };
+
+        Self {
+            measure,
+            frequencies: frequencies.iter().map(|(c, w)| (*c, w / total)).collect(),
+        }
+    }
+
+    /// Distance between two layouts (given by their signatures).
+    pub fn distance(&self, a: &LayoutSignature, b: &LayoutSignature) -> f64 {
+        let pairs = a
+            .locations
+            .iter()
+            .filter_map(|(c, la)| b.locations.get(c).map(|lb| (c, la, lb)));
+
+        match self.measure {
+            DistanceMeasure::KeyDisplacement => pairs
+                .filter(|(_, la, lb)| la.layer == 0 && la.position != lb.position)
+                .count() as f64,
+            DistanceMeasure::FingerAssignment => pairs
+                .filter(|(_, la, lb)| {
+                    la.layer == 0 && (la.hand != lb.hand || la.finger != lb.finger)
+                })
+                .count() as f64,
+            DistanceMeasure::WeightedKeyDisplacement => pairs
+                .filter(|(_, la, lb)| la.position != lb.position)
+                .map(|(c, _, _)| self.frequencies.get(c).unwrap_or(&0.0))
+                .sum(),
+            DistanceMeasure::WeightedFingerAssignment => pairs
+                .filter(|(_, la, lb)| la.hand != lb.hand || la.finger != lb.finger)
+                .map(|(c, _, _)| self.frequencies.get(c).unwrap_or(&0.0))
+                .sum(),
+        }
+    }
+
+    /// Matrix of the pairwise distances between the layouts.
+    pub fn distance_matrix(&self, signatures: &[LayoutSignature]) -> Vec<Vec<f64>> {
+        let n = signatures.len();
+        let mut distances = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let d = self.distance(&signatures[i], &signatures[j]);
+                distances[i][j] = d;
+                distances[j][i] = d;
+            }
+        }
+
+        distances
+    }
+}
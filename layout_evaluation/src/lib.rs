@@ -1,8 +1,17 @@
+pub mod calibration;
+pub mod clustering;
+pub mod comparison;
 pub mod evaluation;
+pub mod formula;
+pub mod layout_distance;
+pub mod load_report;
+pub mod metric_analysis;
+pub mod metric_registry;
 pub mod metrics;
 pub mod ngram_mapper;
 pub mod ngrams;
 pub mod results;
+pub mod typing_log;
 
 #[cfg(test)]
 mod tests {
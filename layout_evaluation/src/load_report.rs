@@ -0,0 +1,285 @@
+//! The `load_report` module provides the load, i.e. the share of keystrokes, of the individual
+//! keys, fingers, and hands of a layout together with renderers for visualizing the key loads as
+//! heatmaps (either in the keyboard's plot template or as SVG).
+//!
+//! The loads are computed from the unigrams as they are used for the unigram metrics. Hence,
+//! presses of modifiers that are required for higher-layer symbols are included. Symbols that are
+//! generated by combos count as a keystroke on each of the involved keys.
+
+use keyboard_layout::key::{Finger, Hand, HandFingerMap, HandMap, MatrixPosition, Position};
+use keyboard_layout::layout::{LayerKey, Layout};
+use keyboard_layout::render::{self, Keycap, RenderOptions};
+
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+
+/// Load of an individual (physical) key.
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyLoad {
+    /// Index of the key in the keyboard's keys.
+    pub index: usize,
+    /// Symbol on the base layer of the key.
+    pub symbol: char,
+    pub matrix_position: MatrixPosition,
+    pub position: Position,
+    pub hand: Hand,
+    pub finger: Finger,
+    /// Share of all keystrokes.
+    pub load: f64,
+}
+
+/// Load of a finger.
+#[derive(Clone, Debug, Serialize)]
+pub struct FingerLoad {
+    pub hand: Hand,
+    pub finger: Finger,
+    /// Share of all keystrokes.
+    pub load: f64,
+}
+
+/// Load of a hand.
+#[derive(Clone, Debug, Serialize)]
+pub struct HandLoad {
+    pub hand: Hand,
+    /// Share of all keystrokes.
+    pub load: f64,
+}
+
+/// Loads of all keys, fingers, and hands of a layout.
+#[derive(Clone, Debug, Serialize)]
+pub struct LoadReport {
+    /// Loads of the keys (in the order of the keyboard's keys).
+    pub keys: Vec<KeyLoad>,
+    /// Loads of the fingers (left pinky to right pinky, thumbs last).
+    pub fingers: Vec<FingerLoad>,
+    /// Loads of the hands.
+    pub hands: Vec<HandLoad>,
+}
+
+const FINGER_ORDER: [(Hand, Finger); 10] = [
+    (Hand::Left, Finger::Pinky),
+    (Hand::Left, Finger::Ring),
+    (Hand::Left, Finger::Middle),
+    (Hand::Left, Finger::Pointer),
+    (Hand::Right, Finger::Pointer),
+    (Hand::Right, Finger::Middle),
+    (Hand::Right, Finger::Ring),
+    (Hand::Right, Finger::Pinky),
+    (Hand::Left, Finger::Thumb),
+    (Hand::Right, Finger::Thumb),
+];
+
+/// Replace symbols that are invisible in a plot.
+pub(crate) fn printable_symbol(c: char) -> char {
+    match c {
+        '\n' => '\u{23ce}',
+        '\t' => '\u{21e5}',
+        ' ' => '\u{2423}',
+        '\u{1b}' => '\u{2327}',
+        c => c,
+    }
+}
+
+/// Format a load as percentage using at most three characters.
+fn format_percentage(load: f64) -> String {
+    let percentage = 100.0 * load;
+    if percentage <= 0.0 {
+        String::new()
+    } else if percentage < 9.95 {
+        format!("{:.1}", percentage)
+    } else {
+        format!("{:.0}", percentage)
+    }
+}
+
+impl LoadReport {
+    /// Compute the loads from the (split) unigrams of a layout.
+    pub fn from_unigrams(unigrams: &[(&LayerKey, f64)], layout: &Layout) -> Self {
+        let keys = &layout.keyboard.keys;
+        let key_indices: FxHashMap<MatrixPosition, usize> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k.matrix_position, i))
+            .collect();
+
+        let mut key_weights = vec![0.0; keys.len()];
+        let mut total_weight = 0.0;
+        for (layerkey, weight) in unigrams {
+            let combo_positions = layerkey
+                .combo_keys
+                .iter()
+                .map(|idx| layout.get_layerkey(idx).key.matrix_position);
+            std::iter::once(layerkey.key.matrix_position)
+                .chain(combo_positions)
+                .filter_map(|pos| key_indices.get(&pos))
+                .for_each(|i| key_weights[*i] += weight);
+            total_weight += weight;
+        }
+        let total_weight = if total_weight > 0.0 {
+            total_weight
+        } else {
+            1.0
+        };
+
+        let symbols: Vec<char> = layout
+            .base_symbols()
+            .into_iter()
+            .map(|symbol| symbol.unwrap_or(' '))
+            .collect();
+
+        let mut finger_loads: HandFingerMap<f64> = HandFingerMap::with_default(0.0);
+        let mut hand_loads: HandMap<f64> = HandMap::with_default(0.0);
+        let key_loads: Vec<KeyLoad> = keys
+            .iter()
+            .zip(key_weights.iter().zip(symbols.iter()))
+            .enumerate()
+            .map(|(index, (key, (weight, symbol)))| {
+                let load = weight / total_weight;
+                *finger_loads.get_mut(&key.hand, &key.finger) += load;
+                *hand_loads.get_mut(&key.hand) += load;
+                KeyLoad {
+                    index,
+                    symbol: *symbol,
+                    matrix_position: key.matrix_position,
+                    position: key.position,
+                    hand: key.hand,
+                    finger: key.finger,
+                    load,
+                }
+            })
+            .collect();
+
+        Self {
+            keys: key_loads,
+            fingers: FINGER_ORDER
+                .iter()
+                .map(|(hand, finger)| FingerLoad {
+                    hand: *hand,
+                    finger: *finger,
+                    load: *finger_loads.get(hand, finger),
+                })
+                .collect(),
+            hands: [Hand::Left, Hand::Right]
+                .iter()
+                .map(|hand| HandLoad {
+                    hand: *hand,
+                    load: *hand_loads.get(hand),
+                })
+                .collect(),
+        }
+    }
+
+    /// Plot the key loads (in percent) into the keyboard's plot template.
+    pub fn plot(&self, layout: &Layout) -> String {
+        let labels: Vec<String> = self
+            .keys
+            .iter()
+            .map(|k| format_percentage(k.load))
+            .collect();
+        let labels: Vec<&str> = labels.iter().map(|s| s.as_str()).collect();
+
+        layout.keyboard.plot_wide(&labels)
+    }
+
+    /// Render the key loads as SVG heatmap based on the keys' positions and sizes. Each key shows
+    /// its base-layer symbol and its load in percent.
+    pub fn svg(&self, layout: &Layout) -> String {
+        let max_load = self.keys.iter().map(|k| k.load).fold(0.0, f64::max);
+        let max_load = if max_load > 0.0 { max_load } else { 1.0 };
+
+        let keycaps: Vec<Keycap> = self
+            .keys
+            .iter()
+            .map(|k| {
+                // from white (no load) to red (maximal load)
+                let intensity = k.load / max_load;
+                Keycap {
+                    labels: vec![printable_symbol(k.symbol).to_string()],
+                    note: Some(format_percentage(k.load)),
+                    fill: Some([
+                        255,
+                        (255.0 * (1.0 - 0.75 * intensity)).round() as u8,
+                        (255.0 * (1.0 - intensity)).round() as u8,
+                    ]),
+                    title: Some(format!(
+                        "{:?} {:?}: {:.2}%",
+                        k.hand,
+                        k.finger,
+                        100.0 * k.load
+                    )),
+                    is_fixed: false,
+                }
+            })
+            .collect();
+
+        let options = RenderOptions {
+            layers: 1,
+            finger_colors: false,
+            ..Default::default()
+        };
+        render::svg(&layout.keyboard, &keycaps, &options)
+    }
+}
+
+impl std::fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Finger loads:")?;
+        for l in self.fingers.iter() {
+            writeln!(
+                f,
+                "  {:>13} {:>5.1}%",
+                format!("{:?} {:?}", l.hand, l.finger),
+                100.0 * l.load
+            )?;
+        }
+        write!(f, "Hand loads:")?;
+        for l in self.hands.iter() {
+            write!(f, " {:?} {:.1}%", l.hand, 100.0 * l.load)?;
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use keyboard_layout::keyboard::{Keyboard, KeyboardYAML};
+    use keyboard_layout::layout_generator::{BaseLayoutYAML, NeoLayoutGenerator};
+    use serde::Deserialize;
+    use std::sync::Arc;
+
+    #[derive(Deserialize)]
+    struct LayoutConfig {
+        keyboard: KeyboardYAML,
+        base_layout: BaseLayoutYAML,
+    }
+
+    #[test]
+    fn dual_role_keys_show_tap_symbols() {
+        let config = std::fs::read_to_string("../config/standard_keyboard.yml")
+            .unwrap()
+            .replace(
+                "dual_role_keys: []",
+                "dual_role_keys:\n    - matrix_position: [4, 2]\n      hold: \"⇧\"\n    - matrix_position: [9, 2]\n      hold: \"⇗\"",
+            );
+        let config: LayoutConfig = serde_yaml::from_str(&config).unwrap();
+        let keyboard = Arc::new(Keyboard::from_yaml_object(config.keyboard).unwrap());
+        let layout = NeoLayoutGenerator::from_object(config.base_layout, keyboard)
+            .unwrap()
+            .generate("xvlcwkhgfqyßuiaeosnrtdüöäpzbm,.j")
+            .unwrap();
+
+        let report = LoadReport::from_unigrams(&[], &layout);
+        let symbol = |matrix_position| {
+            report
+                .keys
+                .iter()
+                .find(|k| k.matrix_position == matrix_position)
+                .map(|k| k.symbol)
+        };
+
+        assert_eq!(symbol(MatrixPosition(4, 2)), Some('a'));
+        assert_eq!(symbol(MatrixPosition(9, 2)), Some('r'));
+    }
+}
@@ -0,0 +1,178 @@
+//! The `metric_analysis` module provides statistics on how the individual metrics contribute to
+//! the total cost of a sample of layouts.
+//!
+//! For each metric, the share of the total cost's variance is computed as the covariance of the
+//! metric's (weighted) cost with the total cost divided by the total cost's variance. These shares
+//! sum up to one over all metrics. A metric with a negligible variance share hardly influences
+//! which of the sampled layouts is considered better, even if it contributes a large (but nearly
+//! constant) amount to the total cost.
+
+use crate::results::{EvaluationResult, MetricType};
+
+use serde::Serialize;
+
+/// Statistics of an individual metric over the sampled layouts.
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricStatistics {
+    /// Name of the metric.
+    pub name: String,
+    /// Type of the metric.
+    pub metric_type: MetricType,
+    /// Mean of the weighted cost.
+    pub mean: f64,
+    /// Standard deviation of the weighted cost.
+    pub std: f64,
+    /// Mean weighted cost relative to the mean total cost.
+    pub cost_share: f64,
+    /// Covariance of the weighted cost with the total cost relative to the total cost's variance.
+    pub variance_share: f64,
+    /// Whether the metric's standard deviation is negligible compared to the total cost's.
+    pub inert: bool,
+}
+
+/// Statistics of all metrics over a sample of layouts.
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricAnalysis {
+    /// Number of sampled layouts.
+    pub n_samples: usize,
+    /// Mean of the total cost.
+    pub total_mean: f64,
+    /// Standard deviation of the total cost.
+    pub total_std: f64,
+    /// Statistics for each metric (in the order of the evaluation results).
+    pub metrics: Vec<MetricStatistics>,
+    /// Pearson correlation coefficients of the metrics' weighted costs (`NaN` for constant metrics).
+    pub correlations: Vec<Vec<f64>>,
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+fn covariance(x: &[f64], y: &[f64]) -> f64 {
+    let (mx, my) = (mean(x), mean(y));
+    x.iter()
+        .zip(y.iter())
+        .map(|(xi, yi)| (xi - mx) * (yi - my))
+        .sum::<f64>()
+        / x.len().max(1) as f64
+}
+
+impl MetricAnalysis {
+    /// Compute the statistics from the evaluation results of the sampled layouts. A metric is
+    /// considered inert if its standard deviation is below `inert_threshold` times the standard
+    /// deviation of the total cost.
+    pub fn from_results(results: &[EvaluationResult], inert_threshold: f64) -> Self {
+        let metric_infos: Vec<(String, MetricType)> = results
+            .first()
+            .map(|r| {
+                r.iter()
+                    .flat_map(|mr| {
+                        mr.metric_costs
+                            .iter()
+                            .map(move |mc| (mc.core.name.clone(), mr.metric_type.clone()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // costs[metric][sample]
+        let mut costs: Vec<Vec<f64>> = vec![Vec::with_capacity(results.len()); metric_infos.len()];
+        for result in results {
+            result
+                .iter()
+                .flat_map(|mr| mr.metric_costs.iter())
+                .zip(costs.iter_mut())
+                .for_each(|(mc, c)| c.push(mc.weighted_cost));
+        }
+        let totals: Vec<f64> = results.iter().map(|r| r.total_cost()).collect();
+
+        let total_mean = mean(&totals);
+        let total_variance = covariance(&totals, &totals);
+        let total_std = total_variance.sqrt();
+
+        let metrics = metric_infos
+            .into_iter()
+            .zip(costs.iter())
+            .map(|((name, metric_type), c)| {
+                let std = covariance(c, c).sqrt();
+                MetricStatistics {
+                    name,
+                    metric_type,
+                    mean: mean(c),
+                    std,
+                    cost_share: mean(c) / total_mean,
+                    variance_share: covariance(c, &totals) / total_variance,
+                    inert: std <= inert_threshold * total_std,
+                }
+            })
+            .collect();
+
+        let correlations = costs
+            .iter()
+            .map(|c1| {
+                costs
+                    .iter()
+                    .map(|c2| covariance(c1, c2) / (covariance(c1, c1) * covariance(c2, c2)).sqrt())
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            n_samples: results.len(),
+            total_mean,
+            total_std,
+            metrics,
+            correlations,
+        }
+    }
+
+    /// Pairs of metrics (indices into `metrics`) whose absolute correlation is at least
+    /// `threshold`, sorted by decreasing absolute correlation.
+    pub fn correlated_pairs(&self, threshold: f64) -> Vec<(usize, usize, f64)> {
+        let mut pairs: Vec<(usize, usize, f64)> = self
+            .correlations
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .skip(i + 1)
+                    .filter(|(_, r)| r.abs() >= threshold)
+                    .map(move |(j, r)| (i, j, *r))
+            })
+            .collect();
+        pairs.sort_by(|(_, _, r1), (_, _, r2)| r2.abs().partial_cmp(&r1.abs()).unwrap());
+
+        pairs
+    }
+}
+
+impl std::fmt::Display for MetricAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Total cost over {} layouts: {:.4} ± {:.4}",
+            self.n_samples, self.total_mean, self.total_std
+        )?;
+        writeln!(
+            f,
+            "  {:<45} {:>10} {:>10} {:>11} {:>15}",
+            "Metric", "Mean", "Std", "Cost share", "Variance share"
+        )?;
+        for m in self.metrics.iter() {
+            writeln!(
+                f,
+                "  {:<45} {:>10.4} {:>10.4} {:>10.2}% {:>14.2}%{}",
+                format!("{} ({:?})", m.name, m.metric_type),
+                m.mean,
+                m.std,
+                100.0 * m.cost_share,
+                100.0 * m.variance_share,
+                if m.inert { "  (inert)" } else { "" }
+            )?;
+        }
+
+        Ok(())
+    }
+}
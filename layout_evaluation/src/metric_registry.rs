@@ -0,0 +1,349 @@
+//! The `metric_registry` module provides a `MetricRegistry` that maps metric names (as used in
+//! configuration files) to constructors of the corresponding metric objects.
+//!
+//! All metrics of this crate are registered in `MetricRegistry::default()`. Further metrics
+//! (e.g. from other crates) can be added with the `register_*_metric` methods. Each constructor
+//! receives the metric's parameters deserialized from the YAML configuration. Registering a name
+//! twice panics; to replace a metric, `unregister` it first.
+
+use crate::metrics::{
+    bigram_metrics, bigram_metrics::BigramMetric, layout_metrics, layout_metrics::LayoutMetric,
+    trigram_metrics, trigram_metrics::TrigramMetric, unigram_metrics,
+    unigram_metrics::UnigramMetric,
+};
+use crate::results::NormalizationType;
+
+use anyhow::Result;
+use rustc_hash::FxHashMap;
+use serde::de::DeserializeOwned;
+
+/// A bigram metric as used by the `Evaluator` (and passed to trigram metrics building on it).
+pub type WeightedBigramMetric = (f64, NormalizationType, Box<dyn BigramMetric>);
+
+type LayoutMetricConstructor =
+    Box<dyn Fn(&serde_yaml::Value) -> Result<Box<dyn LayoutMetric>> + Send + Sync>;
+type UnigramMetricConstructor =
+    Box<dyn Fn(&serde_yaml::Value) -> Result<Box<dyn UnigramMetric>> + Send + Sync>;
+type BigramMetricConstructor =
+    Box<dyn Fn(&serde_yaml::Value) -> Result<Box<dyn BigramMetric>> + Send + Sync>;
+type TrigramMetricConstructor = Box<
+    dyn Fn(&serde_yaml::Value, &[WeightedBigramMetric]) -> Result<Box<dyn TrigramMetric>>
+        + Send
+        + Sync,
+>;
+
+/// A constructor for a metric of any of the supported metric types.
+pub enum MetricConstructor {
+    Layout(LayoutMetricConstructor),
+    Unigram(UnigramMetricConstructor),
+    Bigram(BigramMetricConstructor),
+    Trigram(TrigramMetricConstructor),
+}
+
+/// Deserialize a metric's parameters from a YAML value.
+fn parse_params<P: DeserializeOwned>(name: &str, params: &serde_yaml::Value) -> Result<P> {
+    // metrics without parameters are usually configured with `params: { null: null }`
+    let params = match params {
+        serde_yaml::Value::Null => serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
+        serde_yaml::Value::Mapping(mapping) => serde_yaml::Value::Mapping(
+            mapping
+                .iter()
+                .filter(|(key, _)| !key.is_null())
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+        _ => params.clone(),
+    };
+
+    serde_yaml::from_value(params)
+        .map_err(|e| anyhow::anyhow!("Invalid parameters for metric '{}': {}", name, e))
+}
+
+/// Maps metric names to constructors of the corresponding metrics.
+pub struct MetricRegistry {
+    constructors: FxHashMap<String, MetricConstructor>,
+}
+
+impl MetricRegistry {
+    /// Generate a `MetricRegistry` without any metric.
+    pub fn empty() -> Self {
+        Self {
+            constructors: FxHashMap::default(),
+        }
+    }
+
+    /// Add a constructor under the given name. Panics if the name is registered already (use
+    /// `unregister` first to replace a metric).
+    fn insert(&mut self, name: &str, constructor: MetricConstructor) {
+        if self.constructors.contains_key(name) {
+            panic!("Metric '{}' is registered already", name);
+        }
+        self.constructors.insert(name.to_string(), constructor);
+    }
+
+    /// Remove a metric, e.g. to register a different implementation under its name. Returns its
+    /// constructor if the metric was registered.
+    pub fn unregister(&mut self, name: &str) -> Option<MetricConstructor> {
+        self.constructors.remove(name)
+    }
+
+    /// Register a metric that operates only on the layout itself ("layout metric").
+    pub fn register_layout_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P) -> Box<dyn LayoutMetric> + Send + Sync + 'static,
+    {
+        self.try_register_layout_metric(name, move |p: &P| Ok(constructor(p)));
+    }
+
+    /// Register a layout metric whose construction may fail (e.g. due to invalid parameters).
+    pub fn try_register_layout_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P) -> Result<Box<dyn LayoutMetric>> + Send + Sync + 'static,
+    {
+        let metric_name = name.to_string();
+        self.insert(
+            name,
+            MetricConstructor::Layout(Box::new(move |params| {
+                constructor(&parse_params(&metric_name, params)?)
+            })),
+        );
+    }
+
+    /// Register a metric that operates on the unigram data ("unigram metric").
+    pub fn register_unigram_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P) -> Box<dyn UnigramMetric> + Send + Sync + 'static,
+    {
+        self.try_register_unigram_metric(name, move |p: &P| Ok(constructor(p)));
+    }
+
+    /// Register a unigram metric whose construction may fail (e.g. due to invalid parameters).
+    pub fn try_register_unigram_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P) -> Result<Box<dyn UnigramMetric>> + Send + Sync + 'static,
+    {
+        let metric_name = name.to_string();
+        self.insert(
+            name,
+            MetricConstructor::Unigram(Box::new(move |params| {
+                constructor(&parse_params(&metric_name, params)?)
+            })),
+        );
+    }
+
+    /// Register a metric that operates on the bigram data ("bigram metric").
+    pub fn register_bigram_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P) -> Box<dyn BigramMetric> + Send + Sync + 'static,
+    {
+        self.try_register_bigram_metric(name, move |p: &P| Ok(constructor(p)));
+    }
+
+    /// Register a bigram metric whose construction may fail (e.g. due to invalid parameters).
+    pub fn try_register_bigram_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P) -> Result<Box<dyn BigramMetric>> + Send + Sync + 'static,
+    {
+        let metric_name = name.to_string();
+        self.insert(
+            name,
+            MetricConstructor::Bigram(Box::new(move |params| {
+                constructor(&parse_params(&metric_name, params)?)
+            })),
+        );
+    }
+
+    /// Register a metric that operates on the trigram data ("trigram metric"). The constructor
+    /// additionally receives all enabled bigram metrics.
+    pub fn register_trigram_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P, &[WeightedBigramMetric]) -> Box<dyn TrigramMetric> + Send + Sync + 'static,
+    {
+        self.try_register_trigram_metric(name, move |p: &P, bigram_metrics| {
+            Ok(constructor(p, bigram_metrics))
+        });
+    }
+
+    /// Register a trigram metric whose construction may fail (e.g. due to invalid parameters).
+    pub fn try_register_trigram_metric<P, F>(&mut self, name: &str, constructor: F)
+    where
+        P: DeserializeOwned,
+        F: Fn(&P, &[WeightedBigramMetric]) -> Result<Box<dyn TrigramMetric>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let metric_name = name.to_string();
+        self.insert(
+            name,
+            MetricConstructor::Trigram(Box::new(move |params, bigram_metrics| {
+                constructor(&parse_params(&metric_name, params)?, bigram_metrics)
+            })),
+        );
+    }
+
+    /// Get the constructor of a metric by its name.
+    pub fn get(&self, name: &str) -> Result<&MetricConstructor> {
+        self.constructors.get(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown metric '{}'. Available metrics: {}",
+                name,
+                self.names().join(", ")
+            )
+        })
+    }
+
+    /// Names of all registered metrics (sorted alphabetically).
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.constructors.keys().map(|n| n.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl Default for MetricRegistry {
+    /// Generate a `MetricRegistry` containing all metrics provided by this crate.
+    fn default() -> Self {
+        let mut r = Self::empty();
+
+        // layout metrics
+        r.register_layout_metric("asymmetric_keys", |p| {
+            Box::new(layout_metrics::asymmetric_keys::AsymmetricKeys::new(p))
+        });
+        r.register_layout_metric("shortcut_keys", |p| {
+            Box::new(layout_metrics::shortcut_keys::ShortcutKeys::new(p))
+        });
+
+        // unigram metrics
+        r.register_unigram_metric("combos", |p| {
+            Box::new(unigram_metrics::combos::Combos::new(p))
+        });
+        r.register_unigram_metric("finger_balance", |p| {
+            Box::new(unigram_metrics::finger_balance::FingerBalance::new(p))
+        });
+        r.try_register_unigram_metric("unigram_formula", |p| {
+            Ok(Box::new(unigram_metrics::formula::UnigramFormula::new(p)?))
+        });
+        r.register_unigram_metric("hand_disbalance", |p| {
+            Box::new(unigram_metrics::hand_disbalance::HandDisbalance::new(p))
+        });
+        r.register_unigram_metric("key_costs", |p| {
+            Box::new(unigram_metrics::key_costs::KeyCost::new(p))
+        });
+        r.register_unigram_metric("modifier_chords", |p| {
+            Box::new(unigram_metrics::modifier_chords::ModifierChords::new(p))
+        });
+        r.register_unigram_metric("thumb_stretch", |p| {
+            Box::new(unigram_metrics::thumb_stretch::ThumbStretch::new(p))
+        });
+
+        // bigram metrics
+        r.register_bigram_metric("asymmetric_bigrams", |p| {
+            Box::new(bigram_metrics::asymmetric_bigrams::AsymmetricBigrams::new(
+                p,
+            ))
+        });
+        r.register_bigram_metric("dual_role_misfires", |p| {
+            Box::new(bigram_metrics::dual_role_misfires::DualRoleMisfires::new(p))
+        });
+        r.register_bigram_metric("finger_repeats", |p| {
+            Box::new(bigram_metrics::finger_repeats::FingerRepeats::new(p))
+        });
+        r.register_bigram_metric("finger_repeats_lateral", |p| {
+            Box::new(bigram_metrics::finger_repeats_lateral::FingerRepeatsLateral::new(p))
+        });
+        r.register_bigram_metric("finger_repeats_top_bottom", |p| {
+            Box::new(bigram_metrics::finger_repeats_top_bottom::FingerRepeatsTopBottom::new(p))
+        });
+        r.try_register_bigram_metric("bigram_formula", |p| {
+            Ok(Box::new(bigram_metrics::formula::BigramFormula::new(p)?))
+        });
+        r.register_bigram_metric("line_changes", |p| {
+            Box::new(bigram_metrics::line_changes::LineChanges::new(p))
+        });
+        r.register_bigram_metric("manual_bigram_penalty", |p| {
+            Box::new(bigram_metrics::manual_bigram_penalty::ManualBigramPenalty::new(p))
+        });
+        r.try_register_bigram_metric("measured_latency", |p| {
+            Ok(Box::new(
+                bigram_metrics::measured_latency::MeasuredLatency::new(p)?,
+            ))
+        });
+        r.register_bigram_metric("movement_pattern", |p| {
+            Box::new(bigram_metrics::movement_pattern::MovementPattern::new(p))
+        });
+        r.register_bigram_metric("no_handswitch_after_unbalancing_key", |p| {
+            Box::new(
+                bigram_metrics::no_handswitch_after_unbalancing_key::NoHandSwitchAfterUnbalancingKey::new(p),
+            )
+        });
+        r.register_bigram_metric("thumb_alternation", |p| {
+            Box::new(bigram_metrics::thumb_alternation::ThumbAlternation::new(p))
+        });
+        r.register_bigram_metric("thumb_repeats", |p| {
+            Box::new(bigram_metrics::thumb_repeats::ThumbRepeats::new(p))
+        });
+        r.register_bigram_metric("unbalancing_after_neighboring", |p| {
+            Box::new(
+                bigram_metrics::unbalancing_after_neighboring::UnbalancingAfterNeighboring::new(p),
+            )
+        });
+
+        // trigram metrics
+        r.try_register_trigram_metric("trigram_formula", |p, _| {
+            Ok(Box::new(trigram_metrics::formula::TrigramFormula::new(p)?))
+        });
+        r.register_trigram_metric("irregularity", |p, bigram_metrics| {
+            Box::new(trigram_metrics::irregularity::Irregularity::new(
+                bigram_metrics.to_vec(),
+                p,
+            ))
+        });
+        r.register_trigram_metric("no_handswitch_in_trigram", |p, _| {
+            Box::new(trigram_metrics::no_handswitch_in_trigram::NoHandswitchInTrigram::new(p))
+        });
+        r.register_trigram_metric("secondary_bigrams", |p, bigram_metrics| {
+            Box::new(trigram_metrics::secondary_bigrams::SecondaryBigrams::new(
+                bigram_metrics.to_vec(),
+                p,
+            ))
+        });
+        r.register_trigram_metric("trigram_finger_repeats", |p, _| {
+            Box::new(trigram_metrics::trigram_finger_repeats::TrigramFingerRepeats::new(p))
+        });
+
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "Metric 'key_costs' is registered already")]
+    fn duplicate_name() {
+        let mut registry = MetricRegistry::default();
+        registry.register_unigram_metric("key_costs", |p| {
+            Box::new(unigram_metrics::key_costs::KeyCost::new(p))
+        });
+    }
+
+    #[test]
+    fn replace_metric() {
+        let mut registry = MetricRegistry::default();
+        assert!(registry.unregister("key_costs").is_some());
+        assert!(registry.get("key_costs").is_err());
+        registry.register_unigram_metric("key_costs", |p| {
+            Box::new(unigram_metrics::key_costs::KeyCost::new(p))
+        });
+        assert!(registry.get("key_costs").is_ok());
+    }
+}
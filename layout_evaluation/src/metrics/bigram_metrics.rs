@@ -4,13 +4,18 @@ use priority_queue::DoublePriorityQueue;
 use ordered_float::OrderedFloat;
 
 pub mod asymmetric_bigrams;
+pub mod dual_role_misfires;
 pub mod finger_repeats;
 pub mod finger_repeats_lateral;
 pub mod finger_repeats_top_bottom;
+pub mod formula;
 pub mod line_changes;
 pub mod manual_bigram_penalty;
+pub mod measured_latency;
 pub mod movement_pattern;
 pub mod no_handswitch_after_unbalancing_key;
+pub mod thumb_alternation;
+pub mod thumb_repeats;
 pub mod unbalancing_after_neighboring;
 
 const SHOW_WORST: bool = true;
@@ -0,0 +1,72 @@
+//! The bigram metric `DualRoleMisfires` incurrs a cost for bigrams that are prone to
+//! misfires of dual-role (tap-hold) keys, e.g. "home row mods".
+//!
+//! If a dual-role key is tapped and followed by another key of the same hand, fast rolls
+//! may press the second key before the first one is released and thereby accidentally activate
+//! the hold function. This is even more likely if the second key is hit by a neighboring finger.
+//! Holding the modifier of a dual-role key while pressing another key of the same hand is
+//! awkward and error-prone as well.
+
+use super::BigramMetric;
+
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Cost for tapping a dual-role key followed by another key of the same hand.
+    pub roll_cost: f64,
+    /// If the second key is hit by a neighboring finger, the roll cost is multiplied with this factor.
+    pub neighboring_finger_factor: f64,
+    /// Cost for holding the modifier of a dual-role key while pressing another key of the same hand.
+    pub same_hand_hold_cost: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct DualRoleMisfires {
+    roll_cost: f64,
+    neighboring_finger_factor: f64,
+    same_hand_hold_cost: f64,
+}
+
+impl DualRoleMisfires {
+    pub fn new(params: &Parameters) -> Self {
+        Self {
+            roll_cost: params.roll_cost,
+            neighboring_finger_factor: params.neighboring_finger_factor,
+            same_hand_hold_cost: params.same_hand_hold_cost,
+        }
+    }
+}
+
+impl BigramMetric for DualRoleMisfires {
+    fn name(&self) -> &str {
+        "Dual-Role Misfires"
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        k1: &LayerKey,
+        k2: &LayerKey,
+        weight: f64,
+        _total_weight: f64,
+        _layout: &Layout,
+    ) -> Option<f64> {
+        if !k1.is_dual_role || k1.key.hand != k2.key.hand || k1.key == k2.key {
+            return Some(0.0);
+        }
+
+        let cost = if k1.is_modifier {
+            // the modifier of the dual-role key is held while pressing the second key
+            self.same_hand_hold_cost
+        } else if k1.key.finger.distance(&k2.key.finger) == 1 {
+            self.roll_cost * self.neighboring_finger_factor
+        } else {
+            self.roll_cost
+        };
+
+        Some(weight * cost)
+    }
+}
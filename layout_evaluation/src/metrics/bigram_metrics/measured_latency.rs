@@ -0,0 +1,78 @@
+//! The bigram metric `MeasuredLatency` assigns each bigram the latency that has been measured
+//! for the corresponding pair of keys in keystroke logs (see the `typing_log` module).
+//!
+//! The latencies are identified by the keys' matrix positions, so latencies measured on one layout
+//! can be used for evaluating other layouts of the same keyboard. Pairs of keys without (enough)
+//! measurements are assigned a default latency.
+
+use super::BigramMetric;
+use crate::typing_log::BigramLatencies;
+
+use anyhow::Result;
+use keyboard_layout::key::MatrixPosition;
+use keyboard_layout::layout::{LayerKey, Layout};
+use rustc_hash::FxHashMap;
+
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// File containing the measured latencies (as generated by the `ingest_typing_log` binary).
+    pub latencies_file: String,
+    /// Latencies based on fewer measurements are ignored.
+    pub min_count: usize,
+    /// Latency in milliseconds for pairs of keys without measurements (defaults to the mean of all
+    /// measured latencies).
+    #[serde(default)]
+    pub default_latency: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MeasuredLatency {
+    latencies: FxHashMap<(MatrixPosition, MatrixPosition), f64>,
+    default_latency: f64,
+}
+
+impl MeasuredLatency {
+    pub fn new(params: &Parameters) -> Result<Self> {
+        let latencies: FxHashMap<(MatrixPosition, MatrixPosition), f64> =
+            BigramLatencies::from_yaml(&params.latencies_file)?
+                .latencies
+                .into_iter()
+                .filter(|l| l.count >= params.min_count)
+                .map(|l| ((l.from, l.to), l.latency))
+                .collect();
+
+        let default_latency = params
+            .default_latency
+            .unwrap_or_else(|| latencies.values().sum::<f64>() / latencies.len().max(1) as f64);
+
+        Ok(Self {
+            latencies,
+            default_latency,
+        })
+    }
+}
+
+impl BigramMetric for MeasuredLatency {
+    fn name(&self) -> &str {
+        "Measured Latency"
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        k1: &LayerKey,
+        k2: &LayerKey,
+        weight: f64,
+        _total_weight: f64,
+        _layout: &Layout,
+    ) -> Option<f64> {
+        let latency = self
+            .latencies
+            .get(&(k1.key.matrix_position, k2.key.matrix_position))
+            .unwrap_or(&self.default_latency);
+
+        Some(weight * latency)
+    }
+}
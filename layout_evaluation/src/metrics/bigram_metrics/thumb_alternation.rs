@@ -0,0 +1,70 @@
+//! The bigram metric `ThumbAlternation` incurs a cost for bigrams where a (non-modifier) thumb key,
+//! usually the spacebar, is hit by the thumb of the same hand that hits the preceding or
+//! following key. Alternating between the hands in these cases allows the thumb keystroke to
+//! happen while the other hand is already moving towards its next key.
+//!
+//! *Note:* Modifiers are excluded, as they are held during the other keystroke anyway.
+
+use super::BigramMetric;
+
+use keyboard_layout::key::Finger;
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Cost for a thumb key following a key of the same hand.
+    pub same_hand_before_cost: f64,
+    /// Cost for a thumb key being followed by a key of the same hand.
+    pub same_hand_after_cost: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ThumbAlternation {
+    same_hand_before_cost: f64,
+    same_hand_after_cost: f64,
+}
+
+impl ThumbAlternation {
+    pub fn new(params: &Parameters) -> Self {
+        Self {
+            same_hand_before_cost: params.same_hand_before_cost,
+            same_hand_after_cost: params.same_hand_after_cost,
+        }
+    }
+}
+
+impl BigramMetric for ThumbAlternation {
+    fn name(&self) -> &str {
+        "Thumb Alternation"
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        k1: &LayerKey,
+        k2: &LayerKey,
+        weight: f64,
+        _total_weight: f64,
+        _layout: &Layout,
+    ) -> Option<f64> {
+        if k1.key.hand != k2.key.hand || k1.is_modifier || k2.is_modifier {
+            return Some(0.0);
+        }
+
+        let thumb1 = k1.key.finger == Finger::Thumb;
+        let thumb2 = k2.key.finger == Finger::Thumb;
+
+        let cost = match (thumb1, thumb2) {
+            // a finger key followed by a thumb key of the same hand
+            (false, true) => self.same_hand_before_cost,
+            // a thumb key followed by a finger key of the same hand
+            (true, false) => self.same_hand_after_cost,
+            // thumb repeats are covered by `ThumbRepeats`
+            _ => 0.0,
+        };
+
+        Some(weight * cost)
+    }
+}
@@ -0,0 +1,66 @@
+//! The bigram metric `ThumbRepeats` incurs a cost for bigrams that use the same thumb for
+//! different keys. Those are excluded from `FingerRepeats`, but become relevant for keyboards
+//! with thumb clusters of several keys per thumb. The cost increases with the distance between
+//! the two keys.
+//!
+//! *Note:* Modifiers are excluded, as they are held (and not repeatedly tapped).
+
+use super::BigramMetric;
+
+use keyboard_layout::key::Finger;
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Base cost for a repetition of the same thumb on different keys.
+    pub repeat_cost: f64,
+    /// The distance between the keys is multiplied with this factor and added to the cost.
+    pub distance_cost: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ThumbRepeats {
+    repeat_cost: f64,
+    distance_cost: f64,
+}
+
+impl ThumbRepeats {
+    pub fn new(params: &Parameters) -> Self {
+        Self {
+            repeat_cost: params.repeat_cost,
+            distance_cost: params.distance_cost,
+        }
+    }
+}
+
+impl BigramMetric for ThumbRepeats {
+    fn name(&self) -> &str {
+        "Thumb Repeats"
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        k1: &LayerKey,
+        k2: &LayerKey,
+        weight: f64,
+        _total_weight: f64,
+        _layout: &Layout,
+    ) -> Option<f64> {
+        if k1.key.finger != Finger::Thumb
+            || k2.key.finger != Finger::Thumb
+            || k1.key.hand != k2.key.hand
+            || k1.key == k2.key
+            || k1.is_modifier
+            || k2.is_modifier
+        {
+            return Some(0.0);
+        }
+
+        let distance = k1.key.position.distance(&k2.key.position);
+
+        Some(weight * (self.repeat_cost + self.distance_cost * distance))
+    }
+}
@@ -3,6 +3,7 @@ use keyboard_layout::layout::{LayerKey, Layout};
 use priority_queue::DoublePriorityQueue;
 use ordered_float::OrderedFloat;
 
+pub mod formula;
 pub mod irregularity;
 pub mod no_handswitch_in_trigram;
 pub mod secondary_bigrams;
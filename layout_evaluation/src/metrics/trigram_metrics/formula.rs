@@ -0,0 +1,58 @@
+//! The trigram metric `TrigramFormula` computes the cost of each trigram from a formula given in
+//! the configuration (see the `formula` module for its syntax). It allows prototyping new
+//! metrics without writing Rust code.
+
+use super::TrigramMetric;
+use crate::formula::Formula;
+
+use anyhow::Result;
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+
+fn default_name() -> String {
+    "Trigram Formula".to_string()
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Name of the metric as shown in the evaluation results.
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// Formula for the cost of a trigram (with keys `k1`, `k2`, and `k3`).
+    pub formula: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct TrigramFormula {
+    name: String,
+    formula: Formula,
+}
+
+impl TrigramFormula {
+    pub fn new(params: &Parameters) -> Result<Self> {
+        Ok(Self {
+            name: params.name.clone(),
+            formula: Formula::parse(&params.formula, 3)?,
+        })
+    }
+}
+
+impl TrigramMetric for TrigramFormula {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        k1: &LayerKey,
+        k2: &LayerKey,
+        k3: &LayerKey,
+        weight: f64,
+        total_weight: f64,
+        _layout: &Layout,
+    ) -> Option<f64> {
+        Some(self.formula.eval(&[k1, k2, k3], weight, total_weight))
+    }
+}
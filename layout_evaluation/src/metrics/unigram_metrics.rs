@@ -5,9 +5,13 @@ use keyboard_layout::layout::{LayerKey, Layout};
 use priority_queue::DoublePriorityQueue;
 use ordered_float::OrderedFloat;
 
+pub mod combos;
 pub mod finger_balance;
+pub mod formula;
 pub mod hand_disbalance;
 pub mod key_costs;
+pub mod modifier_chords;
+pub mod thumb_stretch;
 
 const SHOW_WORST: bool = true;
 const N_WORST: usize = 3;
@@ -17,6 +21,12 @@ pub trait UnigramMetric: Send + Sync + UnigramMetricClone + std::fmt::Debug {
     /// Return the name of the metric
     fn name(&self) -> &str;
 
+    /// Whether the metric operates on unigrams of the symbols as they appear in the text
+    /// instead of unigrams where higher-layer symbols are split into base keys and modifiers.
+    fn requires_unsplit_unigrams(&self) -> bool {
+        false
+    }
+
     /// Compute the cost of one unigram (if that is possible, otherwise, return `None`).
     #[inline(always)]
     fn individual_cost(
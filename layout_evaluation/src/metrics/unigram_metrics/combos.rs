@@ -0,0 +1,128 @@
+//! The unigram metric `Combos` incurs a cost for each symbol that is generated by a combo,
+//! i.e. by pressing several keys simultaneously. Like `ModifierChords`, it operates on the symbols
+//! as they appear in the text, i.e. before combos are split into their individual keys.
+//!
+//! Combos are slower and more error-prone than single key presses. Therefore, the optimization
+//! should only assign rare symbols to them. Combos of keys that are not adjacent (neighboring
+//! columns of the same row or neighboring rows of the same column, in any order) and combos
+//! spanning both hands are penalized with additional factors.
+
+use super::UnigramMetric;
+
+use keyboard_layout::key::{Key, MatrixPosition};
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Base cost for each symbol generated by a combo.
+    pub combo_cost: f64,
+    /// The summed cost of the combo's keys (as configured for the `Keyboard`) is multiplied with this factor and added.
+    pub key_cost_factor: f64,
+    /// If the combo's keys are not adjacent, the cost is multiplied with this factor.
+    pub non_adjacent_factor: f64,
+    /// If the combo's keys are spread over both hands, the cost is multiplied with this factor.
+    pub cross_hand_factor: f64,
+    /// For each additional key beyond the second one, the cost is multiplied with this factor.
+    pub multi_key_factor: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Combos {
+    combo_cost: f64,
+    key_cost_factor: f64,
+    non_adjacent_factor: f64,
+    cross_hand_factor: f64,
+    multi_key_factor: f64,
+}
+
+impl Combos {
+    pub fn new(params: &Parameters) -> Self {
+        Self {
+            combo_cost: params.combo_cost,
+            key_cost_factor: params.key_cost_factor,
+            non_adjacent_factor: params.non_adjacent_factor,
+            cross_hand_factor: params.cross_hand_factor,
+            multi_key_factor: params.multi_key_factor,
+        }
+    }
+}
+
+/// Whether the positions form a contiguous block within a single row or a single column.
+fn is_contiguous(positions: &[MatrixPosition]) -> bool {
+    let contiguous = |mut values: Vec<isize>| {
+        values.sort_unstable();
+        values.windows(2).all(|w| w[1] - w[0] == 1)
+    };
+
+    if positions.iter().all(|p| p.1 == positions[0].1) {
+        contiguous(positions.iter().map(|p| p.0).collect())
+    } else if positions.iter().all(|p| p.0 == positions[0].0) {
+        contiguous(positions.iter().map(|p| p.1).collect())
+    } else {
+        false
+    }
+}
+
+impl UnigramMetric for Combos {
+    fn name(&self) -> &str {
+        "Combos"
+    }
+
+    fn requires_unsplit_unigrams(&self) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        key: &LayerKey,
+        weight: f64,
+        _total_weight: f64,
+        layout: &Layout,
+    ) -> Option<f64> {
+        let combo = match layout.get_combo(key) {
+            Some(combo) => combo,
+            None => return Some(0.0),
+        };
+
+        let keys: Vec<&Key> = combo
+            .iter()
+            .map(|key_index| &layout.keyboard.keys[*key_index as usize])
+            .collect();
+
+        // the (virtual) key of a combo carries the summed cost of its keys
+        let mut cost = self.combo_cost + self.key_cost_factor * key.key.cost;
+
+        if keys.iter().any(|k| k.hand != keys[0].hand) {
+            cost *= self.cross_hand_factor;
+        } else if !is_contiguous(&keys.iter().map(|k| k.matrix_position).collect::<Vec<_>>()) {
+            cost *= self.non_adjacent_factor;
+        }
+
+        cost *= self.multi_key_factor.powi(keys.len() as i32 - 2);
+
+        Some(weight * cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contiguous_positions() {
+        let p = |c, r| MatrixPosition(c, r);
+
+        assert!(is_contiguous(&[p(1, 1), p(2, 1)]));
+        assert!(is_contiguous(&[p(0, 1), p(2, 1), p(1, 1)]));
+        assert!(is_contiguous(&[p(3, 2), p(3, 1)]));
+        assert!(is_contiguous(&[p(3, 3), p(3, 1), p(3, 2)]));
+
+        assert!(!is_contiguous(&[p(1, 1), p(3, 1)]));
+        assert!(!is_contiguous(&[p(1, 1), p(1, 3)]));
+        assert!(!is_contiguous(&[p(1, 1), p(2, 2)]));
+        assert!(!is_contiguous(&[p(1, 1), p(1, 1)]));
+    }
+}
@@ -0,0 +1,66 @@
+//! The unigram metric `UnigramFormula` computes the cost of each unigram from a formula given in
+//! the configuration (see the `formula` module for its syntax). It allows prototyping new
+//! metrics without writing Rust code.
+
+use super::UnigramMetric;
+use crate::formula::Formula;
+
+use anyhow::Result;
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+
+fn default_name() -> String {
+    "Unigram Formula".to_string()
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Name of the metric as shown in the evaluation results.
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// Formula for the cost of a unigram (with key `k1`).
+    pub formula: String,
+    /// Whether the formula shall operate on the symbols as they appear in the text instead of the
+    /// base keys and modifiers that higher-layer symbols are split into.
+    #[serde(default)]
+    pub unsplit_unigrams: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct UnigramFormula {
+    name: String,
+    formula: Formula,
+    unsplit_unigrams: bool,
+}
+
+impl UnigramFormula {
+    pub fn new(params: &Parameters) -> Result<Self> {
+        Ok(Self {
+            name: params.name.clone(),
+            formula: Formula::parse(&params.formula, 1)?,
+            unsplit_unigrams: params.unsplit_unigrams,
+        })
+    }
+}
+
+impl UnigramMetric for UnigramFormula {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn requires_unsplit_unigrams(&self) -> bool {
+        self.unsplit_unigrams
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        key: &LayerKey,
+        weight: f64,
+        total_weight: f64,
+        _layout: &Layout,
+    ) -> Option<f64> {
+        Some(self.formula.eval(&[key], weight, total_weight))
+    }
+}
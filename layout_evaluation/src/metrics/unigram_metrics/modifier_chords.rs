@@ -0,0 +1,121 @@
+//! The unigram metric `ModifierChords` models the physical chord that is required for
+//! generating a higher-layer symbol: holding one or more modifiers while pressing the key.
+//! In contrast to the other unigram metrics, it operates on the symbols as they appear in the
+//! text, i.e. before higher-layer symbols are split into their base key and modifiers.
+//!
+//! Each modifier held for a symbol incurs a base cost plus a fraction of the modifier key's own
+//! cost. Chords where the modifier is held with the same hand (or even the same finger) as the key
+//! are penalized with additional factors, chords whose modifier is held with a thumb may be
+//! discounted. Symbols of layers that are usually reached with a layer lock instead of held
+//! modifiers (e.g. a numpad layer) are scaled with a separate factor.
+
+use super::UnigramMetric;
+
+use keyboard_layout::key::Finger;
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Base cost for each modifier that needs to be held.
+    pub modifier_cost: f64,
+    /// The modifier key's cost (as configured for the `Keyboard`) is multiplied with this factor and added.
+    pub modifier_key_cost_factor: f64,
+    /// If the modifier is held with the same hand as the key is pressed, the cost is multiplied with this factor.
+    pub same_hand_factor: f64,
+    /// If the modifier is held with the same finger as the key is pressed, the cost is additionally multiplied with this factor.
+    pub same_finger_factor: f64,
+    /// If the modifier is held with a thumb, the cost is multiplied with this factor.
+    pub thumb_modifier_factor: f64,
+    /// For each additional modifier beyond the first one, the total cost is multiplied with this factor.
+    pub multi_modifier_factor: f64,
+    /// Layers that are usually activated with a layer lock instead of holding the modifiers.
+    pub lock_layers: Vec<usize>,
+    /// The cost of symbols on a layer that is usually locked is multiplied with this factor.
+    pub lock_factor: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModifierChords {
+    modifier_cost: f64,
+    modifier_key_cost_factor: f64,
+    same_hand_factor: f64,
+    same_finger_factor: f64,
+    thumb_modifier_factor: f64,
+    multi_modifier_factor: f64,
+    lock_layers: Vec<usize>,
+    lock_factor: f64,
+}
+
+impl ModifierChords {
+    pub fn new(params: &Parameters) -> Self {
+        Self {
+            modifier_cost: params.modifier_cost,
+            modifier_key_cost_factor: params.modifier_key_cost_factor,
+            same_hand_factor: params.same_hand_factor,
+            same_finger_factor: params.same_finger_factor,
+            thumb_modifier_factor: params.thumb_modifier_factor,
+            multi_modifier_factor: params.multi_modifier_factor,
+            lock_layers: params.lock_layers.clone(),
+            lock_factor: params.lock_factor,
+        }
+    }
+}
+
+impl UnigramMetric for ModifierChords {
+    fn name(&self) -> &str {
+        "Modifier Chords"
+    }
+
+    fn requires_unsplit_unigrams(&self) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        key: &LayerKey,
+        weight: f64,
+        _total_weight: f64,
+        layout: &Layout,
+    ) -> Option<f64> {
+        if key.modifiers.is_empty() {
+            return Some(0.0);
+        }
+
+        let mut cost: f64 = key
+            .modifiers
+            .iter()
+            .map(|mod_idx| {
+                let modifier = layout.get_layerkey(mod_idx);
+                let mut mod_cost =
+                    self.modifier_cost + self.modifier_key_cost_factor * modifier.key.cost;
+
+                if modifier.key.hand == key.key.hand {
+                    mod_cost *= self.same_hand_factor;
+
+                    if modifier.key.finger == key.key.finger {
+                        mod_cost *= self.same_finger_factor;
+                    }
+                }
+
+                if modifier.key.finger == Finger::Thumb {
+                    mod_cost *= self.thumb_modifier_factor;
+                }
+
+                mod_cost
+            })
+            .sum();
+
+        cost *= self
+            .multi_modifier_factor
+            .powi(key.modifiers.len() as i32 - 1);
+
+        if self.lock_layers.contains(&key.layer) {
+            cost *= self.lock_factor;
+        }
+
+        Some(weight * cost)
+    }
+}
@@ -0,0 +1,114 @@
+//! The unigram metric `ThumbStretch` incurs a cost for keystrokes on thumb keys that are located
+//! away from the thumb's resting position, e.g. the outer keys of a thumb cluster.
+//!
+//! The resting position of each thumb is taken to be the (mean) position of the thumb keys of that
+//! hand with the lowest key cost. Keystrokes on thumb keys further away than a threshold incur a
+//! cost that increases with the distance. The resting positions are determined once, when the
+//! metric is first applied (the keyboard does not change between evaluations).
+
+use super::UnigramMetric;
+
+use keyboard_layout::key::{Finger, Hand, Position};
+use keyboard_layout::keyboard::Keyboard;
+use keyboard_layout::layout::{LayerKey, Layout};
+
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Parameters {
+    /// Distances from the thumb's resting position up to this threshold incur no cost.
+    pub threshold: f64,
+    /// Cost per (unit) distance beyond the threshold.
+    pub stretch_cost: f64,
+    /// The distance beyond the threshold is raised to this power.
+    pub exponent: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ThumbStretch {
+    threshold: f64,
+    stretch_cost: f64,
+    exponent: f64,
+    /// Resting positions of the left and the right thumb
+    rest_positions: OnceLock<[Option<Position>; 2]>,
+}
+
+impl ThumbStretch {
+    pub fn new(params: &Parameters) -> Self {
+        Self {
+            threshold: params.threshold,
+            stretch_cost: params.stretch_cost,
+            exponent: params.exponent,
+            rest_positions: OnceLock::new(),
+        }
+    }
+
+    fn rest_position(&self, keyboard: &Keyboard, hand: Hand) -> Option<Position> {
+        let rest_positions = self.rest_positions.get_or_init(|| {
+            [
+                thumb_rest_position(keyboard, Hand::Left),
+                thumb_rest_position(keyboard, Hand::Right),
+            ]
+        });
+
+        match hand {
+            Hand::Left => rest_positions[0],
+            Hand::Right => rest_positions[1],
+        }
+    }
+}
+
+/// Mean position of the lowest-cost thumb keys of the given hand.
+fn thumb_rest_position(keyboard: &Keyboard, hand: Hand) -> Option<Position> {
+    let thumb_keys = keyboard
+        .keys
+        .iter()
+        .filter(|k| k.hand == hand && k.finger == Finger::Thumb);
+
+    let min_cost = thumb_keys
+        .clone()
+        .map(|k| k.cost)
+        .fold(f64::INFINITY, f64::min);
+    let rest_keys: Vec<Position> = thumb_keys
+        .filter(|k| k.cost <= min_cost)
+        .map(|k| k.position)
+        .collect();
+
+    if rest_keys.is_empty() {
+        return None;
+    }
+
+    let n = rest_keys.len() as f64;
+    Some(Position(
+        rest_keys.iter().map(|p| p.0).sum::<f64>() / n,
+        rest_keys.iter().map(|p| p.1).sum::<f64>() / n,
+    ))
+}
+
+impl UnigramMetric for ThumbStretch {
+    fn name(&self) -> &str {
+        "Thumb Stretch"
+    }
+
+    #[inline(always)]
+    fn individual_cost(
+        &self,
+        key: &LayerKey,
+        weight: f64,
+        _total_weight: f64,
+        layout: &Layout,
+    ) -> Option<f64> {
+        if key.key.finger != Finger::Thumb {
+            return Some(0.0);
+        }
+
+        let rest_position = self.rest_position(&layout.keyboard, key.key.hand)?;
+        let stretch = key.key.position.distance(&rest_position) - self.threshold;
+        if stretch <= 0.0 {
+            return Some(0.0);
+        }
+
+        Some(weight * self.stretch_cost * stretch.powf(self.exponent))
+    }
+}
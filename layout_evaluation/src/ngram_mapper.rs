@@ -59,6 +59,9 @@ pub struct MappedNgrams<'s> {
     pub unigrams_not_found: f64,
     /// Total weight (frequencies) of unigrams that can be generated by the layout
     pub unigrams_found: f64,
+    /// Unigrams in terms of `LayerKey`s without splitting higher-layer symbols into base keys and modifiers
+    /// (empty unless requested)
+    pub unsplit_unigrams: Vec<(&'s LayerKey, f64)>,
     /// Bigrams in terms of `LayerKey`s
     pub bigrams: Vec<((&'s LayerKey, &'s LayerKey), f64)>,
     /// Total weight (frequencies) of bigrams that can not be generated by the layout
@@ -75,7 +78,9 @@ pub struct MappedNgrams<'s> {
 
 /// Provides ngrams in terms of a `Layout`'s `LayerKey`s.
 pub trait NgramMapper: Send + Sync + NgramMapperClone + std::fmt::Debug {
-    fn mapped_ngrams<'s>(&self, layout: &'s Layout) -> MappedNgrams<'s>;
+    /// Map the ngrams to the layout's `LayerKey`s. The unsplit unigrams are only generated if
+    /// `unsplit_unigrams` is set, as most metrics do not need them.
+    fn mapped_ngrams<'s>(&self, layout: &'s Layout, unsplit_unigrams: bool) -> MappedNgrams<'s>;
 }
 
 // in order to implement clone for Box<dyn LayoutMetric>, the following trick is necessary
@@ -85,7 +85,7 @@ fn groupby_sum<T: Clone + Eq + std::hash::Hash>(data: &[(T, f64)]) -> Vec<(T, f6
 }
 
 impl NgramMapper for OnDemandNgramMapper {
-    fn mapped_ngrams<'s>(&self, layout: &'s Layout) -> MappedNgrams<'s> {
+    fn mapped_ngrams<'s>(&self, layout: &'s Layout, unsplit_unigrams: bool) -> MappedNgrams<'s> {
         // map char-based unigrams to LayerKeyIndex
         let (unigram_key_indices, unigrams_found, unigrams_not_found) =
             self.unigram_mapper.layerkey_indices(layout);
@@ -94,6 +94,15 @@ impl NgramMapper for OnDemandNgramMapper {
         // map LayerKeyIndex to &LayerKey
         let unigrams = OnDemandUnigramMapper::layerkeys(&unigram_key_indices, &layout);
 
+        // some metrics consider the chords required for higher-layer symbols and need the unsplit unigrams
+        let unsplit_unigrams = if unsplit_unigrams {
+            let unsplit_unigram_key_indices = self.unigram_mapper.unsplit_layerkey_indices(layout);
+            let unsplit_unigram_key_indices = groupby_sum(&unsplit_unigram_key_indices);
+            OnDemandUnigramMapper::layerkeys(&unsplit_unigram_key_indices, layout)
+        } else {
+            Vec::new()
+        };
+
         // map trigrams before bigrams because secondary bigrams from trigrams map be added
         // map char-based trigrams to LayerKeyIndex
         let (trigram_key_indices, trigrams_found, trigrams_not_found) =
@@ -152,6 +161,7 @@ impl NgramMapper for OnDemandNgramMapper {
             unigrams,
             unigrams_found,
             unigrams_not_found,
+            unsplit_unigrams,
             bigrams,
             bigrams_found,
             bigrams_not_found,
@@ -59,6 +59,13 @@ impl OnDemandUnigramMapper {
         (unigram_keys, found_weight, not_found_weight)
     }
 
+    /// For a given `Layout` generate `LayerKeyIndex`-based unigrams without resolving modifiers for higher-layer symbols.
+    pub fn unsplit_layerkey_indices(&self, layout: &Layout) -> UnigramIndices {
+        let (unigram_keys, _not_found_weight) = mapped_unigrams(&self.unigrams, layout);
+
+        unigram_keys
+    }
+
     /// Resolve `&LayerKey` references for `LayerKeyIndex`
     pub fn layerkeys<'s>(
         unigrams: &UnigramIndices,
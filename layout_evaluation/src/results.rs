@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 /// The `NormalizationType` specifies how the total cost of a metric evaluation shall be normalized.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", content = "value")]
 #[serde(rename_all = "snake_case")]
 pub enum NormalizationType {
@@ -17,6 +18,7 @@ pub enum NormalizationType {
 
 /// Specify which data a metric operates on.
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum MetricType {
     Layout,
     Unigram,
@@ -26,6 +28,7 @@ pub enum MetricType {
 
 /// Describes the result of an individual metric evaluation.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MetricResult {
     /// Name of the metric.
     pub name: String,
@@ -42,6 +45,7 @@ pub struct MetricResult {
 /// Describes the normalized results of an individual metric evaluation
 /// taking into account the total found/not found ngram weights.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct NormalizedMetricResult {
     pub core: MetricResult,
     pub weighted_cost: f64,
@@ -50,6 +54,7 @@ pub struct NormalizedMetricResult {
 
 /// Describes a list of metric evaluation results of the same `MetricType`.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MetricResults {
     /// Type of the metric, i.e. which data the metrics operated on.
     pub metric_type: MetricType,
@@ -108,7 +113,7 @@ impl MetricResults {
     }
 
     /// Normalize a metric's cost value with given normalization strategy.
-    fn normalize_value(&self, val: f64, normalization_type: &NormalizationType) -> f64 {
+    pub fn normalize_value(&self, val: f64, normalization_type: &NormalizationType) -> f64 {
         let mut res = match normalization_type {
             NormalizationType::Fixed(t) => val / t,
             NormalizationType::WeightFound(t) => val / (t * self.found_weight),
@@ -162,6 +167,7 @@ impl MetricResults {
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct EvaluationResult {
     individual_results: Vec<MetricResults>,
 }
@@ -0,0 +1,183 @@
+//! The `typing_log` module provides functionality for deriving measured bigram latencies from
+//! keystroke logs.
+//!
+//! A keystroke log lists the typed keys together with a timestamp (in milliseconds), either as CSV
+//! (`key,time` per line, an optional header is skipped) or as JSONL (`{"key": "a", "time": 1234.5}`
+//! per line). Keys are either single symbols or the names `space`, `enter`, and `tab`. Other
+//! named keys (e.g. `backspace`) interrupt the sequence of keystrokes.
+//!
+//! The logs are mapped to the physical keys of the layout that was used for typing. The resulting
+//! latencies are stored per pair of matrix positions, so they can be transferred to other layouts
+//! for the same keyboard.
+
+use keyboard_layout::key::MatrixPosition;
+use keyboard_layout::layout::Layout;
+
+use anyhow::{anyhow, Context, Result};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// A single keystroke of a keystroke log.
+#[derive(Clone, Deserialize, Debug)]
+pub struct Keystroke {
+    /// The typed symbol or the name of the pressed key.
+    pub key: String,
+    /// Timestamp in milliseconds.
+    pub time: f64,
+}
+
+impl Keystroke {
+    /// The symbol generated by the keystroke (if any).
+    fn symbol(&self) -> Option<char> {
+        let mut chars = self.key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => match self.key.to_lowercase().as_str() {
+                "space" => Some(' '),
+                "enter" | "return" => Some('\n'),
+                "tab" => Some('\t'),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Parse a line of a CSV keystroke log. The key may contain commas if it is quoted.
+fn parse_csv_line(line: &str) -> Option<Keystroke> {
+    let (key, time) = line.rsplit_once(',')?;
+    let key = key.trim();
+    let key = key
+        .strip_prefix('"')
+        .and_then(|k| k.strip_suffix('"'))
+        .unwrap_or(key);
+
+    Some(Keystroke {
+        key: key.replace("\"\"", "\""),
+        time: time.trim().parse().ok()?,
+    })
+}
+
+/// Read a keystroke log from a CSV or JSONL file (depending on the file extension).
+pub fn read_keystrokes(filename: &str) -> Result<Vec<Keystroke>> {
+    let content = std::fs::read_to_string(filename)
+        .with_context(|| format!("Could not read keystroke log {}", filename))?;
+    let is_jsonl = filename.ends_with(".jsonl") || filename.ends_with(".json");
+
+    let mut keystrokes = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if is_jsonl {
+            let keystroke = serde_json::from_str(line)
+                .with_context(|| format!("Invalid keystroke in line {} of {}", i + 1, filename))?;
+            keystrokes.push(keystroke);
+        } else {
+            match parse_csv_line(line) {
+                Some(keystroke) => keystrokes.push(keystroke),
+                // a header line is skipped
+                None if i == 0 => continue,
+                None => {
+                    return Err(anyhow!(
+                        "Invalid keystroke in line {} of {}: '{}'",
+                        i + 1,
+                        filename,
+                        line
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(keystrokes)
+}
+
+/// The measured latency between pressing two keys.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct BigramLatency {
+    /// Matrix position of the first key.
+    pub from: MatrixPosition,
+    /// Matrix position of the second key.
+    pub to: MatrixPosition,
+    /// Median of the measured latencies in milliseconds.
+    pub latency: f64,
+    /// Number of measurements.
+    pub count: usize,
+}
+
+/// Measured latencies for pairs of keys (identified by their matrix positions).
+#[derive(Clone, Default, Deserialize, Serialize, Debug)]
+pub struct BigramLatencies {
+    pub latencies: Vec<BigramLatency>,
+}
+
+impl BigramLatencies {
+    /// Derive the latencies from a keystroke log that has been typed with the given layout.
+    ///
+    /// Consecutive keystrokes with a pause longer than `max_pause` (in milliseconds) are not
+    /// considered a bigram. Symbols that require modifiers are skipped as their timing includes
+    /// pressing the modifiers.
+    pub fn from_keystrokes(keystrokes: &[Keystroke], layout: &Layout, max_pause: f64) -> Self {
+        let mut measurements: FxHashMap<(MatrixPosition, MatrixPosition), Vec<f64>> =
+            FxHashMap::default();
+
+        let positions: Vec<Option<(MatrixPosition, f64)>> = keystrokes
+            .iter()
+            .map(|k| {
+                let layerkey = layout.get_layerkey_for_symbol(&k.symbol()?)?;
+                if !layerkey.modifiers.is_empty() {
+                    return None;
+                }
+
+                Some((layerkey.key.matrix_position, k.time))
+            })
+            .collect();
+
+        positions.windows(2).for_each(|w| {
+            if let [Some((from, t1)), Some((to, t2))] = w {
+                let latency = t2 - t1;
+                if latency >= 0.0 && latency <= max_pause {
+                    measurements.entry((*from, *to)).or_default().push(latency);
+                }
+            }
+        });
+
+        let mut latencies: Vec<BigramLatency> = measurements
+            .into_iter()
+            .map(|((from, to), mut values)| {
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let mid = values.len() / 2;
+                let latency = if values.len() % 2 == 0 {
+                    (values[mid - 1] + values[mid]) / 2.0
+                } else {
+                    values[mid]
+                };
+
+                BigramLatency {
+                    from,
+                    to,
+                    latency,
+                    count: values.len(),
+                }
+            })
+            .collect();
+        latencies.sort_by_key(|l| (l.from.1, l.from.0, l.to.1, l.to.0));
+
+        Self { latencies }
+    }
+
+    /// Read latencies from a YAML file.
+    pub fn from_yaml(filename: &str) -> Result<Self> {
+        let f = std::fs::File::open(filename)
+            .with_context(|| format!("Could not open latencies file {}", filename))?;
+        Ok(serde_yaml::from_reader(f)?)
+    }
+
+    /// Write latencies to a YAML file.
+    pub fn save_yaml(&self, filename: &str) -> Result<()> {
+        let f = std::fs::File::create(filename)
+            .with_context(|| format!("Could not create latencies file {}", filename))?;
+        Ok(serde_yaml::to_writer(f, self)?)
+    }
+}
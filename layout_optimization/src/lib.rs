@@ -1,4 +1,5 @@
 pub mod common;
+pub mod observer;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,39 @@
+//! The `observer` module provides an interface for watching optimizations. All optimizers emit the
+//! same structured progress events, so that a consumer (e.g. a console logger or a webservice
+//! streaming them to a browser) does not depend on the optimizer used.
+
+use serde::Serialize;
+
+/// Progress of an optimization after one of its iterations.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProgressEvent {
+    /// Number of completed iterations (generations for genetic algorithms)
+    pub iteration: u64,
+    /// Current layout (best of the generation for genetic algorithms)
+    pub layout: String,
+    pub cost: f64,
+    /// Best layout found so far
+    pub best_layout: String,
+    pub best_cost: f64,
+    /// Whether a new best layout was found in this iteration
+    pub new_best: bool,
+    /// Current temperature (simulated annealing only)
+    pub temperature: Option<f64>,
+}
+
+/// Receives the progress events of an optimization.
+pub trait OptimizationObserver: Send + Sync {
+    fn notify(&self, event: &ProgressEvent);
+
+    /// Whether the optimization shall stop early (returning the best layout found so far).
+    fn should_stop(&self) -> bool {
+        false
+    }
+}
+
+/// An observer that ignores all events.
+pub struct NoObserver;
+
+impl OptimizationObserver for NoObserver {
+    fn notify(&self, _event: &ProgressEvent) {}
+}
@@ -3,16 +3,19 @@ use keyboard_layout::layout_generator::NeoLayoutGenerator;
 use layout_evaluation::evaluation::Evaluator;
 
 use layout_optimization::common::{Cache, PermutationLayoutGenerator};
+use layout_optimization::observer::{OptimizationObserver, ProgressEvent};
 
 use anyhow::Result;
-use serde::Deserialize;
-use std::sync::{mpsc::Receiver, Arc};
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc::channel, Arc};
+use std::thread;
+use std::time::Duration;
 use std::usize;
 
 use abc::{scaling, Candidate, Context, HiveBuilder};
 use rand::{seq::SliceRandom, thread_rng};
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Parameters {
     retries: usize,
     n_switches: usize,
@@ -95,35 +98,101 @@ impl Context for FitnessCalc {
     }
 }
 
-pub fn optimize(
+/// The cost corresponding to a fitness value (the inverse of
+/// [EvaluationResult::optimization_score](layout_evaluation::results::EvaluationResult::optimization_score)
+/// up to rounding).
+fn cost_from_fitness(fitness: f64) -> f64 {
+    1e8 / fitness
+}
+
+/// The layout to optimize and how to evaluate it.
+pub struct OptimizationOptions<'a> {
+    pub evaluator: &'a Evaluator,
+    pub layout_generator: &'a NeoLayoutGenerator,
+    /// Layout to take the keys and fixed positions from
+    pub layout_str: &'a str,
+    pub fixed_characters: &'a str,
+    pub cache_results: bool,
+}
+
+/// How often the observer is asked whether the optimization shall stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs the artificial bee colony, reporting each new best layout to `observer`, until the
+/// observer asks to stop. Then returns the best layout found and the number of rounds that were
+/// run.
+pub fn optimize_with_observer(
     params: &Parameters,
-    evaluator: &Evaluator,
-    layout_str: &str,
-    layout_generator: &NeoLayoutGenerator,
-    fixed_characters: &str,
-    cache_results: bool,
-) -> Receiver<Candidate<Layout>> {
-    let pm = PermutationLayoutGenerator::new(layout_str, fixed_characters, layout_generator);
-
-    let result_cache = if cache_results {
+    options: &OptimizationOptions,
+    observer: &dyn OptimizationObserver,
+) -> (Layout, u64) {
+    let pm = PermutationLayoutGenerator::new(
+        options.layout_str,
+        options.fixed_characters,
+        options.layout_generator,
+    );
+
+    let result_cache = if options.cache_results {
         Some(Cache::new())
     } else {
         None
     };
 
     let core = FitnessCalc {
-        evaluator: Arc::new(evaluator.clone()),
-        layout_generator: pm.clone(),
+        evaluator: Arc::new(options.evaluator.clone()),
+        layout_generator: pm,
         result_cache,
         n_switches: params.n_switches,
     };
 
     let ncpus = num_cpus::get();
-    let hive = HiveBuilder::<FitnessCalc>::new(core, ncpus)
+    let mut hive = HiveBuilder::<FitnessCalc>::new(core, ncpus)
         .set_threads(ncpus)
         .set_retries(params.retries)
-        .set_scaling(scaling::proportionate());
-    // .set_scaling(scaling::power_rank(10_f64));
+        .set_scaling(scaling::proportionate())
+        // .set_scaling(scaling::power_rank(10_f64));
+        .build()
+        .unwrap();
+
+    let (sender, receiver) = channel();
+    hive.set_sender(sender);
+    let hive = Arc::new(hive);
+    let runner = {
+        let hive = hive.clone();
+        thread::spawn(move || hive.run_forever())
+    };
+
+    // the hive forgets its round once it stopped
+    let mut rounds = 0;
+    while !runner.is_finished() {
+        if observer.should_stop() {
+            // the hive ignores this if it did not start yet, so it is repeated until it stopped
+            hive.stop().unwrap();
+        }
+
+        let received = receiver.recv_timeout(STOP_POLL_INTERVAL);
+        if let Ok(Some(round)) = hive.get_round() {
+            rounds = round as u64;
+        }
+        if let Ok(new_best) = received {
+            let layout = new_best.solution.as_text();
+            let cost = cost_from_fitness(new_best.fitness);
+            observer.notify(&ProgressEvent {
+                iteration: rounds,
+                layout: layout.clone(),
+                cost,
+                best_layout: layout,
+                best_cost: cost,
+                new_best: true,
+                temperature: None,
+            });
+        }
+    }
+
+    if let Err(error) = runner.join().unwrap() {
+        log::error!("Optimization failed: {:?}", error);
+    }
 
-    hive.build().unwrap().stream()
+    let layout = hive.get().map(|best| best.solution.clone()).unwrap();
+    (layout, rounds)
 }
@@ -3,6 +3,7 @@ use keyboard_layout::layout_generator::NeoLayoutGenerator;
 use layout_evaluation::evaluation::Evaluator;
 
 use layout_optimization::common::{Cache, PermutationLayoutGenerator};
+use layout_optimization::observer::{OptimizationObserver, ProgressEvent};
 
 use anyhow::Result;
 use serde::{Serialize, Deserialize};
@@ -59,6 +60,13 @@ pub struct FitnessCalc {
     result_cache: Option<Cache<usize>>,
 }
 
+/// The cost corresponding to a fitness value (the inverse of
+/// [EvaluationResult::optimization_score](layout_evaluation::results::EvaluationResult::optimization_score)
+/// up to rounding).
+fn cost_from_fitness(fitness: usize) -> f64 {
+    1e8 / fitness as f64
+}
+
 impl FitnessFunction<Genotype, usize> for FitnessCalc {
     fn fitness_of(&self, genome: &Genotype) -> usize {
         let l = self.layout_generator.generate_layout(genome);
@@ -218,6 +226,46 @@ pub fn init_optimization(
     (sim, pm)
 }
 
+/// An observer printing the progress of an optimization to stdout.
+struct PrintObserver<'a> {
+    evaluator: &'a Evaluator,
+    layout_generator: &'a NeoLayoutGenerator,
+}
+
+impl OptimizationObserver for PrintObserver<'_> {
+    fn notify(&self, event: &ProgressEvent) {
+        if event.new_best {
+            if let Ok(layout) = self.layout_generator.generate(&event.best_layout) {
+                let evaluation_result = self.evaluator.evaluate_layout(&layout);
+                println!(
+                    "New best:\n{}\n\n{}\n{}",
+                    layout,
+                    layout.plot_compact(),
+                    layout.plot(),
+                );
+                println!("{}", evaluation_result);
+            }
+        }
+        println!(
+            "Step: generation: {}, generation's best cost: {:.1}, all time best cost: {:.1}, generation's best: {}",
+            event.iteration, event.cost, event.best_cost, event.layout,
+        );
+    }
+}
+
+/// The layout to optimize and how to evaluate it.
+pub struct OptimizationOptions<'a> {
+    pub evaluator: &'a Evaluator,
+    pub layout_generator: &'a NeoLayoutGenerator,
+    /// Layout to start from (if `start_with_layout`) or to take the keys and fixed positions from
+    pub layout_str: &'a str,
+    pub fixed_characters: &'a str,
+    pub start_with_layout: bool,
+    pub cache_results: bool,
+}
+
+/// Runs the genetic algorithm, printing its progress, then returns the best layout found and the
+/// number of generations that were run.
 pub fn optimize(
     params: &Parameters,
     evaluator: &Evaluator,
@@ -226,82 +274,85 @@ pub fn optimize(
     fixed_characters: &str,
     start_with_layout: bool,
     cache_results: bool,
-) -> Layout {
-    let (mut sim, pm) = init_optimization(
-        params,
+) -> (Layout, u64) {
+    let observer = PrintObserver {
         evaluator,
-        layout_str,
         layout_generator,
+    };
+    let options = OptimizationOptions {
+        evaluator,
+        layout_generator,
+        layout_str,
         fixed_characters,
         start_with_layout,
         cache_results,
+    };
+    let (layout, generations) = optimize_with_observer(params, &options, &observer);
+
+    println!("\nFinal result:\n{}", layout);
+    println!("\n{}", layout.plot_compact());
+    println!("\n{}", layout.plot());
+
+    (layout, generations)
+}
+
+/// Runs the genetic algorithm, reporting the progress of each generation to `observer`, then
+/// returns the best layout found and the number of generations that were run. The run stops
+/// early if the observer asks for it.
+pub fn optimize_with_observer(
+    params: &Parameters,
+    options: &OptimizationOptions,
+    observer: &dyn OptimizationObserver,
+) -> (Layout, u64) {
+    let (mut sim, pm) = init_optimization(
+        params,
+        options.evaluator,
+        options.layout_str,
+        options.layout_generator,
+        options.fixed_characters,
+        options.start_with_layout,
+        options.cache_results,
     );
 
     log::info!("Starting optimization with: {:?}", params);
     let mut all_time_best: Option<(usize, Genotype)> = None;
+    let mut generations = 0;
 
-    loop {
+    while !observer.should_stop() {
         let result = sim.step();
         match result {
             Ok(SimResult::Intermediate(step)) => {
-                let evaluated_population = step.result.evaluated_population;
+                generations = step.iteration;
                 let best_solution = step.result.best_solution;
-                if let Some(king) = &all_time_best {
-                    if best_solution.solution.fitness > king.0 {
-                        let layout = pm.generate_layout(&best_solution.solution.genome);
-                        let evaluation_result = evaluator.evaluate_layout(&layout);
-                        println!(
-                            "New best:\n{}\n\n{}\n{}",
-                            layout,
-                            layout.plot_compact(),
-                            layout.plot(),
-                        );
-                        println!("{}", evaluation_result);
-
-                        all_time_best = Some((
-                            best_solution.solution.fitness,
-                            best_solution.solution.genome.clone(),
-                        ));
-                    }
-                } else {
-                    all_time_best = Some((
-                        best_solution.solution.fitness,
-                        best_solution.solution.genome.clone(),
-                    ));
+                let fitness = best_solution.solution.fitness;
+
+                let new_best = match &all_time_best {
+                    Some(king) => fitness > king.0,
+                    None => true,
+                };
+                if new_best {
+                    all_time_best = Some((fitness, best_solution.solution.genome.clone()));
                 }
-                println!(
-                    "Step: generation: {}, average_fitness: {}, \
-                     best fitness: {}, all time best: {}, duration: {}, processing_time: {}, generation's best: {}",
-                    step.iteration,
-                    evaluated_population.average_fitness(),
-                    best_solution.solution.fitness,
-                    all_time_best.as_ref().unwrap().0,
-                    step.duration.fmt(),
-                    step.processing_time.fmt(),
-                    pm.generate_layout(&best_solution.solution.genome)
-                );
+                let (best_fitness, best_genome) = all_time_best.as_ref().unwrap();
+
+                observer.notify(&ProgressEvent {
+                    iteration: step.iteration,
+                    layout: pm.generate_string(&best_solution.solution.genome),
+                    cost: cost_from_fitness(fitness),
+                    best_layout: pm.generate_string(best_genome),
+                    best_cost: cost_from_fitness(*best_fitness),
+                    new_best,
+                    temperature: None,
+                });
             }
             Ok(SimResult::Final(step, processing_time, duration, stop_reason)) => {
-                println!("{}", stop_reason);
-                println!(
-                    "Final result after {}: generation: {}, processing_time: {}",
-                    duration.fmt(),
+                generations = step.iteration;
+                log::info!(
+                    "{}: final generation: {}, processing_time: {}, duration: {}",
+                    stop_reason,
                     step.iteration,
-                    processing_time.fmt()
-                );
-                println!(
-                    "\n{}",
-                    pm.generate_layout(&all_time_best.as_ref().unwrap().1)
-                );
-                println!(
-                    "\n{}",
-                    pm.generate_layout(&all_time_best.as_ref().unwrap().1)
-                        .plot_compact()
-                );
-                println!(
-                    "\n{}",
-                    pm.generate_layout(&all_time_best.as_ref().unwrap().1)
-                        .plot()
+                    processing_time.fmt(),
+                    duration.fmt(),
                 );
                 break;
             }
@@ -312,5 +363,11 @@ pub fn optimize(
         }
     }
 
-    pm.generate_layout(&all_time_best.as_ref().unwrap().1)
+    let layout = match all_time_best {
+        Some((_, genome)) => pm.generate_layout(&genome),
+        // stopped before the first generation
+        None => pm.generate_layout(&pm.get_permutable_indices()),
+    };
+
+    (layout, generations)
 }
@@ -3,16 +3,20 @@ use keyboard_layout::layout_generator::NeoLayoutGenerator;
 use layout_evaluation::evaluation::Evaluator;
 
 use layout_optimization::common::{Cache, PermutationLayoutGenerator};
+use layout_optimization::observer::{NoObserver, OptimizationObserver, ProgressEvent};
 
 use anyhow::Result;
 use colored::Colorize;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use argmin::prelude::{ArgminKV, ArgminOp, Error, Executor, IterState, Observe, ObserverMode};
+use argmin::prelude::{
+    ArgminIterData, ArgminKV, ArgminOp, Error, Executor, IterState, Observe, ObserverMode,
+    OpWrapper, Solver, TerminationReason,
+};
 use argmin::solver::simulatedannealing::{SATempFunc, SimulatedAnnealing};
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Parameters {
     /// In each modification of the layout, swap this many key-pairs.
     pub key_switches: usize,
@@ -180,6 +184,75 @@ impl Observe<AnnealingStruct<'_>> for IterationObserver {
     }
 }
 
+/// An observer that forwards the progress of each iteration to an [OptimizationObserver].
+struct EventObserver {
+    observer: Arc<dyn OptimizationObserver>,
+    layout_generator: PermutationLayoutGenerator,
+    best_cost: f64,
+}
+
+impl Observe<AnnealingStruct<'_>> for EventObserver {
+    fn observe_iter(
+        &mut self,
+        state: &IterState<AnnealingStruct<'_>>,
+        kv: &ArgminKV,
+    ) -> Result<(), Error> {
+        let temperature = kv
+            .kv
+            .iter()
+            .find(|(key, _)| *key == "t")
+            .and_then(|(_, value)| value.parse().ok());
+        let new_best = state.best_cost < self.best_cost;
+        self.best_cost = state.best_cost;
+
+        self.observer.notify(&ProgressEvent {
+            iteration: state.iter + 1,
+            layout: self.layout_generator.generate_string(&state.param),
+            cost: state.cost,
+            best_layout: self.layout_generator.generate_string(&state.best_param),
+            best_cost: state.best_cost,
+            new_best,
+            temperature,
+        });
+        Ok(())
+    }
+}
+
+/// A solver that stops the wrapped solver as soon as the observer asks for it.
+#[derive(Serialize)]
+struct StoppableSolver<S> {
+    solver: S,
+    #[serde(skip)]
+    observer: Arc<dyn OptimizationObserver>,
+}
+
+impl<O: ArgminOp, S: Solver<O>> Solver<O> for StoppableSolver<S> {
+    const NAME: &'static str = S::NAME;
+
+    fn next_iter(
+        &mut self,
+        op: &mut OpWrapper<O>,
+        state: &IterState<O>,
+    ) -> Result<ArgminIterData<O>, Error> {
+        self.solver.next_iter(op, state)
+    }
+
+    fn init(
+        &mut self,
+        op: &mut OpWrapper<O>,
+        state: &IterState<O>,
+    ) -> Result<Option<ArgminIterData<O>>, Error> {
+        self.solver.init(op, state)
+    }
+
+    fn terminate(&mut self, state: &IterState<O>) -> TerminationReason {
+        if self.observer.should_stop() {
+            return TerminationReason::Aborted;
+        }
+        self.solver.terminate(state)
+    }
+}
+
 /// Calculates the [Standard Deviation](https://en.wikipedia.org/wiki/Standard_deviation)
 /// for the cost of some amount of Layouts, then returns it.
 ///
@@ -218,7 +291,24 @@ fn get_cost_sd(
     sd
 }
 
-/// Performs one run of Simulated Annealing, then returns the best layout found.
+/// The layout to optimize and how to evaluate and log it.
+pub struct OptimizationOptions<'a> {
+    /// Name of the run in the log messages
+    pub process_name: &'a str,
+    pub evaluator: &'a Evaluator,
+    pub layout_generator: &'a NeoLayoutGenerator,
+    /// Layout to start from (if `start_with_layout`) or to take the keys and fixed positions from
+    pub layout_str: &'a str,
+    pub fixed_characters: &'a str,
+    pub start_with_layout: bool,
+    /// Initial temperature (calculated from the costs of random neighbors if not given)
+    pub init_temp: Option<f64>,
+    pub log_everything: bool,
+    pub result_cache: Option<Cache<f64>>,
+}
+
+/// Performs one run of Simulated Annealing, then returns the best layout found and the number of
+/// iterations that were run.
 pub fn optimize(
     process_name: &str,
     params: &Parameters,
@@ -230,7 +320,40 @@ pub fn optimize(
     optional_init_temp: Option<f64>,
     log_everything: bool,
     result_cache: Option<Cache<f64>>,
-) -> Layout {
+) -> (Layout, u64) {
+    let options = OptimizationOptions {
+        process_name,
+        evaluator,
+        layout_generator,
+        layout_str,
+        fixed_characters,
+        start_with_layout,
+        init_temp: optional_init_temp,
+        log_everything,
+        result_cache,
+    };
+    optimize_with_observer(params, options, Arc::new(NoObserver))
+}
+
+/// Performs one run of Simulated Annealing, reporting the progress of each iteration to
+/// `observer`, then returns the best layout found and the number of iterations that were run. The
+/// run stops early if the observer asks for it.
+pub fn optimize_with_observer(
+    params: &Parameters,
+    options: OptimizationOptions,
+    observer: Arc<dyn OptimizationObserver>,
+) -> (Layout, u64) {
+    let OptimizationOptions {
+        process_name,
+        evaluator,
+        layout_generator,
+        layout_str,
+        fixed_characters,
+        start_with_layout,
+        init_temp: optional_init_temp,
+        log_everything,
+        result_cache,
+    } = options;
     let pm = PermutationLayoutGenerator::new(layout_str, fixed_characters, layout_generator);
     // Get initial Layout.
     let init_layout = match start_with_layout {
@@ -272,6 +395,10 @@ pub fn optimize(
         /////////////////////////
         // Optional: stop if there was no accepted solution after [params.stall_accepted] iterations
         .stall_accepted(params.stall_accepted);
+    let solver = StoppableSolver {
+        solver,
+        observer: observer.clone(),
+    };
 
     let best_observer = BestObserver {
         id: process_name.to_string(),
@@ -282,6 +409,11 @@ pub fn optimize(
         layout_generator: pm.clone(),
         log_everything,
     };
+    let event_observer = EventObserver {
+        observer,
+        layout_generator: pm.clone(),
+        best_cost: f64::INFINITY,
+    };
     let iter_observer_mode = if log_everything {
         ObserverMode::Always
     } else {
@@ -299,11 +431,15 @@ pub fn optimize(
         // Optional: Attach a observer
         .add_observer(best_observer, ObserverMode::NewBest)
         .add_observer(iter_observer, iter_observer_mode)
+        .add_observer(event_observer, ObserverMode::Always)
         // Optional: Set maximum number of iterations (defaults to `std::u64::MAX`)
         .max_iters(params.max_iters)
         .run()
         .unwrap();
 
     let best_layout_param = res.state().get_best_param();
-    pm.generate_layout(&best_layout_param)
+    (
+        pm.generate_layout(&best_layout_param),
+        res.state().get_iter(),
+    )
 }
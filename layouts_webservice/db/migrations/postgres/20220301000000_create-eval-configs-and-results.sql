@@ -0,0 +1,24 @@
+CREATE TABLE eval_configs (
+    id SERIAL PRIMARY KEY,
+    hash VARCHAR NOT NULL UNIQUE,
+    eval_parameters VARCHAR NOT NULL,
+    layout_config VARCHAR NOT NULL,
+    ngrams VARCHAR NOT NULL,
+    created TIMESTAMP
+);
+
+CREATE TABLE results (
+    id SERIAL PRIMARY KEY,
+    layout_id INTEGER NOT NULL REFERENCES layouts(id),
+    config_id INTEGER REFERENCES eval_configs(id),
+    total_cost DOUBLE PRECISION NOT NULL,
+    details_json VARCHAR NOT NULL,
+    printed VARCHAR NOT NULL,
+    created TIMESTAMP
+);
+
+-- the configuration of existing results is unknown
+INSERT INTO results (layout_id, config_id, total_cost, details_json, printed, created)
+    SELECT id, NULL, total_cost, details_json, printed, created FROM layouts;
+
+ALTER TABLE layouts ADD COLUMN config_id INTEGER REFERENCES eval_configs(id);
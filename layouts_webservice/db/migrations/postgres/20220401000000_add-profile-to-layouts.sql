@@ -0,0 +1,2 @@
+-- existing layouts were evaluated with the standard keyboard
+ALTER TABLE layouts ADD COLUMN profile VARCHAR NOT NULL DEFAULT 'standard_keyboard';
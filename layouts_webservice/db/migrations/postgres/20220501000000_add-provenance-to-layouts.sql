@@ -0,0 +1,2 @@
+-- how the layout was found (JSON), e.g. by an optimization job of the webservice
+ALTER TABLE layouts ADD COLUMN provenance TEXT;
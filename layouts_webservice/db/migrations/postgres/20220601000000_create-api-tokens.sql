@@ -0,0 +1,8 @@
+-- per-user tokens for the api (only the SHA-256 hash of a token is stored)
+CREATE TABLE api_tokens (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    admin BOOL NOT NULL DEFAULT FALSE,
+    created TIMESTAMP
+);
@@ -0,0 +1,16 @@
+-- weighted costs of the individual metrics of each result, so that layouts can be filtered by them
+CREATE TABLE metric_costs (
+    layout_id INTEGER NOT NULL REFERENCES layouts(id),
+    config_id INTEGER REFERENCES eval_configs(id),
+    name VARCHAR NOT NULL,
+    cost DOUBLE PRECISION NOT NULL
+);
+
+CREATE INDEX metric_costs_layout ON metric_costs (layout_id, config_id, name);
+
+INSERT INTO metric_costs (layout_id, config_id, name, cost)
+    SELECT r.layout_id, r.config_id, mc->'core'->>'name', (mc->>'weighted_cost')::DOUBLE PRECISION
+    FROM results r,
+        json_array_elements(r.details_json::json->'individual_results') ir,
+        json_array_elements(ir->'metric_costs') mc
+    WHERE mc->>'weighted_cost' IS NOT NULL;
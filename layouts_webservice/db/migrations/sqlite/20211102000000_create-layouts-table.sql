@@ -0,0 +1,10 @@
+CREATE TABLE layouts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    layout TEXT NOT NULL,
+    total_cost DOUBLE PRECISION NOT NULL,
+    details_json TEXT NOT NULL,
+    printed TEXT NOT NULL,
+    published_by TEXT,
+    created TIMESTAMP,
+    highlight BOOLEAN
+);
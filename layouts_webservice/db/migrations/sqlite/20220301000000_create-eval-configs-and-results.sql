@@ -0,0 +1,24 @@
+CREATE TABLE eval_configs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    hash TEXT NOT NULL UNIQUE,
+    eval_parameters TEXT NOT NULL,
+    layout_config TEXT NOT NULL,
+    ngrams TEXT NOT NULL,
+    created TIMESTAMP
+);
+
+CREATE TABLE results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    layout_id INTEGER NOT NULL REFERENCES layouts(id),
+    config_id INTEGER REFERENCES eval_configs(id),
+    total_cost DOUBLE PRECISION NOT NULL,
+    details_json TEXT NOT NULL,
+    printed TEXT NOT NULL,
+    created TIMESTAMP
+);
+
+-- the configuration of existing results is unknown
+INSERT INTO results (layout_id, config_id, total_cost, details_json, printed, created)
+    SELECT id, NULL, total_cost, details_json, printed, created FROM layouts;
+
+ALTER TABLE layouts ADD COLUMN config_id INTEGER REFERENCES eval_configs(id);
@@ -0,0 +1,2 @@
+-- existing layouts were evaluated with the standard keyboard
+ALTER TABLE layouts ADD COLUMN profile TEXT NOT NULL DEFAULT 'standard_keyboard';
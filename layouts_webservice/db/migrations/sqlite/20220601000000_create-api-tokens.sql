@@ -0,0 +1,8 @@
+-- per-user tokens for the api (only the SHA-256 hash of a token is stored)
+CREATE TABLE api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    admin BOOLEAN NOT NULL DEFAULT FALSE,
+    created TIMESTAMP
+);
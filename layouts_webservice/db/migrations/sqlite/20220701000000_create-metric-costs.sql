@@ -0,0 +1,16 @@
+-- weighted costs of the individual metrics of each result, so that layouts can be filtered by them
+CREATE TABLE metric_costs (
+    layout_id INTEGER NOT NULL REFERENCES layouts(id),
+    config_id INTEGER REFERENCES eval_configs(id),
+    name TEXT NOT NULL,
+    cost DOUBLE PRECISION NOT NULL
+);
+
+CREATE INDEX metric_costs_layout ON metric_costs (layout_id, config_id, name);
+
+INSERT INTO metric_costs (layout_id, config_id, name, cost)
+    SELECT r.layout_id, r.config_id, json_extract(mc.value, '$.core.name'), json_extract(mc.value, '$.weighted_cost')
+    FROM results r,
+        json_each(r.details_json, '$.individual_results') ir,
+        json_each(ir.value, '$.metric_costs') mc
+    WHERE json_extract(mc.value, '$.weighted_cost') IS NOT NULL;
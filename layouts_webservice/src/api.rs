@@ -1,51 +1,58 @@
 use super::Options;
 
+use super::auth::{self, Admin, Caller, RateLimiter};
+use super::eval_config::ConfigVersions;
+use super::jobs::{JobEvent, JobInfo, Jobs};
+use super::optimization::{OptimizationRequest, OptimizerLimits, OptimizerParameters};
+use super::profiles::{Profile, Profiles};
+use super::storage::{
+    self, ApiTokenDB, EvalConfigDB, LayoutEvaluationDB, LayoutQuery, LayoutStorage,
+};
+use super::transfer;
+
+use rocket::data::{Data, Limits, ToByteUnit};
 use rocket::fairing::{self, AdHoc};
-use rocket::http::Status;
-use rocket::response::status::Created;
-use rocket::serde::{json::Json, Deserialize, Serialize};
-use rocket::State;
+use rocket::http::{ContentType, Header, Status};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::status::{Accepted, Created, NoContent};
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::serde::{
+    json::{self, Json, Value},
+    Deserialize, Serialize,
+};
+use rocket::{Shutdown, State};
 use rocket::{Build, Rocket};
-use rocket_db_pools::{sqlx, Connection, Database};
 
-use keyboard_layout::layout_generator::NeoLayoutGenerator;
-use layout_evaluation::evaluation::Evaluator;
-use layout_evaluation::results::EvaluationResult;
+use schemars::JsonSchema;
+use layouts_webservice_client::api::{
+    EvalConfig, LayoutEvaluation, ListQuery, PostLayout, SortDirection,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
-#[derive(Database)]
-#[database("sqlx")]
-struct Db(sqlx::PgPool);
+type Db = Arc<dyn LayoutStorage>;
 
-// type Result<T, E = rocket::response::Debug<sqlx::Error>> = std::result::Result<T, E>;
 type Result<T, E = Status> = std::result::Result<T, E>;
 
-#[derive(Debug, Clone, Deserialize, Serialize, sqlx::FromRow)]
-#[serde(crate = "rocket::serde")]
-struct LayoutEvaluationDB {
-    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
-    id: Option<i32>,
-    layout: String,
-    total_cost: f64,
-    details_json: String,
-    printed: String,
-    published_by: Option<String>,
-    highlight: bool,
-}
-
-#[derive(Debug, Clone, Serialize)]
-struct LayoutEvaluation {
-    layout: String,
-    total_cost: f64,
-    published_by: Option<String>,
-    details: Option<EvaluationResult>,
-    printed: Option<String>,
-    plot: Option<String>,
-    highlight: bool,
+impl From<EvalConfigDB> for EvalConfig {
+    fn from(item: EvalConfigDB) -> Self {
+        Self {
+            id: item.id,
+            hash: item.hash,
+            eval_parameters: item.eval_parameters,
+            layout_config: item.layout_config,
+            ngrams: item.ngrams,
+        }
+    }
 }
 
 impl From<LayoutEvaluationDB> for LayoutEvaluation {
     fn from(item: LayoutEvaluationDB) -> Self {
         Self {
+            profile: item.profile,
             layout: item.layout,
             total_cost: item.total_cost,
             published_by: item.published_by,
@@ -53,76 +60,137 @@ impl From<LayoutEvaluationDB> for LayoutEvaluation {
             printed: None, //Some(item.printed),
             plot: None,
             highlight: item.highlight,
+            config_version: item.config_id,
+            provenance: item
+                .provenance
+                .and_then(|p| serde_json::from_str(&p).ok()),
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct PostLayout {
-    layout: String,
-    published_by: Option<String>,
-    highlight: Option<bool>,
-    secret: Option<String>,
+/// Check that the caller may perform an admin action, either with an admin token or with the
+/// secret given in the request's body.
+fn is_admin(caller: &Caller, secret: Option<&str>, config: &Options) -> bool {
+    caller.is_admin() || secret.is_some_and(|secret| config.secret == secret)
+}
+
+fn check_rate_limit(rate_limiter: &RateLimiter, caller: &Caller) -> Result<()> {
+    if !rate_limiter.check(caller) {
+        println!("Rate limit exceeded ({:?}).", caller.user.as_ref().map(|u| &u.name));
+        return Err(Status::TooManyRequests);
+    }
+
+    Ok(())
+}
+
+/// The profile named by the first path segment after `/api/` and the version of its current
+/// evaluation configuration. Forwards if there is no such profile, so that the routes of the
+/// default profile (e.g. `/api/<layout>`) match instead.
+struct ProfileParam<'r> {
+    profile: &'r Profile,
+    config_version: i32,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ProfileParam<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, ()> {
+        let profiles = request.rocket().state::<Arc<Profiles>>();
+        let versions = request.rocket().state::<ConfigVersions>();
+        let name = request.param::<&str>(0).and_then(|name| name.ok());
+
+        match (profiles, versions, name) {
+            (Some(profiles), Some(versions), Some(name)) => match profiles.get(name) {
+                Some(profile) => Outcome::Success(ProfileParam {
+                    profile,
+                    config_version: versions.0[&profile.name],
+                }),
+                None => Outcome::Forward(Status::NotFound),
+            },
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
 }
 
 #[options("/")]
 fn cors_preflight() -> () {
 }
 
-#[post("/", data = "<layout>")]
-async fn post(
-    mut db: Connection<Db>,
-    layout: Json<PostLayout>,
-    layout_generator: &State<NeoLayoutGenerator>,
-    evaluator: &State<Evaluator>,
-    config: &State<Options>,
+#[options("/<_>/<_..>")]
+fn cors_preflight_profile(_profile: ProfileParam<'_>) {}
+
+#[allow(clippy::too_many_arguments)]
+async fn post_layout(
+    db: &Db,
+    layout: &PostLayout,
+    profile: &Profile,
+    config_version: i32,
+    config: &Options,
+    caller: &Caller,
+    rate_limiter: &RateLimiter,
 ) -> Result<Created<Json<LayoutEvaluation>>> {
     // check if highlight wants to be set without permission
-    let is_admin = config.secret == layout.secret.clone().unwrap_or("".to_string());
+    let is_admin = is_admin(caller, layout.secret.as_deref(), config);
     let highlight = layout.highlight.unwrap_or(false);
     if highlight && !is_admin {
         return Err(Status::Forbidden);
     };
+    check_rate_limit(rate_limiter, caller)?;
 
     // generate layout
-    let l = layout_generator
+    let l = profile
+        .layout_generator
         .generate(&layout.layout)
         .map_err(|_| Status::BadRequest)?;
     let layout_str = l.as_text();
 
     // check if layout is in database already
-    let result = sqlx::query_as::<_, LayoutEvaluationDB>("SELECT * FROM layouts WHERE layout = $1")
-        .bind(&layout_str)
-        .fetch_one(&mut *db)
-        .await
-        .ok();
+    let result = db.get(&profile.name, &layout_str).await.map_err(|e| {
+        eprintln!("Error while fetching layout from db: {:?}", e);
+        Status::InternalServerError
+    })?;
 
     let result = match result {
         None => {
-            println!("Evaluating new layout: {}", layout_str);
-            let evaluation_result = evaluator.evaluate_layout(&l);
+            println!("Evaluating new layout ({}): {}", profile.name, layout_str);
+            let evaluation_result = profile.evaluator.evaluate_layout(&l);
+
+            // layouts published with a token are credited to its user
+            let user = caller.user.as_ref().map(|user| user.name.clone());
+            let published_by = layout.published_by.clone().or_else(|| user.clone());
+            let mut provenance = match &layout.provenance {
+                Some(provenance) => {
+                    json::to_value(provenance).map_err(|_| Status::InternalServerError)?
+                }
+                None => Value::Object(Default::default()),
+            };
+            if let Some(user) = user {
+                provenance["user"] = Value::String(user);
+            }
+            let provenance = match provenance.as_object() {
+                Some(fields) if fields.is_empty() => None,
+                _ => Some(provenance.to_string()),
+            };
 
             let result = LayoutEvaluationDB {
                 id: None,
+                profile: profile.name.clone(),
                 layout: layout_str,
                 total_cost: evaluation_result.total_cost(),
-                published_by: layout.published_by.clone(),
+                published_by,
                 details_json: serde_json::to_string(&evaluation_result)
                     .map_err(|_| Status::InternalServerError)?,
                 printed: format!("{}", evaluation_result),
                 highlight,
+                config_id: Some(config_version),
+                provenance,
             };
 
-            sqlx::query("INSERT INTO layouts (layout, total_cost, published_by, details_json, printed, highlight, created) VALUES ($1, $2, $3, $4, $5, $6, NOW())")
-                .bind(&result.layout)
-                .bind(&result.total_cost)
-                .bind(&result.published_by)
-                .bind(&result.details_json)
-                .bind(&result.printed)
-                .bind(&result.highlight)
-                .execute(&mut *db)
-                .await
-                .map_err(|_| Status::InternalServerError)?;
+            db.insert(&result).await.map_err(|e| {
+                eprintln!("Error while storing layout in db: {:?}", e);
+                Status::InternalServerError
+            })?;
 
             result
         }
@@ -132,128 +200,644 @@ async fn post(
     Ok(Created::new("/").body(Json(result.into())))
 }
 
-#[get("/")]
-async fn list(mut db: Connection<Db>) -> Result<Json<Vec<LayoutEvaluation>>> {
-    let layouts = sqlx::query_as::<_, LayoutEvaluationDB>(
-        "SELECT NULL AS id, layout, total_cost, published_by, details_json, printed, highlight FROM layouts",
+#[post("/", data = "<layout>")]
+async fn post(
+    db: &State<Db>,
+    layout: Json<PostLayout>,
+    profiles: &State<Arc<Profiles>>,
+    versions: &State<ConfigVersions>,
+    config: &State<Options>,
+    caller: Caller,
+    rate_limiter: &State<RateLimiter>,
+) -> Result<Created<Json<LayoutEvaluation>>> {
+    let profile = profiles.default_profile();
+    let config_version = versions.0[&profile.name];
+    post_layout(db, &layout, profile, config_version, config, &caller, rate_limiter).await
+}
+
+// `<_..>` matches the trailing slash of "/api/<profile>/"
+#[post("/<_>/<_..>", data = "<layout>", rank = 2)]
+async fn post_profile(
+    db: &State<Db>,
+    profile: ProfileParam<'_>,
+    layout: Json<PostLayout>,
+    config: &State<Options>,
+    caller: Caller,
+    rate_limiter: &State<RateLimiter>,
+) -> Result<Created<Json<LayoutEvaluation>>> {
+    post_layout(
+        db,
+        &layout,
+        profile.profile,
+        profile.config_version,
+        config,
+        &caller,
+        rate_limiter,
     )
-    .fetch_all(&mut *db)
     .await
-    .map_err(|e| {
-        eprintln!("Error while fetching all layouts from db: {:?}", e);
+}
+
+impl From<&ListQuery> for LayoutQuery {
+    fn from(query: &ListQuery) -> Self {
+        Self {
+            published_by: query.published_by.clone(),
+            highlight: query.highlight,
+            min_cost: query.min_cost,
+            max_cost: query.max_cost,
+            layout: query.layout.clone(),
+            min_metric: query.min_metric.clone(),
+            max_metric: query.max_metric.clone(),
+            order: query.sort.unwrap_or_default(),
+            descending: query.order == Some(SortDirection::Desc),
+            limit: query.limit.map(i64::from),
+            offset: query.offset.map_or(0, i64::from),
+        }
+    }
+}
+
+/// Names of the metrics a listing filters on that are not evaluated by the profile.
+fn unknown_metrics<'q>(query: &'q ListQuery, profile: &Profile) -> Vec<&'q str> {
+    let known = profile.evaluator.metric_names();
+    query
+        .min_metric
+        .keys()
+        .chain(query.max_metric.keys())
+        .filter(|name| !known.contains(name))
+        .map(|name| name.as_str())
+        .collect()
+}
+
+/// A page of layouts with the total number of layouts matching the query.
+#[derive(Responder)]
+struct LayoutList {
+    layouts: Json<Vec<LayoutEvaluation>>,
+    total: Header<'static>,
+}
+
+async fn list_layouts(db: &Db, profile: &Profile, query: &ListQuery) -> Result<LayoutList> {
+    let fetch_error = |e| {
+        eprintln!("Error while fetching layouts from db: {:?}", e);
         Status::InternalServerError
-    })?
-    .into_iter()
-    .map(|e| e.into())
-    .collect();
+    };
 
-    Ok(Json(layouts))
+    let unknown_metrics = unknown_metrics(query, profile);
+    if !unknown_metrics.is_empty() {
+        println!("Unknown metrics in query: {}", unknown_metrics.join(", "));
+        return Err(Status::BadRequest);
+    }
+
+    let layout_query = LayoutQuery::from(query);
+    let layouts = db
+        .list(&profile.name, &layout_query)
+        .await
+        .map_err(fetch_error)?;
+    let total = db
+        .count(&profile.name, &layout_query)
+        .await
+        .map_err(fetch_error)?;
+
+    Ok(LayoutList {
+        layouts: Json(layouts.into_iter().map(|e| e.into()).collect()),
+        total: Header::new("X-Total-Count", total.to_string()),
+    })
 }
 
-#[get("/<layout>")]
-async fn get(
-    mut db: Connection<Db>,
+#[get("/?<query..>")]
+async fn list(
+    db: &State<Db>,
+    query: ListQuery,
+    profiles: &State<Arc<Profiles>>,
+) -> Result<LayoutList> {
+    list_layouts(db, profiles.default_profile(), &query).await
+}
+
+// ranked before `/<layout>`, which would also match "/api/<profile>/"
+#[get("/<_>/<_..>?<query..>", rank = 2)]
+async fn list_profile(
+    db: &State<Db>,
+    profile: ProfileParam<'_>,
+    query: ListQuery,
+) -> Result<LayoutList> {
+    list_layouts(db, profile.profile, &query).await
+}
+
+#[get("/configs")]
+async fn configs(db: &State<Db>) -> Result<Json<Vec<EvalConfig>>> {
+    let configs = db.list_configs().await.map_err(|e| {
+        eprintln!("Error while fetching configs from db: {:?}", e);
+        Status::InternalServerError
+    })?;
+
+    Ok(Json(configs.into_iter().map(|c| c.into()).collect()))
+}
+
+/// Names of the metrics the layouts of the default profile are evaluated with (e.g. for filtering
+/// listings with `min_metric`/`max_metric`).
+#[get("/metrics")]
+fn metrics(profiles: &State<Arc<Profiles>>) -> Json<Vec<String>> {
+    Json(profiles.default_profile().evaluator.metric_names())
+}
+
+// ranked after `/jobs/<id>`, but before `/<_>/<layout>`
+#[get("/<_>/metrics", rank = 0)]
+fn metrics_profile(profile: ProfileParam<'_>) -> Json<Vec<String>> {
+    Json(profile.profile.evaluator.metric_names())
+}
+
+#[get("/openapi.json")]
+fn openapi() -> Json<Value> {
+    Json(super::openapi::document())
+}
+
+/// Fetch a layout with its current result or, if given, its result under configuration version
+/// `config`.
+async fn get_layout(
+    db: &Db,
+    profile: &Profile,
     layout: &str,
-    layout_generator: &State<NeoLayoutGenerator>,
+    config: Option<i32>,
 ) -> Option<Json<LayoutEvaluation>> {
-    sqlx::query_as::<_, LayoutEvaluationDB>(
-        "SELECT NULL AS id, layout, total_cost, published_by, details_json, printed, highlight FROM layouts WHERE layout = $1",
-    )
-    .bind(layout)
-    .fetch_one(&mut *db)
-    .await
-    .map(|e| {
+    let result = match config {
+        Some(config_id) => db.get_result(&profile.name, layout, config_id).await,
+        None => db.get(&profile.name, layout).await,
+    };
+
+    result.ok().flatten().map(|e| {
         let mut res: LayoutEvaluation = e.clone().into();
-        let l = layout_generator.generate(&e.layout).unwrap();
+        let l = profile.layout_generator.generate(&e.layout).unwrap();
         res.plot = Some(l.plot());
         res.details = Some(serde_json::from_str(&e.details_json).unwrap());
         res.printed = Some(e.printed);
         Json(res)
     })
-    .ok()
 }
 
+#[get("/<layout>?<config>", rank = 3)]
+async fn get(
+    db: &State<Db>,
+    layout: &str,
+    config: Option<i32>,
+    profiles: &State<Arc<Profiles>>,
+) -> Option<Json<LayoutEvaluation>> {
+    get_layout(db, profiles.default_profile(), layout, config).await
+}
+
+// ranked before `/<_>/<_..>`, which would also match "/api/<profile>/<layout>"
+#[get("/<_>/<layout>?<config>", rank = 1)]
+async fn get_profile(
+    db: &State<Db>,
+    profile: ProfileParam<'_>,
+    layout: &str,
+    config: Option<i32>,
+) -> Option<Json<LayoutEvaluation>> {
+    get_layout(db, profile.profile, layout, config).await
+}
+
+/// Start a background job re-evaluating all layouts of the given profiles with their current
+/// configurations.
+fn start_reevaluation(
+    db: &Db,
+    profiles: &Arc<Profiles>,
+    jobs: &Jobs,
+    targets: Vec<(String, i32)>,
+) -> Result<Accepted<Json<JobInfo>>> {
+    let names = targets.iter().map(|(name, _)| name.clone()).collect();
+    let job = jobs.create("reeval", names).ok_or_else(|| {
+        println!("A re-evaluation of these profiles is already running.");
+        Status::Conflict
+    })?;
+    println!("Starting re-evaluation job {} ({:?})", job.id, job.profiles);
+
+    let info = job.info();
+    jobs.spawn_reevaluation(job, db.clone(), profiles.clone(), targets);
+
+    Ok(Accepted(Json(info)))
+}
+
+/// Re-evaluate the layouts of all profiles (in a background job).
 #[post("/reeval", data = "<secret>")]
 async fn reeval(
-    mut db: Connection<Db>,
+    db: &State<Db>,
+    secret: &str,
+    profiles: &State<Arc<Profiles>>,
+    versions: &State<ConfigVersions>,
+    jobs: &State<Jobs>,
+    config: &State<Options>,
+    caller: Caller,
+) -> Result<Accepted<Json<JobInfo>>> {
+    if !is_admin(&caller, Some(secret), config) {
+        println!("Wrong password provided for re-evaluation.");
+        return Err(Status::Unauthorized);
+    }
+
+    let targets = profiles
+        .iter()
+        .map(|profile| (profile.name.clone(), versions.0[&profile.name]))
+        .collect();
+    start_reevaluation(db, profiles, jobs, targets)
+}
+
+#[post("/<_>/reeval", data = "<secret>")]
+async fn reeval_profile(
+    db: &State<Db>,
+    profile: ProfileParam<'_>,
     secret: &str,
-    layout_generator: &State<NeoLayoutGenerator>,
-    evaluator: &State<Evaluator>,
+    profiles: &State<Arc<Profiles>>,
+    jobs: &State<Jobs>,
     config: &State<Options>,
-) -> Result<()> {
-    let is_admin = config.secret == secret.to_string();
-    if !is_admin {
+    caller: Caller,
+) -> Result<Accepted<Json<JobInfo>>> {
+    if !is_admin(&caller, Some(secret), config) {
         println!("Wrong password provided for re-evaluation.");
         return Err(Status::Unauthorized);
     }
 
-    println!("Reevaluating results");
-    let results: Vec<LayoutEvaluationDB> = sqlx::query_as::<_, LayoutEvaluationDB>(
-        "SELECT id, layout, total_cost, details_json, printed, published_by, highlight FROM layouts",
+    let targets = vec![(profile.profile.name.clone(), profile.config_version)];
+    start_reevaluation(db, profiles, jobs, targets)
+}
+
+/// Start a background job optimizing a layout for the given profile.
+#[allow(clippy::too_many_arguments)]
+fn start_optimization(
+    db: &Db,
+    profiles: &Arc<Profiles>,
+    jobs: &Jobs,
+    profile: &Profile,
+    config_version: i32,
+    mut request: OptimizationRequest,
+    limits: &OptimizerLimits,
+    caller: &Caller,
+    rate_limiter: &RateLimiter,
+) -> Result<Accepted<Json<JobInfo>>> {
+    // optimizations are expensive, so only users with a token may submit them
+    let user = caller.user.as_ref().ok_or(Status::Unauthorized)?;
+    check_rate_limit(rate_limiter, caller)?;
+    if request.published_by.is_none() {
+        request.published_by = Some(user.name.clone());
+    }
+    profile
+        .layout_generator
+        .generate(&request.layout)
+        .map_err(|_| Status::BadRequest)?;
+    let params = OptimizerParameters::from_request(&request, limits).map_err(|e| {
+        println!("Invalid optimization parameters: {}", e);
+        Status::BadRequest
+    })?;
+
+    let job = jobs
+        .create_optimization(&profile.name, &user.name)
+        .ok_or_else(|| {
+            println!("Too many optimizations are waiting already.");
+            Status::ServiceUnavailable
+        })?;
+    println!(
+        "Starting optimization job {} ({}, {:?})",
+        job.id, profile.name, request.optimizer
+    );
+
+    let info = job.info();
+    jobs.spawn_optimization(
+        job,
+        db.clone(),
+        profiles.clone(),
+        config_version,
+        request,
+        params,
+    );
+
+    Ok(Accepted(Json(info)))
+}
+
+/// Optimize a layout on the server (in a background job). The result is stored automatically.
+#[allow(clippy::too_many_arguments)]
+#[post("/optimize", data = "<request>")]
+async fn optimize(
+    db: &State<Db>,
+    request: Json<OptimizationRequest>,
+    profiles: &State<Arc<Profiles>>,
+    versions: &State<ConfigVersions>,
+    jobs: &State<Jobs>,
+    config: &State<Options>,
+    caller: Caller,
+    rate_limiter: &State<RateLimiter>,
+) -> Result<Accepted<Json<JobInfo>>> {
+    let profile = profiles.default_profile();
+    let config_version = versions.0[&profile.name];
+    start_optimization(
+        db,
+        profiles,
+        jobs,
+        profile,
+        config_version,
+        request.into_inner(),
+        &config.optimization_limits,
+        &caller,
+        rate_limiter,
     )
-    .fetch_all(&mut *db)
-    .await
-    .map_err(|_| Status::InternalServerError)?;
-
-    for result in results {
-        let layout = layout_generator.generate(&result.layout).unwrap();
-        let evaluation_result = evaluator.evaluate_layout(&layout);
-        let total_cost = evaluation_result.total_cost();
-        let details_json = Some(serde_json::to_string(&evaluation_result).unwrap());
-        let printed = format!("{}", evaluation_result);
-
-        println!(
-            "Re-evaluated {} (id: {}) from {:>.2} to {:>.2}",
-            result.layout,
-            result.id.unwrap(),
-            result.total_cost,
-            total_cost
-        );
-        sqlx::query(
-            "UPDATE layouts SET total_cost = $1, details_json = $2 , printed = $3 WHERE id = $4",
-        )
-        .bind(&total_cost)
-        .bind(&details_json)
-        .bind(&printed)
-        .bind(&result.id)
-        .execute(&mut *db)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[post("/<_>/optimize", data = "<request>")]
+async fn optimize_profile(
+    db: &State<Db>,
+    profile: ProfileParam<'_>,
+    request: Json<OptimizationRequest>,
+    profiles: &State<Arc<Profiles>>,
+    jobs: &State<Jobs>,
+    config: &State<Options>,
+    caller: Caller,
+    rate_limiter: &State<RateLimiter>,
+) -> Result<Accepted<Json<JobInfo>>> {
+    start_optimization(
+        db,
+        profiles,
+        jobs,
+        profile.profile,
+        profile.config_version,
+        request.into_inner(),
+        &config.optimization_limits,
+        &caller,
+        rate_limiter,
+    )
+}
+
+#[get("/jobs")]
+fn list_jobs(jobs: &State<Jobs>) -> Json<Vec<JobInfo>> {
+    Json(jobs.list())
+}
+
+#[get("/jobs/<id>")]
+fn get_job(id: u64, jobs: &State<Jobs>) -> Option<Json<JobInfo>> {
+    jobs.get(id).map(|job| Json(job.info()))
+}
+
+/// Stream the events of a job as server-sent events: its current state (`status`), the progress
+/// of an optimization (`progress`), and finally its completion (again `status`).
+#[get("/jobs/<id>/events")]
+fn job_events(id: u64, jobs: &State<Jobs>, mut shutdown: Shutdown) -> Option<EventStream![]> {
+    let job = jobs.get(id)?;
+    // subscribe before fetching the state, so that the completion can not be missed
+    let mut events = job.subscribe();
+
+    Some(EventStream! {
+        let info = job.info();
+        let active = info.status.is_active();
+        yield Event::json(&info).event("status");
+        if !active {
+            return;
+        }
+
+        loop {
+            let event = select! {
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            match event {
+                JobEvent::Progress(progress) => yield Event::json(&progress).event("progress"),
+                JobEvent::Completed(info) => {
+                    yield Event::json(&info).event("status");
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// Cancel a job (admins and the user who submitted it).
+#[post("/jobs/<id>/cancel", data = "<secret>")]
+fn cancel_job(
+    id: u64,
+    secret: &str,
+    jobs: &State<Jobs>,
+    config: &State<Options>,
+    caller: Caller,
+) -> Result<Json<JobInfo>> {
+    let job = jobs.get(id).ok_or(Status::NotFound)?;
+    let is_submitter = match (&caller.user, &job.submitted_by) {
+        (Some(user), Some(submitted_by)) => &user.name == submitted_by,
+        _ => false,
+    };
+    if !is_submitter && !is_admin(&caller, Some(secret), config) {
+        println!("Missing permissions for cancelling job {}.", id);
+        return Err(if caller.user.is_some() {
+            Status::Forbidden
+        } else {
+            Status::Unauthorized
+        });
+    }
+
+    job.cancel();
+
+    Ok(Json(job.info()))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct PostToken {
+    /// Name of the user the token is issued to
+    name: String,
+    #[serde(default)]
+    admin: bool,
+}
+
+/// A newly issued token. The token itself is only returned once.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct NewToken {
+    #[serde(flatten)]
+    info: ApiTokenDB,
+    token: String,
+}
+
+#[get("/tokens")]
+async fn list_tokens(db: &State<Db>, _admin: Admin) -> Result<Json<Vec<ApiTokenDB>>> {
+    let tokens = db.list_tokens().await.map_err(|e| {
+        eprintln!("Error while fetching api tokens from db: {:?}", e);
+        Status::InternalServerError
+    })?;
+
+    Ok(Json(tokens))
+}
+
+/// Issue a new api token (admins only).
+#[post("/tokens", data = "<request>")]
+async fn post_token(
+    db: &State<Db>,
+    request: Json<PostToken>,
+    admin: Admin,
+) -> Result<Created<Json<NewToken>>> {
+    let token = auth::generate_token();
+    let id = db
+        .insert_token(&request.name, &auth::hash_token(&token), request.admin)
         .await
-        .map_err(|_| Status::InternalServerError)?;
+        .map_err(|e| {
+            eprintln!("Error while storing api token in db: {:?}", e);
+            Status::InternalServerError
+        })?;
+    println!(
+        "Issued api token {} for {} (by {})",
+        id, request.name, admin.0.name
+    );
+
+    let info = ApiTokenDB {
+        id,
+        name: request.name.clone(),
+        admin: request.admin,
+    };
+    Ok(Created::new(format!("/api/tokens/{}", id)).body(Json(NewToken { info, token })))
+}
+
+/// Revoke an api token (admins only).
+#[delete("/tokens/<id>")]
+async fn delete_token(db: &State<Db>, id: i32, _admin: Admin) -> Result<NoContent> {
+    let deleted = db.delete_token(id).await.map_err(|e| {
+        eprintln!("Error while deleting api token from db: {:?}", e);
+        Status::InternalServerError
+    })?;
+
+    if deleted {
+        Ok(NoContent)
+    } else {
+        Err(Status::NotFound)
     }
+}
 
-    Ok(())
+/// Export all layouts (of a profile, if given) with their results and provenance as JSONL
+/// (admins only).
+#[get("/export?<profile>")]
+async fn export(
+    db: &State<Db>,
+    profile: Option<&str>,
+    admin: Admin,
+) -> Result<(ContentType, String)> {
+    let jsonl = transfer::export(db.as_ref(), profile).await.map_err(|e| {
+        eprintln!("Error while exporting layouts from db: {:?}", e);
+        Status::InternalServerError
+    })?;
+    println!("Exported {} layouts (by {})", jsonl.lines().count(), admin.0.name);
+
+    Ok((ContentType::new("application", "x-ndjson"), jsonl))
 }
 
-// #[delete("/<layout>")]
-// async fn delete(mut db: Connection<Db>, layout: &str) -> Result<Option<()>> {
-//     let result = sqlx::query("DELETE FROM layouts WHERE layout = $1")
-//         .bind(layout)
-//         .execute(&mut *db)
-//         .await
-//         .map_err(|_| Status::InternalServerError)?;
+/// Import layouts given as JSONL, e.g. as exported by `/export` (admins only), in a background
+/// job. Layouts that are stored already are skipped.
+#[allow(clippy::too_many_arguments)]
+#[post("/import?<reevaluate>", data = "<data>")]
+async fn import(
+    db: &State<Db>,
+    data: Data<'_>,
+    reevaluate: Option<bool>,
+    limits: &Limits,
+    profiles: &State<Arc<Profiles>>,
+    versions: &State<ConfigVersions>,
+    jobs: &State<Jobs>,
+    admin: Admin,
+) -> Result<Accepted<Json<JobInfo>>> {
+    let jsonl = data
+        .open(limits.get("import").unwrap_or_else(|| 64.mebibytes()))
+        .into_string()
+        .await
+        .map_err(|_| Status::BadRequest)?;
+    if !jsonl.is_complete() {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    let names = profiles.iter().map(|profile| profile.name.clone()).collect();
+    let job = jobs.create("import", names).ok_or_else(|| {
+        println!("An import is already running.");
+        Status::Conflict
+    })?;
+    println!("Starting import job {} (by {})", job.id, admin.0.name);
 
-//     Ok((result.rows_affected() == 1).then(|| ()))
-// }
+    let info = job.info();
+    jobs.spawn_import(
+        job,
+        db.inner().clone(),
+        profiles.inner().clone(),
+        versions.inner().clone(),
+        jsonl.into_inner(),
+        reevaluate.unwrap_or(false),
+    );
 
-async fn run_migrations(rocket: Rocket<Build>) -> fairing::Result {
-    match Db::fetch(&rocket) {
-        Some(db) => match sqlx::migrate!("db/migrations").run(&**db).await {
-            Ok(_) => Ok(rocket),
+    Ok(Accepted(Json(info)))
+}
+
+/// Store the evaluation configurations of all profiles and collect their versions.
+async fn register(db: &Db, profiles: &Profiles) -> Option<ConfigVersions> {
+    let mut versions = BTreeMap::new();
+    for profile in profiles.iter() {
+        match db.register_config(&profile.eval_config).await {
+            Ok(id) => {
+                info!(
+                    "Evaluating profile {} with config version {} ({})",
+                    profile.name, id, profile.eval_config.hash
+                );
+                versions.insert(profile.name.clone(), id);
+            }
             Err(e) => {
-                error!("Failed to initialize SQLx database: {}", e);
-                Err(rocket)
+                error!(
+                    "Failed to register evaluation config of profile {}: {}",
+                    profile.name, e
+                );
+                return None;
             }
-        },
+        }
+    }
+
+    Some(ConfigVersions(versions))
+}
+
+async fn register_configs(rocket: Rocket<Build>) -> fairing::Result {
+    let versions = match (rocket.state::<Db>(), rocket.state::<Arc<Profiles>>()) {
+        (Some(db), Some(profiles)) => register(db, profiles).await,
+        _ => None,
+    };
+
+    match versions {
+        Some(versions) => Ok(rocket.manage(versions)),
         None => Err(rocket),
     }
 }
 
 pub fn stage() -> AdHoc {
-    AdHoc::on_ignite("SQLx Stage", |rocket| async {
+    AdHoc::on_ignite("Layouts API", |rocket| async {
         rocket
-            .attach(Db::init())
-            .attach(AdHoc::try_on_ignite("SQLx Migrations", run_migrations))
-            // .mount("/", routes![list, post, get, delete, reeval])
-            .mount("/api", routes![list, post, get, reeval, cors_preflight])
+            .attach(storage::stage())
+            .attach(AdHoc::try_on_ignite("Evaluation Configs", register_configs))
+            .mount(
+                "/api",
+                routes![
+                    list,
+                    list_profile,
+                    post,
+                    post_profile,
+                    configs,
+                    metrics,
+                    metrics_profile,
+                    openapi,
+                    get,
+                    get_profile,
+                    reeval,
+                    reeval_profile,
+                    optimize,
+                    optimize_profile,
+                    list_jobs,
+                    get_job,
+                    job_events,
+                    cancel_job,
+                    list_tokens,
+                    post_token,
+                    delete_token,
+                    export,
+                    import,
+                    cors_preflight,
+                    cors_preflight_profile
+                ],
+            )
     })
 }
@@ -0,0 +1,203 @@
+//! The `auth` module authenticates api requests with per-user tokens and limits the rate of
+//! requests that create work on the server (publishing layouts, submitting optimizations).
+//!
+//! Tokens are passed in the `Authorization: Bearer <token>` header. The configured `secret`
+//! is accepted as a token with admin permissions as well.
+
+use super::storage::LayoutStorage;
+use super::Options;
+
+use rand::Rng;
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Generate a new random token (hex).
+pub fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The hash of a token as stored in the database (SHA-256, hex).
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// The user a request was authenticated as.
+#[derive(Clone, Debug)]
+pub struct ApiUser {
+    pub name: String,
+    pub admin: bool,
+}
+
+/// The caller of a request: the authenticated user, if the request carries a valid token, or an
+/// anonymous client. Fails with `401 Unauthorized` if an unknown token is given.
+#[derive(Clone, Debug)]
+pub struct Caller {
+    pub user: Option<ApiUser>,
+    /// Key the caller's requests are counted under for rate limiting
+    rate_key: String,
+}
+
+impl Caller {
+    pub fn is_admin(&self) -> bool {
+        self.user.as_ref().is_some_and(|user| user.admin)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Caller {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, ()> {
+        let header = match request.headers().get_one("Authorization") {
+            Some(header) => header,
+            None => {
+                let address = request
+                    .client_ip()
+                    .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+                return Outcome::Success(Caller {
+                    user: None,
+                    rate_key: format!("address:{}", address),
+                });
+            }
+        };
+        let token = match header.strip_prefix("Bearer ") {
+            Some(token) => token.trim(),
+            None => return Outcome::Error((Status::Unauthorized, ())),
+        };
+
+        let options = request.rocket().state::<Options>();
+        if options.is_some_and(|options| options.secret == token) {
+            return Outcome::Success(Caller {
+                user: Some(ApiUser {
+                    name: "admin".to_string(),
+                    admin: true,
+                }),
+                rate_key: "admin".to_string(),
+            });
+        }
+
+        let db = match request.rocket().state::<Arc<dyn LayoutStorage>>() {
+            Some(db) => db,
+            None => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        match db.get_token(&hash_token(token)).await {
+            Ok(Some(token)) => Outcome::Success(Caller {
+                user: Some(ApiUser {
+                    name: token.name,
+                    admin: token.admin,
+                }),
+                rate_key: format!("token:{}", token.id),
+            }),
+            Ok(None) => {
+                println!("Unknown api token provided.");
+                Outcome::Error((Status::Unauthorized, ()))
+            }
+            Err(e) => {
+                eprintln!("Error while fetching api token from db: {:?}", e);
+                Outcome::Error((Status::InternalServerError, ()))
+            }
+        }
+    }
+}
+
+/// A caller with admin permissions. Fails with `403 Forbidden` for other callers.
+pub struct Admin(pub ApiUser);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, ()> {
+        match Caller::from_request(request).await {
+            Outcome::Success(Caller {
+                user: Some(user), ..
+            }) if user.admin => Outcome::Success(Admin(user)),
+            Outcome::Success(_) => Outcome::Error((Status::Forbidden, ())),
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(s) => Outcome::Forward(s),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct RateLimitConfig {
+    /// Length of the rate limiting window in seconds
+    #[serde(default = "default_period")]
+    pub period: u64,
+
+    /// Requests per window of a client without token (0 means unlimited)
+    #[serde(default = "default_anonymous_requests")]
+    pub anonymous_requests: u32,
+
+    /// Requests per window of a user with token (0 means unlimited)
+    #[serde(default = "default_token_requests")]
+    pub token_requests: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            period: default_period(),
+            anonymous_requests: default_anonymous_requests(),
+            token_requests: default_token_requests(),
+        }
+    }
+}
+
+fn default_period() -> u64 {
+    60
+}
+
+fn default_anonymous_requests() -> u32 {
+    10
+}
+
+fn default_token_requests() -> u32 {
+    60
+}
+
+/// Counts the requests of each caller in fixed windows. Admins are not limited.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a request of the caller and check whether it is within the caller's limit.
+    pub fn check(&self, caller: &Caller) -> bool {
+        let limit = match &caller.user {
+            Some(user) if user.admin => return true,
+            Some(_) => self.config.token_requests,
+            None => self.config.anonymous_requests,
+        };
+        if limit == 0 {
+            return true;
+        }
+
+        let period = Duration::from_secs(self.config.period);
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        // forget the callers whose windows have passed
+        windows.retain(|_, (start, _)| now.duration_since(*start) < period);
+
+        let (_, count) = windows.entry(caller.rate_key.clone()).or_insert((now, 0));
+        *count += 1;
+
+        *count <= limit
+    }
+}
@@ -0,0 +1,122 @@
+//! The `eval_config` module identifies the configuration an evaluation was produced with. The
+//! evaluation parameters, the keyboard configuration, and the ngram files are hashed together, so
+//! any change to them results in a new configuration version.
+
+use super::profiles::ProfileOptions;
+
+use anyhow::Result;
+use layouts_webservice_client::config_hash::ConfigFiles;
+use rocket::serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The files an evaluation depends on.
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct EvalConfig {
+    /// SHA-256 hash (hex) of all configuration files and ngram files
+    pub hash: String,
+    /// Content of the evaluation parameters file
+    pub eval_parameters: String,
+    /// Content of the layout configuration (keyboard and base layout) file
+    pub layout_config: String,
+    /// Ngram files with the hashes of their contents (one per line)
+    pub ngrams: String,
+}
+
+impl EvalConfig {
+    /// Read and hash the files referenced in a profile's options.
+    pub fn from_options(options: &ProfileOptions) -> Result<Self> {
+        let files = ConfigFiles {
+            eval_parameters: &options.eval_parameters,
+            layout_config: &options.layout_config,
+            unigrams: &options.unigrams,
+            bigrams: &options.bigrams,
+            trigrams: &options.trigrams,
+        };
+        let hashed = files.hash(&[])?;
+
+        let ngrams = [
+            ("unigrams", files.unigrams),
+            ("bigrams", files.bigrams),
+            ("trigrams", files.trigrams),
+        ]
+        .iter()
+        .zip(hashed.ngram_hashes.iter())
+        .map(|((name, filename), hash)| format!("{}: {} ({})", name, filename, hash))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+        Ok(Self {
+            hash: hashed.hash,
+            eval_parameters: hashed.eval_parameters,
+            layout_config: hashed.layout_config,
+            ngrams,
+        })
+    }
+}
+
+/// Versions (database ids) of the configurations the service currently evaluates layouts with
+/// (for each profile).
+#[derive(Clone, Debug)]
+pub struct ConfigVersions(pub BTreeMap<String, i32>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evolve_keyboard_layout::common;
+
+    #[test]
+    fn same_hash_as_optimizers() {
+        let dir = std::env::temp_dir().join(format!("eval_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+        let mut ngrams = BTreeMap::new();
+        for (name, content) in [
+            ("unigrams", "10 e\n8 n\n"),
+            ("bigrams", "5 en\n"),
+            ("trigrams", "3 ern\n"),
+        ] {
+            std::fs::write(path(name), content).unwrap();
+            ngrams.insert(name, path(name));
+        }
+        // the optimizers take the ngram files from the evaluation parameters
+        let eval_parameters =
+            std::fs::read_to_string("../config/evaluation_parameters.yml").unwrap();
+        let mut eval_parameters: serde_yaml::Value =
+            serde_yaml::from_str(&eval_parameters).unwrap();
+        eval_parameters["ngrams"] = serde_yaml::to_value(&ngrams).unwrap();
+        std::fs::write(
+            path("eval_parameters.yml"),
+            serde_yaml::to_string(&eval_parameters).unwrap(),
+        )
+        .unwrap();
+
+        let profile_options = ProfileOptions {
+            eval_parameters: path("eval_parameters.yml"),
+            layout_config: "../config/standard_keyboard.yml".to_string(),
+            unigrams: ngrams["unigrams"].clone(),
+            bigrams: ngrams["bigrams"].clone(),
+            trigrams: ngrams["trigrams"].clone(),
+        };
+        let mut options = common::Options {
+            eval_parameters: profile_options.eval_parameters.clone(),
+            layout_config: profile_options.layout_config.clone(),
+            corpus: None,
+            text: None,
+            tops: None,
+            no_split_modifiers: false,
+            no_add_secondary_bigrams: false,
+            no_increase_common_bigrams: false,
+        };
+
+        let config = EvalConfig::from_options(&profile_options).unwrap();
+        assert_eq!(common::config_hash(&options).unwrap(), config.hash);
+
+        // deviating from the configuration files results in another configuration
+        options.tops = Some(0.9);
+        assert_ne!(common::config_hash(&options).unwrap(), config.hash);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,619 @@
+//! The `jobs` module runs long-running tasks (e.g. re-evaluating all stored layouts, optimizing
+//! a layout, or importing layouts) in the background. Each job gets an id that can be used to query its progress or to cancel it.
+//! Jobs are only kept in memory, i.e. they are lost when the service restarts. Completed jobs are
+//! forgotten after a while (see `Jobs::new`).
+
+use super::eval_config::ConfigVersions;
+use super::optimization::{self, OptimizationRequest, OptimizerParameters};
+use super::profiles::Profiles;
+use super::storage::{EvaluationUpdate, LayoutEvaluationDB, LayoutQuery, LayoutStorage};
+use super::transfer;
+
+use anyhow::{anyhow, Result};
+use layout_optimization::observer::ProgressEvent;
+use layouts_webservice_client::api::ImportSummary;
+pub use layouts_webservice_client::api::{JobInfo, JobStatus};
+use rayon::prelude::*;
+use rocket::serde::json::json;
+use rocket::tokio::sync::{broadcast, Notify, Semaphore};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Events published by a job to its subscribers.
+#[derive(Clone, Debug)]
+pub enum JobEvent {
+    /// Progress of an optimization
+    Progress(ProgressEvent),
+    /// The job finished, failed, or was cancelled
+    Completed(JobInfo),
+}
+
+#[derive(Debug)]
+struct JobState {
+    status: JobStatus,
+    error: Option<String>,
+    best: Option<(String, f64)>,
+    import_summary: Option<ImportSummary>,
+    /// When the job finished, failed, or was cancelled
+    completed: Option<Instant>,
+}
+
+/// A background job with its progress.
+#[derive(Debug)]
+pub struct Job {
+    pub id: u64,
+    pub kind: String,
+    pub profiles: Vec<String>,
+    pub submitted_by: Option<String>,
+    state: Mutex<JobState>,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+    /// Wakes up a queued job waiting for a slot when it is cancelled
+    cancellation: Notify,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl Job {
+    pub fn info(&self) -> JobInfo {
+        let state = self.state.lock().unwrap();
+        JobInfo {
+            id: self.id,
+            kind: self.kind.clone(),
+            profiles: self.profiles.clone(),
+            submitted_by: self.submitted_by.clone(),
+            status: state.status,
+            done: self.done.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            error: state.error.clone(),
+            best_layout: state.best.as_ref().map(|(layout, _)| layout.clone()),
+            best_cost: state.best.as_ref().map(|(_, cost)| *cost),
+            import_summary: state.import_summary.clone(),
+        }
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.state.lock().unwrap().status
+    }
+
+    fn set_status(&self, status: JobStatus, error: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        state.status = status;
+        state.error = error;
+        if !status.is_active() {
+            state.completed = Some(Instant::now());
+        }
+    }
+
+    /// Mark a queued job as running. Returns `false` if it was cancelled before it started.
+    fn start(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.status != JobStatus::Queued {
+            return false;
+        }
+        state.status = JobStatus::Running;
+        true
+    }
+
+    /// When the job completed (`None` while it is active).
+    fn completed(&self) -> Option<Instant> {
+        self.state.lock().unwrap().completed
+    }
+
+    pub fn set_progress(&self, done: usize, total: usize) {
+        self.done.store(done, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn set_best(&self, layout: String, cost: f64) {
+        self.state.lock().unwrap().best = Some((layout, cost));
+    }
+
+    /// Count an item as processed.
+    pub fn add_done(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn set_import_summary(&self, summary: ImportSummary) {
+        self.state.lock().unwrap().import_summary = Some(summary);
+    }
+
+    /// Subscribe to the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    /// Send an event to all current subscribers (events without subscribers are dropped).
+    pub fn publish(&self, event: JobEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Request the job to stop. A queued job is cancelled immediately (and no longer counts
+    /// towards the queue), a running one is stopped at the next item it processes.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+
+        let was_queued = {
+            let mut state = self.state.lock().unwrap();
+            if state.status == JobStatus::Queued {
+                state.status = JobStatus::Cancelled;
+                state.completed = Some(Instant::now());
+                true
+            } else {
+                false
+            }
+        };
+        if was_queued {
+            self.cancellation.notify_one();
+            self.publish(JobEvent::Completed(self.info()));
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Mark the job as finished, failed, or (if it was cancelled) cancelled.
+    fn complete(&self, result: Result<()>) {
+        if !self.status().is_active() {
+            // cancelled while it was queued
+            return;
+        }
+        match result {
+            _ if self.is_cancelled() => self.set_status(JobStatus::Cancelled, None),
+            Ok(()) => self.set_status(JobStatus::Finished, None),
+            Err(e) => self.set_status(JobStatus::Failed, Some(e.to_string())),
+        }
+        self.publish(JobEvent::Completed(self.info()));
+    }
+}
+
+/// Number of events buffered for each subscriber (slow subscribers miss older events)
+const EVENT_CAPACITY: usize = 256;
+
+/// Registry of all jobs with the worker pool they use for evaluations.
+pub struct Jobs {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, Arc<Job>>>,
+    pool: Arc<rayon::ThreadPool>,
+    /// Limits the number of optimizations running at the same time
+    optimization_slots: Arc<Semaphore>,
+    /// Maximal number of optimizations waiting for a slot
+    optimization_queue: usize,
+    /// Completed jobs are forgotten after this duration
+    completed_ttl: Duration,
+    /// Maximal number of completed jobs kept (the oldest ones are forgotten first)
+    completed_jobs: usize,
+}
+
+impl Jobs {
+    /// Create the registry with a worker pool of `threads` threads (0 means one per cpu). At most
+    /// `optimization_jobs` optimizations run at the same time, further ones are queued (up to
+    /// `optimization_queue` of them). Completed jobs are kept for `completed_ttl`, but at most
+    /// `completed_jobs` of them.
+    pub fn new(
+        threads: usize,
+        optimization_jobs: usize,
+        optimization_queue: usize,
+        completed_ttl: Duration,
+        completed_jobs: usize,
+    ) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("job-worker-{}", i))
+            .build()?;
+
+        Ok(Self {
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(BTreeMap::new()),
+            pool: Arc::new(pool),
+            optimization_slots: Arc::new(Semaphore::new(optimization_jobs)),
+            optimization_queue,
+            completed_ttl,
+            completed_jobs,
+        })
+    }
+
+    /// Register a new job. Returns `None` if an active job of the same kind already works on one
+    /// of the profiles.
+    pub fn create(&self, kind: &str, profiles: Vec<String>) -> Option<Arc<Job>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let conflict = jobs.values().any(|job| {
+            job.kind == kind
+                && job.status().is_active()
+                && job.profiles.iter().any(|p| profiles.contains(p))
+        });
+        if conflict {
+            return None;
+        }
+
+        Some(self.insert(&mut jobs, kind, profiles, None))
+    }
+
+    /// Register a new optimization job for the profile, submitted by the given user. Returns
+    /// `None` if the queue of optimizations waiting for a slot is full.
+    pub fn create_optimization(&self, profile: &str, submitted_by: &str) -> Option<Arc<Job>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let queued = jobs
+            .values()
+            .filter(|job| job.kind == "optimize" && job.status() == JobStatus::Queued)
+            .count();
+        if queued >= self.optimization_queue {
+            return None;
+        }
+
+        Some(self.insert(
+            &mut jobs,
+            "optimize",
+            vec![profile.to_string()],
+            Some(submitted_by.to_string()),
+        ))
+    }
+
+    /// Forget the completed jobs that are older than the ttl or exceed the maximal number.
+    fn prune(&self, jobs: &mut BTreeMap<u64, Arc<Job>>) {
+        let now = Instant::now();
+        jobs.retain(|_, job| match job.completed() {
+            Some(completed) => now.duration_since(completed) < self.completed_ttl,
+            None => true,
+        });
+
+        let mut completed: Vec<(Instant, u64)> = jobs
+            .values()
+            .filter_map(|job| job.completed().map(|completed| (completed, job.id)))
+            .collect();
+        if completed.len() > self.completed_jobs {
+            completed.sort_unstable();
+            let excess = completed.len() - self.completed_jobs;
+            for (_, id) in completed.into_iter().take(excess) {
+                jobs.remove(&id);
+            }
+        }
+    }
+
+    fn insert(
+        &self,
+        jobs: &mut BTreeMap<u64, Arc<Job>>,
+        kind: &str,
+        profiles: Vec<String>,
+        submitted_by: Option<String>,
+    ) -> Arc<Job> {
+        self.prune(jobs);
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let job = Arc::new(Job {
+            id,
+            kind: kind.to_string(),
+            profiles,
+            submitted_by,
+            state: Mutex::new(JobState {
+                status: JobStatus::Queued,
+                error: None,
+                best: None,
+                import_summary: None,
+                completed: None,
+            }),
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            cancelled: AtomicBool::new(false),
+            cancellation: Notify::new(),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        });
+        jobs.insert(id, job.clone());
+
+        job
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<Job>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.prune(&mut jobs);
+        jobs.get(&id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.prune(&mut jobs);
+        jobs.values().map(|job| job.info()).collect()
+    }
+
+    /// Re-evaluate all layouts of the given profiles (with their configuration versions) in the
+    /// background. The new results are evaluated in parallel and stored in a single transaction
+    /// once all of them are available, so a failed or cancelled job leaves the database unchanged.
+    pub fn spawn_reevaluation(
+        &self,
+        job: Arc<Job>,
+        db: Arc<dyn LayoutStorage>,
+        profiles: Arc<Profiles>,
+        targets: Vec<(String, i32)>,
+    ) {
+        let pool = self.pool.clone();
+        rocket::tokio::spawn(async move {
+            let result = reevaluate(&job, db, profiles, targets, pool).await;
+            if let Err(e) = &result {
+                eprintln!("Re-evaluation job {} failed: {:?}", job.id, e);
+            }
+            job.complete(result);
+            println!("Re-evaluation job {}: {:?}", job.id, job.status());
+        });
+    }
+
+    /// Import layouts given as JSONL (see `transfer::import`) in the background. The summary of
+    /// the import is part of the job once it finished.
+    pub fn spawn_import(
+        &self,
+        job: Arc<Job>,
+        db: Arc<dyn LayoutStorage>,
+        profiles: Arc<Profiles>,
+        versions: ConfigVersions,
+        jsonl: String,
+        reevaluate: bool,
+    ) {
+        let pool = self.pool.clone();
+        rocket::tokio::spawn(async move {
+            let result = if job.start() {
+                transfer::import(
+                    &job,
+                    db.as_ref(),
+                    profiles,
+                    &versions,
+                    pool,
+                    &jsonl,
+                    reevaluate,
+                )
+                .await
+            } else {
+                Err(anyhow!("Job was cancelled"))
+            };
+            let result = result.map(|summary| {
+                println!(
+                    "Import job {}: imported {} layouts ({} evaluated, {} duplicates, {} errors)",
+                    job.id,
+                    summary.imported,
+                    summary.evaluated,
+                    summary.duplicates,
+                    summary.errors.len(),
+                );
+                job.set_import_summary(summary);
+            });
+            if let Err(e) = &result {
+                eprintln!("Import job {} failed: {:?}", job.id, e);
+            }
+            job.complete(result);
+            println!("Import job {}: {:?}", job.id, job.status());
+        });
+    }
+
+    /// Run an optimization in the background once a slot is free. The resulting layout is
+    /// evaluated and stored (unless it is known already) together with its provenance, i.e. the
+    /// job and the optimizer settings it was found with.
+    pub fn spawn_optimization(
+        &self,
+        job: Arc<Job>,
+        db: Arc<dyn LayoutStorage>,
+        profiles: Arc<Profiles>,
+        config_version: i32,
+        request: OptimizationRequest,
+        params: OptimizerParameters,
+    ) {
+        let pool = self.pool.clone();
+        let slots = self.optimization_slots.clone();
+        rocket::tokio::spawn(async move {
+            let result = rocket::tokio::select! {
+                permit = slots.acquire() => match permit {
+                    Ok(_permit) => {
+                        run_optimization(&job, db, profiles, config_version, request, params, pool)
+                            .await
+                    }
+                    Err(e) => Err(e.into()),
+                },
+                // the job was completed by `Job::cancel` already
+                _ = job.cancellation.notified() => {
+                    println!("Optimization job {}: {:?}", job.id, job.status());
+                    return;
+                }
+            };
+            if let Err(e) = &result {
+                eprintln!("Optimization job {} failed: {:?}", job.id, e);
+            }
+            job.complete(result);
+            println!("Optimization job {}: {:?}", job.id, job.status());
+        });
+    }
+}
+
+async fn reevaluate(
+    job: &Arc<Job>,
+    db: Arc<dyn LayoutStorage>,
+    profiles: Arc<Profiles>,
+    targets: Vec<(String, i32)>,
+    pool: Arc<rayon::ThreadPool>,
+) -> Result<()> {
+    if !job.start() {
+        return Err(anyhow!("Job was cancelled"));
+    }
+
+    let mut layouts = Vec::new();
+    for (profile, config_id) in targets.iter() {
+        for layout in db.list(profile, &LayoutQuery::default()).await? {
+            layouts.push((profile.clone(), *config_id, layout));
+        }
+    }
+    job.set_total(layouts.len());
+    println!(
+        "Re-evaluation job {}: evaluating {} layouts",
+        job.id,
+        layouts.len()
+    );
+
+    let worker_job = job.clone();
+    let updates: Result<Vec<EvaluationUpdate>> = rocket::tokio::task::spawn_blocking(move || {
+        pool.install(|| {
+            layouts
+                .par_iter()
+                .map(|(profile, config_id, result)| {
+                    if worker_job.is_cancelled() {
+                        return Err(anyhow!("Job was cancelled"));
+                    }
+
+                    let profile = profiles
+                        .get(profile)
+                        .ok_or_else(|| anyhow!("Unknown profile '{}'", profile))?;
+                    let layout = profile.layout_generator.generate(&result.layout)?;
+                    let evaluation_result = profile.evaluator.evaluate_layout(&layout);
+
+                    worker_job.add_done();
+
+                    Ok(EvaluationUpdate {
+                        id: result.id.ok_or_else(|| anyhow!("Layout without id"))?,
+                        config_id: *config_id,
+                        total_cost: evaluation_result.total_cost(),
+                        details_json: serde_json::to_string(&evaluation_result)?,
+                        printed: format!("{}", evaluation_result),
+                    })
+                })
+                .collect()
+        })
+    })
+    .await?;
+    let updates = updates?;
+
+    if job.is_cancelled() {
+        return Err(anyhow!("Job was cancelled"));
+    }
+
+    db.update_evaluations(&updates).await
+}
+
+async fn run_optimization(
+    job: &Arc<Job>,
+    db: Arc<dyn LayoutStorage>,
+    profiles: Arc<Profiles>,
+    config_version: i32,
+    request: OptimizationRequest,
+    params: OptimizerParameters,
+    pool: Arc<rayon::ThreadPool>,
+) -> Result<()> {
+    if !job.start() {
+        return Err(anyhow!("Job was cancelled"));
+    }
+
+    let profile_name = job.profiles[0].clone();
+    let provenance = json!({
+        "job": job.id,
+        "optimizer": request.optimizer,
+        "parameters": params.to_json()?,
+        "layout": request.layout,
+        "fixed_characters": request.fixed_characters,
+        "start_with_layout": request.start_with_layout,
+    });
+
+    let worker_job = job.clone();
+    let worker_profiles = profiles.clone();
+    let worker_profile_name = profile_name.clone();
+    let evaluated: Result<LayoutEvaluationDB> = rocket::tokio::task::spawn_blocking(move || {
+        let profile = worker_profiles
+            .get(&worker_profile_name)
+            .ok_or_else(|| anyhow!("Unknown profile '{}'", worker_profile_name))?;
+        let layout =
+            pool.install(|| optimization::optimize(&worker_job, profile, &request, &params))?;
+        let evaluation_result = profile.evaluator.evaluate_layout(&layout);
+        worker_job.set_best(layout.as_text(), evaluation_result.total_cost());
+
+        Ok(LayoutEvaluationDB {
+            id: None,
+            profile: profile.name.clone(),
+            layout: layout.as_text(),
+            total_cost: evaluation_result.total_cost(),
+            published_by: request.published_by.clone(),
+            details_json: serde_json::to_string(&evaluation_result)?,
+            printed: format!("{}", evaluation_result),
+            highlight: false,
+            config_id: Some(config_version),
+            provenance: Some(provenance.to_string()),
+        })
+    })
+    .await?;
+    let evaluated = evaluated?;
+
+    if job.is_cancelled() {
+        return Err(anyhow!("Job was cancelled"));
+    }
+
+    if db.get(&profile_name, &evaluated.layout).await?.is_some() {
+        println!(
+            "Optimization job {}: layout {} is known already",
+            job.id, evaluated.layout
+        );
+        return Ok(());
+    }
+    println!(
+        "Optimization job {}: storing layout {} ({})",
+        job.id, evaluated.layout, evaluated.total_cost
+    );
+
+    db.insert(&evaluated).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(jobs: &Jobs) -> Vec<u64> {
+        jobs.list().iter().map(|job| job.id).collect()
+    }
+
+    #[test]
+    fn prune_beyond_max_count() {
+        let jobs = Jobs::new(1, 1, 10, Duration::from_secs(3600), 2).unwrap();
+        let active = jobs.create("reeval", vec!["a".to_string()]).unwrap();
+        for profile in ["b", "c", "d"] {
+            let job = jobs.create("reeval", vec![profile.to_string()]).unwrap();
+            job.complete(Ok(()));
+        }
+        assert_eq!(ids(&jobs), vec![1, 3, 4]);
+
+        let job = jobs.create("reeval", vec!["e".to_string()]).unwrap();
+        job.complete(Ok(()));
+        assert_eq!(ids(&jobs), vec![1, 4, 5]);
+        assert!(jobs.get(3).is_none());
+        assert!(active.status().is_active());
+    }
+
+    #[test]
+    fn prune_after_ttl() {
+        let jobs = Jobs::new(1, 1, 10, Duration::ZERO, 100).unwrap();
+        jobs.create("reeval", vec!["a".to_string()]).unwrap();
+        let job = jobs.create("reeval", vec!["b".to_string()]).unwrap();
+        job.complete(Err(anyhow!("failed")));
+        assert_eq!(job.status(), JobStatus::Failed);
+        assert!(jobs.get(2).is_none());
+
+        jobs.create_optimization("a", "someone").unwrap();
+        assert_eq!(ids(&jobs), vec![1, 3]);
+    }
+
+    #[test]
+    fn cancel_queued_optimization() {
+        let jobs = Jobs::new(1, 1, 1, Duration::from_secs(3600), 100).unwrap();
+        let job = jobs.create_optimization("a", "someone").unwrap();
+        assert!(jobs.create_optimization("a", "someone").is_none());
+
+        let mut events = job.subscribe();
+        job.cancel();
+        assert_eq!(job.status(), JobStatus::Cancelled);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(JobEvent::Completed(info)) if info.status == JobStatus::Cancelled
+        ));
+        assert!(!job.start());
+
+        // the cancelled job no longer occupies the queue
+        assert!(jobs.create_optimization("a", "someone").is_some());
+    }
+}
@@ -1,52 +1,106 @@
 #[macro_use] extern crate rocket;
 
 use rocket::fairing::AdHoc;
+use rocket::figment::Figment;
 use rocket::fs::FileServer;
+use rocket::{Build, Rocket};
 
-use keyboard_layout::{
-    keyboard::{Keyboard, KeyboardYAML},
-    layout_generator::{BaseLayoutYAML, NeoLayoutGenerator},
-};
+use keyboard_layout::{keyboard::KeyboardYAML, layout_generator::BaseLayoutYAML};
 use layout_evaluation::{
-    evaluation::{Evaluator, MetricParameters},
-    ngram_mapper::on_demand_ngram_mapper::{NgramMapperConfig, OnDemandNgramMapper},
-    ngrams::{Bigrams, Trigrams, Unigrams},
+    evaluation::MetricParameters, ngram_mapper::on_demand_ngram_mapper::NgramMapperConfig,
 };
 
 use anyhow::Result;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 mod api;
+mod auth;
+mod eval_config;
+mod jobs;
+mod openapi;
+mod optimization;
+mod profiles;
+mod storage;
+mod transfer;
+
+use profiles::{ProfileOptions, Profiles};
 
 
 #[derive(Clone, Deserialize, Debug)]
 struct Options {
-    /// Filename of evaluation configuration file to use
-    pub eval_parameters: String,
+    /// Name of the profile given by the top-level configuration files
+    #[serde(default = "default_profile_name")]
+    pub default_profile: String,
+
+    /// Configuration files of the default profile
+    #[serde(flatten)]
+    pub profile: ProfileOptions,
 
-    /// Filename of layout configuration file to use
-    pub layout_config: String,
+    /// Further named profiles
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileOptions>,
 
     /// Directory with static content to serve
     pub static_dir: String,
 
-    /// Unigram file
-    pub unigrams: String,
+    /// Number of worker threads for background jobs (0 means one per cpu)
+    #[serde(default)]
+    pub job_threads: usize,
 
-    /// Bigram file
-    pub bigrams: String,
+    /// Maximal number of optimizations running at the same time
+    #[serde(default = "default_optimization_jobs")]
+    pub optimization_jobs: usize,
 
-    /// Trigram file
-    pub trigrams: String,
+    /// Maximal number of submitted optimizations waiting for a free slot
+    #[serde(default = "default_optimization_queue")]
+    pub optimization_queue: usize,
 
-    /// Secret for performing admin actions
+    /// Seconds for which completed jobs can still be queried
+    #[serde(default = "default_completed_jobs_ttl")]
+    pub completed_jobs_ttl: u64,
+
+    /// Maximal number of completed jobs that can still be queried
+    #[serde(default = "default_completed_jobs")]
+    pub completed_jobs: usize,
+
+    /// Upper limits of the parameters of submitted optimizations
+    #[serde(default)]
+    pub optimization_limits: optimization::OptimizerLimits,
+
+    /// Secret for performing admin actions (also accepted as an admin api token)
     pub secret: String,
 
+    /// Limits for publishing layouts and submitting optimizations
+    #[serde(default)]
+    pub rate_limit: auth::RateLimitConfig,
+
     /// CORS allowed origins
     pub allowed_cors_origins: String,
 }
 
+fn default_profile_name() -> String {
+    "standard_keyboard".to_string()
+}
+
+fn default_optimization_jobs() -> usize {
+    1
+}
+
+fn default_optimization_queue() -> usize {
+    10
+}
+
+fn default_completed_jobs_ttl() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_completed_jobs() -> usize {
+    100
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub struct NGramConfig {
     pub unigrams: String,
@@ -111,59 +165,396 @@ impl Fairing for Cors {
         ));
         response.set_header(Header::new(
             "Access-Control-Allow-Methods",
-            "GET, POST, PATCH, OPTIONS",
+            "GET, POST, PATCH, DELETE, OPTIONS",
         ));
         response.set_header(Header::new(
             "Access-Control-Allow-Headers",
-            "*"
+            // the wildcard does not cover the authorization header
+            "*, Authorization"
+        ));
+        response.set_header(Header::new(
+            "Access-Control-Expose-Headers",
+            "X-Total-Count"
         ));
     }
 }
 
 #[launch]
 fn rocket() -> _ {
-    let rocket = rocket::build();
+    build(rocket::Config::figment())
+}
+
+/// Build the service from the given configuration.
+fn build(figment: Figment) -> Rocket<Build> {
+    let rocket = rocket::custom(figment);
     let figment = rocket.figment();
 
     let options: Options = figment.extract().expect("config");
 
-    let layout_config = LayoutConfig::from_yaml(&options.layout_config).expect(&format!(
-        "Could not load config file '{}'",
-        &options.layout_config
-    ));
-    let keyboard = Arc::new(Keyboard::from_yaml_object(layout_config.keyboard));
-    let layout_generator = NeoLayoutGenerator::from_object(layout_config.base_layout, keyboard);
-    let eval_params = EvaluationParameters::from_yaml(&options.eval_parameters).expect(&format!(
-        "Could not read evaluation yaml file '{}'",
-        &options.eval_parameters
-    ));
-    let p = &options.unigrams;
-    let unigrams = Unigrams::from_file(&p).expect(&format!(
-        "Could not read 1-gramme file from '{}'.",
-        &p
-    ));
-    let p = &options.bigrams;
-    let bigrams = Bigrams::from_file(&p).expect(&format!(
-        "Could not read 2-gramme file from '{}'.",
-        &p
-    ));
-    let p = &options.trigrams;
-    let trigrams = Trigrams::from_file(&p).expect(&format!(
-        "Could not read 3-gramme file from '{}'.",
-        &p
-    ));
-    let ngram_mapper_config = eval_params.ngram_mapper.clone();
-    let ngram_mapper = OnDemandNgramMapper::with_ngrams(unigrams, bigrams, trigrams, ngram_mapper_config);
-
-    let evaluator =
-        Evaluator::default(Box::new(ngram_mapper)).default_metrics(&eval_params.metrics);
-
+    let profiles = Profiles::load(&options.default_profile, &options.profile, &options.profiles);
+    let jobs = jobs::Jobs::new(
+        options.job_threads,
+        options.optimization_jobs,
+        options.optimization_queue,
+        Duration::from_secs(options.completed_jobs_ttl),
+        options.completed_jobs,
+    ).expect("Could not create worker pool");
 
     rocket
-        .manage(evaluator)
-        .manage(layout_generator)
+        .manage(Arc::new(profiles))
+        .manage(jobs)
+        .manage(auth::RateLimiter::new(options.rate_limit.clone()))
         .attach(AdHoc::config::<Options>())
         .attach(api::stage())
         .attach(Cors { options: options.clone() })
         .mount("/", FileServer::from(&options.static_dir))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use layouts_webservice_client::api::ListQuery;
+    use rocket::form::{Form, Strict};
+    use rocket::http::{ContentType, Header, Method, RawStr, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::{self, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const LAYOUT: &str = "xvlcwkhgfqyßuiaeosnrtdüöäpzbm,.j";
+    const SECRET: &str = "test_secret";
+
+    /// Distinguishes the ngram directories of clients in tests running in parallel.
+    static CLIENT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    /// A client of the service with the standard keyboard, a few ngrams, and an in-memory database.
+    fn client() -> Client {
+        let dir = std::env::temp_dir().join(format!(
+            "layouts_webservice_{}_{}",
+            std::process::id(),
+            CLIENT_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ngrams = [
+            ("unigrams", "10 e\n8 n\n6 r\n4 a\n"),
+            ("bigrams", "5 en\n4 er\n2 ra\n"),
+            ("trigrams", "3 ern\n1 era\n"),
+        ];
+        let mut figment = Figment::from(rocket::Config::debug_default())
+            .merge(("eval_parameters", "../config/evaluation_parameters.yml"))
+            .merge(("layout_config", "../config/standard_keyboard.yml"))
+            .merge(("static_dir", "static"))
+            .merge(("secret", SECRET))
+            .merge(("allowed_cors_origins", "*"))
+            .merge(("database.backend", "sqlite"))
+            .merge(("database.url", "sqlite::memory:"));
+        for (name, content) in ngrams {
+            let filename = dir.join(name);
+            std::fs::write(&filename, content).unwrap();
+            figment = figment.merge((name, filename.to_str().unwrap()));
+        }
+
+        let client = Client::tracked(build(figment)).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        client
+    }
+
+    fn admin() -> Header<'static> {
+        Header::new("Authorization", format!("Bearer {}", SECRET))
+    }
+
+    #[test]
+    fn mounted_routes() {
+        let client = client();
+
+        let response = client.get("/api/configs").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let configs: Value = response.into_json().unwrap();
+        assert_eq!(configs.as_array().unwrap().len(), 1);
+
+        let response = client.get("/api/openapi.json").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .post("/api")
+            .header(ContentType::JSON)
+            .body(
+                json::json!({ "layout": LAYOUT, "published_by": "tester" }).to_string(),
+            )
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+
+        let response = client
+            .post("/api")
+            .header(ContentType::JSON)
+            .body(json::json!({ "layout": "abc" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let uri = format!("/api/{}", RawStr::new(LAYOUT).percent_encode());
+        let response = client.get(uri).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let layout: Value = response.into_json().unwrap();
+        assert_eq!(layout["published_by"], "tester");
+
+        let response = client
+            .get("/api/standard_keyboard/?published_by=TEST")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("X-Total-Count"), Some("1"));
+
+        let response = client.get("/api?published_by=nobody").dispatch();
+        assert_eq!(response.headers().get_one("X-Total-Count"), Some("0"));
+
+        let response = client.get("/api?sort=date&order=desc&limit=1").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/api/tokens").dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client.get("/api/tokens").header(admin()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/api/export").header(admin()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap().lines().count(), 1);
+
+        let response = client.get("/api/jobs/1").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    /// Every route of the api (except for the CORS preflights) has to be described in the OpenAPI
+    /// document. Routes of further profiles are covered by the ones of the default profile.
+    #[test]
+    fn documented_routes() {
+        let client = client();
+        let document = openapi::document();
+
+        for route in client.rocket().routes() {
+            let path = route.uri.path().to_string();
+            if route.method == Method::Options || !path.starts_with("/api") {
+                continue;
+            }
+
+            let mut segments: Vec<&str> = path["/api".len()..]
+                .split('/')
+                .filter(|segment| !segment.is_empty() && *segment != "<_..>")
+                .collect();
+            if segments.first() == Some(&"<_>") {
+                segments.remove(0);
+            }
+            let documented_path = format!("/{}", segments.join("/"))
+                .replace('<', "{")
+                .replace('>', "}");
+
+            let method = route.method.as_str().to_lowercase();
+            assert!(
+                document["paths"][&documented_path][&method].is_object(),
+                "{} {} is not documented (as {})",
+                route.method,
+                path,
+                documented_path,
+            );
+        }
+    }
+
+    #[test]
+    fn documented_list_parameters() {
+        let document = openapi::document();
+        let schemas = &document["components"]["schemas"];
+
+        // a valid value for each documented parameter of the listings
+        let query: Vec<String> = document["paths"]["/"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|parameter| {
+                let name = parameter["name"].as_str().unwrap();
+                if parameter["style"] == "deepObject" {
+                    return format!("{}[Finger%20Balance]=1", name);
+                }
+
+                let mut schema = &parameter["schema"];
+                if let Some(reference) = schema["$ref"].as_str() {
+                    schema = &schemas[reference.rsplit('/').next().unwrap()];
+                }
+                if schema["oneOf"].is_array() {
+                    // an enum with documented variants
+                    schema = &schema["oneOf"][0];
+                }
+                let value = match schema["type"].as_str().unwrap() {
+                    "integer" => "1".to_string(),
+                    "number" => "1.5".to_string(),
+                    "boolean" => "true".to_string(),
+                    _ => schema["enum"][0].as_str().unwrap_or("text").to_string(),
+                };
+                format!("{}={}", name, value)
+            })
+            .collect();
+
+        // all of them are fields of the `ListQuery` (strict parsing rejects unknown fields)
+        let parsed = Form::<Strict<ListQuery>>::parse(&query.join("&")).unwrap();
+        assert!(parsed.limit.is_some() && parsed.sort.is_some() && parsed.order.is_some());
+        assert_eq!(parsed.min_metric.len(), 1);
+        assert_eq!(parsed.max_metric.len(), 1);
+    }
+
+    #[test]
+    fn import_foreign_results() {
+        let client = client();
+
+        let response = client
+            .post("/api")
+            .header(ContentType::JSON)
+            .body(json::json!({ "layout": LAYOUT }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let export = client
+            .get("/api/export")
+            .header(admin())
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let record: Value = json::from_str(export.lines().next().unwrap()).unwrap();
+
+        // two other layouts with made-up costs, one of them evaluated with another configuration
+        let mut current = record.clone();
+        current["layout"] = "vxlcwkhgfqyßuiaeosnrtdüöäpzbm,.j".into();
+        current["result"]["total_cost"] = 1.0.into();
+        let mut foreign = record;
+        foreign["layout"] = "xvclwkhgfqyßuiaeosnrtdüöäpzbm,.j".into();
+        foreign["result"]["total_cost"] = 1.0.into();
+        foreign["result"]["config_hash"] = "another configuration".into();
+
+        let response = client
+            .post("/api/import")
+            .header(admin())
+            .body(format!("{}\n{}\n", current, foreign))
+            .dispatch();
+        assert_eq!(response.status(), Status::Accepted);
+        let job: Value = response.into_json().unwrap();
+        let uri = format!("/api/jobs/{}", job["id"]);
+        let job = loop {
+            let job: Value = client.get(&uri).dispatch().into_json().unwrap();
+            if job["status"] != "queued" && job["status"] != "running" {
+                break job;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(job["status"], "finished");
+        assert_eq!(job["done"], 2);
+        let summary = &job["import_summary"];
+        assert_eq!(summary["imported"], 2);
+        assert_eq!(summary["evaluated"], 1);
+        assert_eq!(
+            summary["foreign_results"],
+            json::json!(["standard_keyboard: xvclwkhgfqyßuiaeosnrtdüöäpzbm,.j"])
+        );
+
+        let total_cost = |layout: &str| {
+            let uri = format!("/api/{}", RawStr::new(layout).percent_encode());
+            let layout: Value = client.get(uri).dispatch().into_json().unwrap();
+            layout["total_cost"].as_f64().unwrap()
+        };
+        assert_eq!(total_cost("vxlcwkhgfqyßuiaeosnrtdüöäpzbm,.j"), 1.0);
+        assert_ne!(total_cost("xvclwkhgfqyßuiaeosnrtdüöäpzbm,.j"), 1.0);
+    }
+
+    #[test]
+    fn metric_filters() {
+        let client = client();
+
+        let response = client
+            .post("/api")
+            .header(ContentType::JSON)
+            .body(json::json!({ "layout": LAYOUT }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+
+        let uri = format!("/api/{}", RawStr::new(LAYOUT).percent_encode());
+        let layout: Value = client.get(uri).dispatch().into_json().unwrap();
+        let metric = layout["details"]["individual_results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|results| results["metric_costs"].as_array().unwrap())
+            .find(|metric| metric["weighted_cost"].is_f64())
+            .unwrap();
+        let name = metric["core"]["name"].as_str().unwrap();
+        let cost = metric["weighted_cost"].as_f64().unwrap();
+
+        let names: Vec<String> = client.get("/api/metrics").dispatch().into_json().unwrap();
+        assert!(names.iter().any(|known| known == name));
+        let name = RawStr::new(name).percent_encode();
+
+        let count = |query: String| {
+            let response = client.get(format!("/api?{}", query)).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            response.headers().get_one("X-Total-Count").unwrap().to_string()
+        };
+        assert_eq!(count(format!("min_metric[{}]={}", name, cost - 1.0)), "1");
+        assert_eq!(count(format!("max_metric[{}]={}", name, cost - 1.0)), "0");
+        assert_eq!(count(format!("min_metric[{}]={}", name, cost + 1.0)), "0");
+        assert_eq!(count(format!("max_metric[{}]={}", name, cost + 1.0)), "1");
+
+        let response = client.get("/api?min_metric[Unknown%20Metric]=1").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    fn user_token(client: &Client, name: &str) -> Header<'static> {
+        let response = client
+            .post("/api/tokens")
+            .header(admin())
+            .header(ContentType::JSON)
+            .body(json::json!({ "name": name }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let token: Value = response.into_json().unwrap();
+        let token = token["token"].as_str().unwrap();
+
+        Header::new("Authorization", format!("Bearer {}", token))
+    }
+
+    #[test]
+    fn optimization_permissions() {
+        let client = client();
+        let optimize = |max_iters: u64, token: Option<Header<'static>>| {
+            let mut request = client.post("/api/optimize").header(ContentType::JSON).body(
+                json::json!({
+                    "optimizer": "sa",
+                    "parameters": {
+                        "key_switches": 1,
+                        "stall_accepted": 10,
+                        "max_iters": max_iters,
+                    },
+                    "layout": LAYOUT,
+                })
+                .to_string(),
+            );
+            if let Some(token) = token {
+                request = request.header(token);
+            }
+            request.dispatch()
+        };
+
+        assert_eq!(optimize(10, None).status(), Status::Unauthorized);
+        let alice = user_token(&client, "alice");
+        let response = optimize(1_000_000_000, Some(alice.clone()));
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = optimize(10, Some(alice.clone()));
+        assert_eq!(response.status(), Status::Accepted);
+        let job: Value = response.into_json().unwrap();
+        assert_eq!(job["submitted_by"], "alice");
+        let cancel = format!("/api/jobs/{}/cancel", job["id"]);
+
+        let response = client.post(&cancel).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        let bob = user_token(&client, "bob");
+        let response = client.post(&cancel).header(bob).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client.post(&cancel).header(alice).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.post(&cancel).header(admin()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
@@ -0,0 +1,330 @@
+//! The `openapi` module describes the api as an OpenAPI document (served under
+//! `/api/openapi.json`). The schemas of the request and response bodies and the query parameters
+//! of the listings are generated from the types the handlers (and the `layouts_webservice_client`
+//! crate) use. The paths, the remaining parameters, and the responses are written by hand; the
+//! tests check that each mounted route is documented.
+
+use super::api::{NewToken, PostToken};
+use super::jobs::JobInfo;
+use super::optimization::OptimizationRequest;
+use super::storage::ApiTokenDB;
+
+use layouts_webservice_client::api::{
+    EvalConfig, LayoutEvaluation, LayoutRecord, ListQuery, PostLayout,
+};
+use rocket::serde::json::{json, Value};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+
+/// A JSON request or response body with the given schema.
+fn json_content(schema: &Schema) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+fn response(description: &str, schema: Option<&Schema>) -> Value {
+    match schema {
+        Some(schema) => json!({ "description": description, "content": json_content(schema) }),
+        None => json!({ "description": description }),
+    }
+}
+
+fn array(schema: &Schema) -> Value {
+    json!({ "type": "array", "items": schema })
+}
+
+fn array_response(description: &str, schema: &Schema) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": array(schema) } },
+    })
+}
+
+fn parameter(name: &str, location: &str, schema: Value, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": location,
+        "required": location == "path",
+        "schema": schema,
+        "description": description,
+    })
+}
+
+/// The query parameters of the layout listings, generated from the fields of `ListQuery`. The
+/// filters on individual metrics are nested parameters that are not included.
+fn list_parameters(generator: &mut SchemaGenerator) -> Vec<Value> {
+    let query = ListQuery::json_schema(generator).into_object();
+    let properties = query
+        .object
+        .map(|object| object.properties)
+        .unwrap_or_default();
+
+    properties
+        .into_iter()
+        .map(|(name, schema)| {
+            let mut schema = schema.into_object();
+            let description = schema
+                .metadata
+                .as_mut()
+                .and_then(|metadata| metadata.description.take())
+                .unwrap_or_default();
+            // query parameters are optional anyway
+            schema.extensions.remove("nullable");
+            parameter(&name, "query", json!(schema), &description)
+        })
+        .collect()
+}
+
+/// The secret (as plain text body) of the admin requests that predate the api tokens.
+fn secret_body() -> Value {
+    json!({
+        "description": "Admin secret (may be empty with an admin token)",
+        "content": { "text/plain": { "schema": { "type": "string" } } }
+    })
+}
+
+/// A JSONL body (one JSON value with the given schema per line).
+fn jsonl_content(schema: &Schema) -> Value {
+    json!({ "application/x-ndjson": { "schema": schema } })
+}
+
+/// Generate the OpenAPI document of the api.
+pub fn document() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let layout_evaluation = generator.subschema_for::<LayoutEvaluation>();
+    let post_layout = generator.subschema_for::<PostLayout>();
+    let eval_config = generator.subschema_for::<EvalConfig>();
+    let optimization_request = generator.subschema_for::<OptimizationRequest>();
+    let job_info = generator.subschema_for::<JobInfo>();
+    let api_token = generator.subschema_for::<ApiTokenDB>();
+    let post_token = generator.subschema_for::<PostToken>();
+    let new_token = generator.subschema_for::<NewToken>();
+    let layout_record = generator.subschema_for::<LayoutRecord>();
+    let metric_name = generator.subschema_for::<String>();
+    let mut list_parameters = list_parameters(&mut generator);
+    let schemas = generator.take_definitions();
+
+    let string = json!({ "type": "string" });
+    let integer = json!({ "type": "integer" });
+    let number = json!({ "type": "number" });
+    let boolean = json!({ "type": "boolean" });
+    let job_id = parameter("id", "path", integer.clone(), "Id of the job");
+    for (name, description) in [
+        (
+            "min_metric",
+            "Minimal weighted costs of individual metrics, e.g. `min_metric[Finger Balance]=0.5`",
+        ),
+        ("max_metric", "Maximal weighted costs of individual metrics"),
+    ] {
+        list_parameters.push(json!({
+            "name": name,
+            "in": "query",
+            "style": "deepObject",
+            "schema": { "type": "object", "additionalProperties": number },
+            "description": description,
+        }));
+    }
+    let layout_list = json!({
+        "description": "Layouts matching the query",
+        "headers": {
+            "X-Total-Count": {
+                "description": "Total number of layouts matching the query (ignoring the page)",
+                "schema": integer,
+            }
+        },
+        "content": { "application/json": { "schema": array(&layout_evaluation) } },
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Keyboard layout optimizer",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Evaluation and publication of keyboard layouts. The routes of layouts (`/`, `/{layout}`, `/metrics`, `/reeval`, `/optimize`) serve the default profile; they are available for further profiles under `/{profile}/...` as well.",
+        },
+        "servers": [{ "url": "/api" }],
+        "paths": {
+            "/": {
+                "get": {
+                    "summary": "List the published layouts",
+                    "parameters": list_parameters,
+                    "responses": {
+                        "200": layout_list,
+                        "400": response("Unknown metric in `min_metric` or `max_metric`", None),
+                    },
+                },
+                "post": {
+                    "summary": "Publish a layout",
+                    "security": [{}, { "token": [] }],
+                    "requestBody": { "required": true, "content": json_content(&post_layout) },
+                    "responses": {
+                        "201": response("The evaluated layout (the stored one if it was published before)", Some(&layout_evaluation)),
+                        "400": response("Invalid layout", None),
+                        "401": response("Unknown token", None),
+                        "403": response("Highlighting requires admin permissions", None),
+                        "429": response("Rate limit exceeded", None),
+                    },
+                },
+            },
+            "/{layout}": {
+                "get": {
+                    "summary": "Fetch a layout with its details",
+                    "parameters": [
+                        parameter("layout", "path", string.clone(), "The layout"),
+                        parameter("config", "query", integer.clone(), "Version of the evaluation configuration (default: the current one)"),
+                    ],
+                    "responses": {
+                        "200": response("The layout", Some(&layout_evaluation)),
+                        "404": response("The layout has not been published (or not been evaluated with the configuration)", None),
+                    },
+                },
+            },
+            "/configs": {
+                "get": {
+                    "summary": "List the versions of the evaluation configuration",
+                    "responses": { "200": array_response("All configuration versions", &eval_config) },
+                },
+            },
+            "/metrics": {
+                "get": {
+                    "summary": "List the names of the metrics (e.g. for `min_metric` and `max_metric`)",
+                    "responses": { "200": array_response("Names of the profile's metrics", &metric_name) },
+                },
+            },
+            "/reeval": {
+                "post": {
+                    "summary": "Re-evaluate all layouts with the current configuration (admins only)",
+                    "security": [{}, { "token": [] }],
+                    "requestBody": secret_body(),
+                    "responses": {
+                        "202": response("The started job", Some(&job_info)),
+                        "401": response("Missing admin permissions", None),
+                        "409": response("A re-evaluation is running already", None),
+                    },
+                },
+            },
+            "/optimize": {
+                "post": {
+                    "summary": "Optimize a layout on the server (users with a token only)",
+                    "security": [{ "token": [] }],
+                    "requestBody": { "required": true, "content": json_content(&optimization_request) },
+                    "responses": {
+                        "202": response("The started job", Some(&job_info)),
+                        "400": response("Invalid layout or parameters (e.g. exceeding the limits)", None),
+                        "401": response("Missing api token", None),
+                        "429": response("Rate limit exceeded", None),
+                        "503": response("Too many optimizations are waiting already", None),
+                    },
+                },
+            },
+            "/jobs": {
+                "get": {
+                    "summary": "List the jobs",
+                    "responses": { "200": array_response("All jobs", &job_info) },
+                },
+            },
+            "/jobs/{id}": {
+                "get": {
+                    "summary": "Fetch the state of a job",
+                    "parameters": [job_id],
+                    "responses": {
+                        "200": response("The job", Some(&job_info)),
+                        "404": response("Unknown job", None),
+                    },
+                },
+            },
+            "/jobs/{id}/events": {
+                "get": {
+                    "summary": "Stream the events of a job (`status` and `progress`) as server-sent events",
+                    "parameters": [job_id],
+                    "responses": {
+                        "200": { "description": "The events", "content": { "text/event-stream": { "schema": string.clone() } } },
+                        "404": response("Unknown job", None),
+                    },
+                },
+            },
+            "/jobs/{id}/cancel": {
+                "post": {
+                    "summary": "Cancel a job (admins and the user who submitted it)",
+                    "security": [{}, { "token": [] }],
+                    "parameters": [job_id],
+                    "requestBody": secret_body(),
+                    "responses": {
+                        "200": response("The job", Some(&job_info)),
+                        "401": response("Missing admin permissions", None),
+                        "403": response("The job was submitted by another user", None),
+                        "404": response("Unknown job", None),
+                    },
+                },
+            },
+            "/tokens": {
+                "get": {
+                    "summary": "List the api tokens (admins only)",
+                    "security": [{ "token": [] }],
+                    "responses": {
+                        "200": array_response("All tokens", &api_token),
+                        "403": response("Missing admin permissions", None),
+                    },
+                },
+                "post": {
+                    "summary": "Issue an api token (admins only)",
+                    "security": [{ "token": [] }],
+                    "requestBody": { "required": true, "content": json_content(&post_token) },
+                    "responses": {
+                        "201": response("The new token (it is only returned once)", Some(&new_token)),
+                        "403": response("Missing admin permissions", None),
+                    },
+                },
+            },
+            "/tokens/{id}": {
+                "delete": {
+                    "summary": "Revoke an api token (admins only)",
+                    "security": [{ "token": [] }],
+                    "parameters": [parameter("id", "path", integer, "Id of the token")],
+                    "responses": {
+                        "204": response("The token was revoked", None),
+                        "403": response("Missing admin permissions", None),
+                        "404": response("Unknown token", None),
+                    },
+                },
+            },
+            "/export": {
+                "get": {
+                    "summary": "Export the layouts with their results and provenance as JSONL (admins only)",
+                    "security": [{ "token": [] }],
+                    "parameters": [parameter("profile", "query", string, "Only layouts of this profile (default: all profiles)")],
+                    "responses": {
+                        "200": { "description": "One layout per line", "content": jsonl_content(&layout_record) },
+                        "403": response("Missing admin permissions", None),
+                    },
+                },
+            },
+            "/import": {
+                "post": {
+                    "summary": "Import layouts given as JSONL in a background job, skipping those that are stored already (admins only); the summary is part of the finished job",
+                    "security": [{ "token": [] }],
+                    "parameters": [parameter("reevaluate", "query", boolean, "Evaluate all layouts with the current configuration instead of keeping their results")],
+                    "requestBody": { "required": true, "content": jsonl_content(&layout_record) },
+                    "responses": {
+                        "202": response("The started job", Some(&job_info)),
+                        "403": response("Missing admin permissions", None),
+                        "409": response("An import is running already", None),
+                        "413": response("The file exceeds the \"import\" limit", None),
+                    },
+                },
+            },
+            "/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": { "200": { "description": "The OpenAPI document" } },
+                },
+            },
+        },
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "token": { "type": "http", "scheme": "bearer" },
+            },
+        },
+    })
+}
@@ -0,0 +1,231 @@
+//! The `optimization` module runs the layout optimizers of the `layout_optimization_*` crates
+//! on the server. Optimizations are submitted through the api and executed as background jobs.
+
+use super::jobs::{Job, JobEvent};
+use super::profiles::Profile;
+
+use anyhow::{bail, Result};
+use keyboard_layout::layout::Layout;
+use layout_optimization::common::Cache;
+use layout_optimization::observer::{OptimizationObserver, ProgressEvent};
+use layout_optimization_genevo::optimization as genevo_optimization;
+use layout_optimization_sa::optimization as sa_optimization;
+use rocket::serde::{json::Value, Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Optimizers that can be run on the server.
+///
+/// The ABC optimizer is not offered, as it runs indefinitely on its own threads.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum Optimizer {
+    /// Genetic algorithm (`layout_optimization_genevo`)
+    Genevo,
+    /// Simulated annealing (`layout_optimization_sa`)
+    Sa,
+}
+
+/// An optimization job as submitted to the api.
+#[derive(Clone, Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct OptimizationRequest {
+    pub optimizer: Optimizer,
+    /// Parameters of the optimizer (its defaults are used if omitted)
+    pub parameters: Option<Value>,
+    /// Layout to start from (or, without `start_with_layout`, the keys to permute)
+    pub layout: String,
+    /// Keys that keep their position from `layout`
+    #[serde(default)]
+    pub fixed_characters: String,
+    /// Start the optimization from `layout` instead of random permutations
+    #[serde(default)]
+    pub start_with_layout: bool,
+    pub published_by: Option<String>,
+}
+
+/// Upper limits of the optimizer parameters accepted from the api, so that a single request can
+/// not occupy the server indefinitely.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct OptimizerLimits {
+    /// Maximal number of generations of the genetic algorithm
+    #[serde(default = "default_generation_limit")]
+    pub generation_limit: u64,
+
+    /// Maximal population size of the genetic algorithm
+    #[serde(default = "default_population_size")]
+    pub population_size: usize,
+
+    /// Maximal number of iterations of simulated annealing
+    #[serde(default = "default_max_iters")]
+    pub max_iters: u64,
+}
+
+impl Default for OptimizerLimits {
+    fn default() -> Self {
+        Self {
+            generation_limit: default_generation_limit(),
+            population_size: default_population_size(),
+            max_iters: default_max_iters(),
+        }
+    }
+}
+
+fn default_generation_limit() -> u64 {
+    5000
+}
+
+fn default_population_size() -> usize {
+    200
+}
+
+fn default_max_iters() -> u64 {
+    500_000
+}
+
+/// Fail if a parameter exceeds its limit.
+fn check_limit<T: PartialOrd + std::fmt::Display>(name: &str, value: T, limit: T) -> Result<()> {
+    if value > limit {
+        bail!("'{}' is {}, the maximum is {}", name, value, limit);
+    }
+
+    Ok(())
+}
+
+/// Parsed parameters of an optimizer.
+pub enum OptimizerParameters {
+    Genevo(genevo_optimization::Parameters),
+    Sa(sa_optimization::Parameters),
+}
+
+impl OptimizerParameters {
+    /// Parse the parameters given in the request, falling back to the optimizer's defaults.
+    /// Fails if they exceed the limits.
+    pub fn from_request(request: &OptimizationRequest, limits: &OptimizerLimits) -> Result<Self> {
+        let params = match (request.optimizer, &request.parameters) {
+            (Optimizer::Genevo, None) => Self::Genevo(Default::default()),
+            (Optimizer::Genevo, Some(p)) => Self::Genevo(serde_json::from_value(p.clone())?),
+            (Optimizer::Sa, None) => Self::Sa(Default::default()),
+            (Optimizer::Sa, Some(p)) => Self::Sa(serde_json::from_value(p.clone())?),
+        };
+
+        match &params {
+            Self::Genevo(p) => {
+                check_limit(
+                    "generation_limit",
+                    p.generation_limit,
+                    limits.generation_limit,
+                )?;
+                check_limit("population_size", p.population_size, limits.population_size)?;
+            }
+            Self::Sa(p) => check_limit("max_iters", p.max_iters, limits.max_iters)?,
+        }
+
+        Ok(params)
+    }
+
+    /// The parameters as stored in the provenance of the resulting layout.
+    pub fn to_json(&self) -> Result<Value> {
+        let value = match self {
+            Self::Genevo(p) => serde_json::to_value(p)?,
+            Self::Sa(p) => serde_json::to_value(p)?,
+        };
+
+        Ok(value)
+    }
+}
+
+/// Forwards the progress of an optimization to its job. Events are published at most every
+/// `EVENT_INTERVAL` (except for new best layouts), so that fast optimizers do not flood the
+/// subscribers.
+struct JobObserver {
+    job: Arc<Job>,
+    total: usize,
+    last_event: Mutex<Option<Instant>>,
+}
+
+const EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+impl JobObserver {
+    fn new(job: &Arc<Job>, total: usize) -> Self {
+        Self {
+            job: job.clone(),
+            total,
+            last_event: Mutex::new(None),
+        }
+    }
+}
+
+impl OptimizationObserver for JobObserver {
+    fn notify(&self, event: &ProgressEvent) {
+        self.job.set_progress(event.iteration as usize, self.total);
+        if event.new_best {
+            self.job
+                .set_best(event.best_layout.clone(), event.best_cost);
+        }
+
+        let mut last_event = self.last_event.lock().unwrap();
+        let due = last_event.is_none_or(|last| last.elapsed() >= EVENT_INTERVAL);
+        if event.new_best || due {
+            self.job.publish(JobEvent::Progress(event.clone()));
+            *last_event = Some(Instant::now());
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.job.is_cancelled()
+    }
+}
+
+/// Run the optimization (blocking) and return the best layout found. The job's progress and its
+/// best layout so far are updated while running, and the optimization stops early if the job is
+/// cancelled.
+pub fn optimize(
+    job: &Arc<Job>,
+    profile: &Profile,
+    request: &OptimizationRequest,
+    params: &OptimizerParameters,
+) -> Result<Layout> {
+    let total = match params {
+        OptimizerParameters::Genevo(params) => params.generation_limit as usize,
+        OptimizerParameters::Sa(params) => params.max_iters as usize,
+    };
+    let (layout, _) = match params {
+        OptimizerParameters::Genevo(params) => {
+            let observer = JobObserver::new(job, total);
+            let options = genevo_optimization::OptimizationOptions {
+                evaluator: &profile.evaluator,
+                layout_generator: &profile.layout_generator,
+                layout_str: &request.layout,
+                fixed_characters: &request.fixed_characters,
+                start_with_layout: request.start_with_layout,
+                cache_results: true,
+            };
+            genevo_optimization::optimize_with_observer(params, &options, &observer)
+        }
+        OptimizerParameters::Sa(params) => {
+            let observer = Arc::new(JobObserver::new(job, total));
+            let process_name = format!("Job {}", job.id);
+            let options = sa_optimization::OptimizationOptions {
+                process_name: &process_name,
+                evaluator: &profile.evaluator,
+                layout_generator: &profile.layout_generator,
+                layout_str: &request.layout,
+                fixed_characters: &request.fixed_characters,
+                start_with_layout: request.start_with_layout,
+                init_temp: None,
+                log_everything: false,
+                result_cache: Some(Cache::new()),
+            };
+            sa_optimization::optimize_with_observer(params, options, observer)
+        }
+    };
+    if !job.is_cancelled() {
+        // the optimizers may stop before their last iteration (e.g. if they stall)
+        job.set_progress(total, total);
+    }
+
+    Ok(layout)
+}
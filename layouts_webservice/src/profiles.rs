@@ -0,0 +1,153 @@
+//! The `profiles` module provides named evaluation profiles. Each profile has its own keyboard,
+//! evaluation parameters, and ngrams, so the same letter arrangement can be evaluated on
+//! different physical boards by a single service.
+
+use super::eval_config::EvalConfig;
+use super::{EvaluationParameters, LayoutConfig};
+
+use keyboard_layout::{keyboard::Keyboard, layout_generator::NeoLayoutGenerator};
+use layout_evaluation::{
+    evaluation::Evaluator,
+    ngram_mapper::on_demand_ngram_mapper::OnDemandNgramMapper,
+    ngrams::{Bigrams, Trigrams, Unigrams},
+};
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Configuration files of a profile.
+#[derive(Clone, Deserialize, Debug)]
+pub struct ProfileOptions {
+    /// Filename of evaluation configuration file to use
+    pub eval_parameters: String,
+
+    /// Filename of layout configuration file to use
+    pub layout_config: String,
+
+    /// Unigram file
+    pub unigrams: String,
+
+    /// Bigram file
+    pub bigrams: String,
+
+    /// Trigram file
+    pub trigrams: String,
+}
+
+/// A named combination of keyboard, evaluation parameters, and ngrams.
+pub struct Profile {
+    pub name: String,
+    pub layout_generator: NeoLayoutGenerator,
+    pub evaluator: Evaluator,
+    pub eval_config: EvalConfig,
+}
+
+impl Profile {
+    /// Load the keyboard, evaluation parameters, and ngrams of a profile. Panics if any of the
+    /// files can not be read.
+    pub fn load(name: &str, options: &ProfileOptions) -> Self {
+        let layout_config = LayoutConfig::from_yaml(&options.layout_config).unwrap_or_else(|e| {
+            panic!(
+                "Could not load config file '{}': {:?}",
+                &options.layout_config, e
+            )
+        });
+        let keyboard = Arc::new(
+            Keyboard::from_yaml_object(layout_config.keyboard).unwrap_or_else(|e| {
+                panic!(
+                    "Invalid keyboard config file '{}': {:#}",
+                    &options.layout_config, e
+                )
+            }),
+        );
+        let layout_generator = NeoLayoutGenerator::from_object(layout_config.base_layout, keyboard)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Invalid layout config file '{}': {}",
+                    &options.layout_config, e
+                )
+            });
+        let eval_params =
+            EvaluationParameters::from_yaml(&options.eval_parameters).unwrap_or_else(|e| {
+                panic!(
+                    "Could not read evaluation yaml file '{}': {:?}",
+                    &options.eval_parameters, e
+                )
+            });
+        let p = &options.unigrams;
+        let unigrams = Unigrams::from_file(p)
+            .unwrap_or_else(|e| panic!("Could not read 1-gramme file from '{}': {:?}", p, e));
+        let p = &options.bigrams;
+        let bigrams = Bigrams::from_file(p)
+            .unwrap_or_else(|e| panic!("Could not read 2-gramme file from '{}': {:?}", p, e));
+        let p = &options.trigrams;
+        let trigrams = Trigrams::from_file(p)
+            .unwrap_or_else(|e| panic!("Could not read 3-gramme file from '{}': {:?}", p, e));
+        let ngram_mapper_config = eval_params.ngram_mapper.clone();
+        let ngram_mapper =
+            OnDemandNgramMapper::with_ngrams(unigrams, bigrams, trigrams, ngram_mapper_config);
+
+        let evaluator = Evaluator::default(Box::new(ngram_mapper))
+            .default_metrics(&eval_params.metrics)
+            .expect("Could not initialize metrics");
+
+        let eval_config =
+            EvalConfig::from_options(options).expect("Could not hash evaluation configuration");
+
+        Self {
+            name: name.to_string(),
+            layout_generator,
+            evaluator,
+            eval_config,
+        }
+    }
+}
+
+/// All profiles served by the service. The default profile is served under `/api/`, all profiles
+/// (including the default one) under `/api/<profile>/`.
+pub struct Profiles {
+    default: String,
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    /// Load the default profile and the further named profiles.
+    pub fn load(
+        default_name: &str,
+        default_options: &ProfileOptions,
+        options: &BTreeMap<String, ProfileOptions>,
+    ) -> Self {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(
+            default_name.to_string(),
+            Profile::load(default_name, default_options),
+        );
+        for (name, profile_options) in options.iter() {
+            if name == default_name {
+                panic!(
+                    "Profile '{}' is already defined by the top-level configuration",
+                    name
+                );
+            }
+            profiles.insert(name.to_string(), Profile::load(name, profile_options));
+        }
+
+        Self {
+            default: default_name.to_string(),
+            profiles,
+        }
+    }
+
+    pub fn default_profile(&self) -> &Profile {
+        &self.profiles[&self.default]
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Profile> {
+        self.profiles.values()
+    }
+}
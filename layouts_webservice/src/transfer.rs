@@ -0,0 +1,233 @@
+//! The `transfer` module exports the stored layouts with their results and provenance as JSONL
+//! (one `LayoutRecord` per line) and imports such files, e.g. to move the layouts to another
+//! database or to seed a new instance of the service.
+
+use super::eval_config::ConfigVersions;
+use super::jobs::Job;
+use super::profiles::Profiles;
+use super::storage::{LayoutEvaluationDB, LayoutExportDB, LayoutImport, LayoutStorage};
+
+use anyhow::{anyhow, Result};
+use layouts_webservice_client::api::{ImportSummary, LayoutRecord, LayoutResult};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+impl TryFrom<LayoutExportDB> for LayoutRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(item: LayoutExportDB) -> Result<Self> {
+        let layout = item.layout;
+        Ok(Self {
+            profile: Some(layout.profile),
+            layout: layout.layout,
+            published_by: layout.published_by,
+            highlight: layout.highlight,
+            created: item.created,
+            provenance: layout
+                .provenance
+                .and_then(|p| serde_json::from_str(&p).ok()),
+            result: Some(LayoutResult {
+                total_cost: layout.total_cost,
+                details: serde_json::from_str(&layout.details_json)?,
+                printed: layout.printed,
+                config_hash: item.config_hash,
+            }),
+        })
+    }
+}
+
+/// Export all layouts (of a profile, if given) as JSONL.
+pub async fn export(db: &dyn LayoutStorage, profile: Option<&str>) -> Result<String> {
+    let mut jsonl = String::new();
+    for layout in db.export(profile).await? {
+        let record = LayoutRecord::try_from(layout)?;
+        jsonl.push_str(&serde_json::to_string(&record)?);
+        jsonl.push('\n');
+    }
+
+    Ok(jsonl)
+}
+
+/// A layout of an import that is not stored yet.
+struct NewLayout {
+    profile: String,
+    layout: String,
+    record: LayoutRecord,
+    /// Version of the configuration the exported result was produced with (if it is known)
+    config_id: Option<i32>,
+    /// Whether the layout has to be evaluated
+    evaluate: bool,
+}
+
+/// Import layouts given as JSONL. Layouts that are stored already (or occur multiple times) are
+/// skipped, as are invalid lines (they are reported in the summary). Layouts without result are
+/// evaluated with the current configuration of their profile, with `reevaluate` all of them are.
+/// Results produced with another (or an unknown) configuration are not comparable to the stored
+/// ones, so their layouts are evaluated as well (and reported in the summary). The progress of the
+/// evaluations is reported to the job, nothing is stored if it is cancelled.
+pub async fn import(
+    job: &Arc<Job>,
+    db: &dyn LayoutStorage,
+    profiles: Arc<Profiles>,
+    versions: &ConfigVersions,
+    pool: Arc<rayon::ThreadPool>,
+    jsonl: &str,
+    reevaluate: bool,
+) -> Result<ImportSummary> {
+    let config_ids: HashMap<String, i32> = db
+        .list_configs()
+        .await?
+        .into_iter()
+        .map(|config| (config.hash, config.id))
+        .collect();
+
+    let mut summary = ImportSummary::default();
+    let mut seen = HashSet::new();
+    let mut layouts = Vec::new();
+    for (i, line) in jsonl.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: LayoutRecord = match serde_json::from_str(line) {
+            Ok(record) => record,
+            Err(e) => {
+                summary.errors.push(format!("line {}: {}", i + 1, e));
+                continue;
+            }
+        };
+        let profile = match &record.profile {
+            Some(name) => profiles.get(name),
+            None => Some(profiles.default_profile()),
+        };
+        let profile = match profile {
+            Some(profile) => profile,
+            None => {
+                let name = record.profile.unwrap_or_default();
+                summary
+                    .errors
+                    .push(format!("line {}: unknown profile '{}'", i + 1, name));
+                continue;
+            }
+        };
+        let layout = match profile.layout_generator.generate(&record.layout) {
+            Ok(layout) => layout.as_text(),
+            Err(e) => {
+                summary
+                    .errors
+                    .push(format!("line {}: invalid layout: {}", i + 1, e));
+                continue;
+            }
+        };
+
+        if !seen.insert((profile.name.clone(), layout.clone()))
+            || db.get(&profile.name, &layout).await?.is_some()
+        {
+            summary.duplicates += 1;
+            continue;
+        }
+
+        let config_id = record
+            .result
+            .as_ref()
+            .and_then(|result| result.config_hash.as_ref())
+            .and_then(|hash| config_ids.get(hash).copied());
+        let foreign_result =
+            record.result.is_some() && config_id != versions.0.get(&profile.name).copied();
+        if foreign_result && !reevaluate {
+            summary
+                .foreign_results
+                .push(format!("{}: {}", profile.name, layout));
+        }
+        layouts.push(NewLayout {
+            profile: profile.name.clone(),
+            layout,
+            evaluate: reevaluate || record.result.is_none() || foreign_result,
+            config_id,
+            record,
+        });
+    }
+
+    job.set_total(layouts.len());
+
+    let config_versions = versions.0.clone();
+    let worker_job = job.clone();
+    let imports: Result<Vec<(LayoutImport, bool)>> =
+        rocket::tokio::task::spawn_blocking(move || {
+            pool.install(|| {
+                layouts
+                    .into_par_iter()
+                    .map(|new| {
+                        if worker_job.is_cancelled() {
+                            return Err(anyhow!("Job was cancelled"));
+                        }
+                        let import = to_import(&profiles, &config_versions, new)?;
+                        worker_job.add_done();
+                        Ok(import)
+                    })
+                    .collect()
+            })
+        })
+        .await?;
+    let (imports, evaluated): (Vec<LayoutImport>, Vec<bool>) = imports?.into_iter().unzip();
+
+    if job.is_cancelled() {
+        return Err(anyhow!("Job was cancelled"));
+    }
+
+    db.import(&imports).await?;
+
+    summary.imported = imports.len();
+    summary.evaluated = evaluated.into_iter().filter(|evaluated| *evaluated).count();
+
+    Ok(summary)
+}
+
+/// Evaluate a new layout (if required) and prepare it for storage. Returns whether it was
+/// evaluated as well.
+fn to_import(
+    profiles: &Profiles,
+    config_versions: &BTreeMap<String, i32>,
+    new: NewLayout,
+) -> Result<(LayoutImport, bool)> {
+    let (total_cost, details_json, printed, config_id) = match new.record.result {
+        Some(result) if !new.evaluate => (
+            result.total_cost,
+            serde_json::to_string(&result.details)?,
+            result.printed,
+            new.config_id,
+        ),
+        _ => {
+            let profile = profiles
+                .get(&new.profile)
+                .ok_or_else(|| anyhow!("Unknown profile '{}'", new.profile))?;
+            let layout = profile.layout_generator.generate(&new.layout)?;
+            let evaluation_result = profile.evaluator.evaluate_layout(&layout);
+            (
+                evaluation_result.total_cost(),
+                serde_json::to_string(&evaluation_result)?,
+                format!("{}", evaluation_result),
+                config_versions.get(&new.profile).copied(),
+            )
+        }
+    };
+
+    let import = LayoutImport {
+        layout: LayoutEvaluationDB {
+            id: None,
+            profile: new.profile,
+            layout: new.layout,
+            total_cost,
+            details_json,
+            printed,
+            published_by: new.record.published_by,
+            highlight: new.record.highlight,
+            config_id,
+            provenance: new.record.provenance.map(|p| p.to_string()),
+        },
+        created: new.record.created,
+    };
+
+    Ok((import, new.evaluate))
+}
@@ -10,10 +10,18 @@ Vue.component('layouts-app', {
   <b-row>
     <b-col xl="6">
       <b-form inline>
-        <b-form-input v-model="filter" debounce="500" placeholder="Filter" class="mb-2 mr-sm-2 mb-sm-0"></b-form-input>
+        <b-form-input v-model="publishedBy" debounce="500" placeholder="Published by" class="mb-2 mr-sm-2 mb-sm-0"></b-form-input>
+        <b-form-input v-model="layoutPattern" debounce="500" placeholder="Layout (_ for any key)" class="mb-2 mr-sm-2 mb-sm-0"></b-form-input>
+        <b-form-input v-model="maxCost" type="number" debounce="500" placeholder="Max. cost" class="mb-2 mr-sm-2 mb-sm-0"></b-form-input>
+        <b-form-select v-model="sort" :options="sortOptions" class="mb-2 mr-sm-2 mb-sm-0"></b-form-select>
+      </b-form>
+      <b-form inline>
+        <b-form-select v-model="metric" :options="metricOptions" class="mb-2 mr-sm-2 mb-sm-0"></b-form-select>
+        <b-form-input v-model="maxMetricCost" type="number" debounce="500" placeholder="Max. metric cost" class="mb-2 mr-sm-2 mb-sm-0"></b-form-input>
+        <b-form-checkbox v-model="onlyHighlighted" class="mb-2 mr-sm-2 mb-sm-0">only well-known</b-form-checkbox>
         <b-form-checkbox v-model="bestInFamily" class="mb-2 mr-sm-2 mb-sm-0">only show best in family (clears selection)</b-form-checkbox>
       </b-form>
-      <layouts-table :url="url" :bestInFamily="bestInFamily" :filter="filter" @details="setDetails"></layouts-table>
+      <layouts-table :url="url" :bestInFamily="bestInFamily" :query="query" @details="setDetails"></layouts-table>
     </b-col>
 
     <b-col xl="6">
@@ -42,10 +50,38 @@ Vue.component('layouts-app', {
     data () {
         return {
             details: [],
-            filter: null,
+            publishedBy: null,
+            layoutPattern: null,
+            maxCost: null,
+            metric: null,
+            metricNames: [],
+            maxMetricCost: null,
+            onlyHighlighted: false,
+            sort: "cost",
+            sortOptions: [
+                { value: "cost", text: "Lowest cost first" },
+                { value: "-cost", text: "Highest cost first" },
+                { value: "-date", text: "Newest first" },
+                { value: "date", text: "Oldest first" },
+            ],
         }
     },
     computed: {
+        query () {
+            // query parameters for the layout list (filtering is done by the server)
+            const params = new URLSearchParams()
+            params.set("sort", this.sort.replace("-", ""))
+            params.set("order", this.sort.startsWith("-") ? "desc" : "asc")
+            if (this.publishedBy) params.set("published_by", this.publishedBy)
+            if (this.layoutPattern) params.set("layout", this.layoutPattern)
+            if (this.maxCost) params.set("max_cost", this.maxCost)
+            if (this.onlyHighlighted) params.set("highlight", true)
+            if (this.metric && this.maxMetricCost) params.set(`max_metric[${this.metric}]`, this.maxMetricCost)
+            return params.toString()
+        },
+        metricOptions () {
+            return [{ value: null, text: "Metric" }].concat(this.metricNames)
+        },
         chartStyles () {
             return {
                 height: "600px",
@@ -54,6 +90,12 @@ Vue.component('layouts-app', {
         },
     },
     created () {
+        // only known metric names are accepted by the server's filter
+        fetch(`${this.url}/metrics`)
+            .then(response => response.ok ? response.json() : [])
+            .then(names => {
+                this.metricNames = names
+            })
     },
     mounted () {
     },
@@ -185,28 +185,24 @@ Vue.component('layout-details', {
 Vue.component('layouts-table', {
     template: `
 <div>
+  <b-alert :show="error !== null" variant="danger">{{ error }}</b-alert>
   <b-table
     sticky-header="600px"
     primary-key="layout"
     small
     head-variant="light"
-    sort-by="total_cost"
-    sort-icon-left
-    no-sort-reset
     :items="rows"
     :fields="fields"
-    :filter="filter"
-    :per-page="perPage"
+    :per-page="bestInFamily ? perPage : 0"
     :current-page="currentPage"
     :tbody-tr-class="rowClass"
     @row-clicked="onRowClicked"
-    @filtered="onFiltered"
    >
   </b-table>
   <b-pagination
-    v-if="filteredRows > perPage"
+    v-if="totalRows > perPage"
     v-model="currentPage"
-    :total-rows="filteredRows"
+    :total-rows="totalRows"
     :per-page="perPage"
     size="sm"
   >
@@ -216,14 +212,15 @@ Vue.component('layouts-table', {
     props: {
         url: { type: String, default: null },
         bestInFamily: { type: Boolean, default: true },
-        filter: { type: String, default: null },
+        query: { type: String, default: "" },
         perPage: { type: Number, default: 500 },
     },
     data () {
         return {
             layouts: [],
             currentPage: 1,
-            filteredRows: 0,
+            totalRows: 0,
+            error: null,
         }
 
     },
@@ -260,12 +257,10 @@ Vue.component('layouts-table', {
                 {
                     key: 'published_by',
                     label: 'Published by',
-                    sortable: true
                 },
                 {
                     key: 'total_cost',
                     label: 'Cost',
-                    sortable: true,
                     formatter: (c) => c.toFixed(2)
                 },
                 {
@@ -275,17 +270,14 @@ Vue.component('layouts-table', {
                 {
                     key: 'family',
                     label: 'Family',
-                    sortable: true
                 },
                 {
                     key: 'periodComma',
                     label: 'Period/Comma',
-                    sortable: true,
                 },
                 {
                     key: 'highlight',
                     label: 'Well-known',
-                    sortable: true
                 }
             ]
         },
@@ -297,16 +289,40 @@ Vue.component('layouts-table', {
         bestInFamily () {
             this.rows.forEach(item => item.selected = false)
             this.$emit('details', [])
+            this.currentPage = 1
+            this.fetchLayouts()
+        },
+        query () {
+            this.currentPage = 1
+            this.fetchLayouts()
+        },
+        currentPage () {
+            // the server provides the pages, unless all layouts are needed to find the families' best
+            if (!this.bestInFamily) this.fetchLayouts()
         },
     },
     methods: {
         fetchLayouts () {
             if (this.url === null) return null
-            return fetch(this.url)
-                .then(response => response.json())
-                .then(data => {
+            let query = this.query
+            if (!this.bestInFamily) {
+                query += `&limit=${this.perPage}&offset=${(this.currentPage - 1) * this.perPage}`
+            }
+            return fetch(`${this.url}/?${query}`)
+                .then(response => {
+                    if (!response.ok) throw new Error(`Could not fetch the layouts (${response.status} ${response.statusText})`)
+                    const total = parseInt(response.headers.get("X-Total-Count"))
+                    return response.json().then(data => ({ data, total }))
+                })
+                .then(({ data, total }) => {
+                    this.error = null
                     this.layouts = data
-                    this.filteredRows = data.length
+                    this.totalRows = this.bestInFamily ? this.rows.length : total
+                })
+                .catch(error => {
+                    this.error = error.message
+                    this.layouts = []
+                    this.totalRows = 0
                 })
         },
         rowClass (item, type) {
@@ -323,10 +339,6 @@ Vue.component('layouts-table', {
             let selection = this.rows.filter(item => item.selected).sort((a, b) => a.total_cost - b.total_cost)
             this.$emit("details", selection)
         },
-        onFiltered (items) {
-            this.filteredRows = items.length
-            this.currentPage = 1
-        },
     }
 })
 
@@ -0,0 +1,257 @@
+//! The `api` module contains the JSON bodies of the requests to and responses of the layouts
+//! webservice. The webservice uses the same types, so that the client can not drift apart from it.
+
+use layout_evaluation::results::EvaluationResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// An evaluated layout as returned by the webservice.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LayoutEvaluation {
+    /// Name of the profile the layout was evaluated with
+    pub profile: String,
+    pub layout: String,
+    pub total_cost: f64,
+    pub published_by: Option<String>,
+    /// Results of the individual metrics (only for single layouts)
+    pub details: Option<EvaluationResult>,
+    /// Printed evaluation (only for single layouts)
+    pub printed: Option<String>,
+    /// Plot of the layout (only for single layouts)
+    pub plot: Option<String>,
+    pub highlight: bool,
+    /// Version of the evaluation configuration the result was produced with
+    pub config_version: Option<i32>,
+    /// How the layout was found (e.g. a `PublishProvenance` or the optimization job)
+    pub provenance: Option<Value>,
+}
+
+/// A layout to publish.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PostLayout {
+    pub layout: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_by: Option<String>,
+    /// Highlight the layout (admins only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<bool>,
+    /// Admin secret (alternatively to an admin token)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// How the layout was found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<PublishProvenance>,
+}
+
+/// How a published layout was found, so that it can be reproduced.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PublishProvenance {
+    /// Name of the optimizer, e.g. "genevo", "sa", or "abc"
+    pub optimizer: Option<String>,
+    /// Parameters of the optimizer
+    pub parameters: Option<Value>,
+    /// Number of iterations (or generations) the optimization was run for
+    pub iterations: Option<u64>,
+    /// Runtime of the optimization in seconds
+    pub runtime: Option<f64>,
+    /// Hash of the evaluation configuration the layout was optimized with (see `EvalConfig`)
+    pub config_hash: Option<String>,
+}
+
+/// A version of the webservice's evaluation configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EvalConfig {
+    pub id: i32,
+    /// SHA-256 hash (hex) of all configuration files and ngram files
+    pub hash: String,
+    pub eval_parameters: String,
+    pub layout_config: String,
+    /// Ngram files with the hashes of their contents (one per line)
+    pub ngrams: String,
+}
+
+/// Order of listed layouts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "form", derive(rocket::FromFormField))]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutOrder {
+    /// By total cost
+    #[default]
+    Cost,
+    /// By the date the layout was stored
+    Date,
+}
+
+/// Direction of the order of listed layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "form", derive(rocket::FromFormField))]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Query parameters of the layout listings.
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "form", derive(rocket::FromForm))]
+pub struct ListQuery {
+    /// Maximal number of layouts to return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Number of layouts to skip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    /// Order of the layouts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<LayoutOrder>,
+    /// Direction of the order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortDirection>,
+    /// Only layouts whose publisher contains this (ignoring case)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_by: Option<String>,
+    /// Only (not) highlighted layouts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<bool>,
+    /// Minimal total cost
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_cost: Option<f64>,
+    /// Maximal total cost
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+    /// Pattern the layout has to contain ("_" matches any key, "%" any number of keys)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Minimal weighted costs of individual metrics (e.g. `min_metric[Finger Balance]=0.5`)
+    #[serde(skip)]
+    pub min_metric: BTreeMap<String, f64>,
+    /// Maximal weighted costs of individual metrics
+    #[serde(skip)]
+    pub max_metric: BTreeMap<String, f64>,
+}
+
+impl ListQuery {
+    /// The filters on individual metrics as query parameters (they can not be serialized as
+    /// nested query parameters by serde).
+    pub(crate) fn metric_parameters(&self) -> Vec<(String, f64)> {
+        let min = self
+            .min_metric
+            .iter()
+            .map(|(name, cost)| (format!("min_metric[{}]", name), *cost));
+        let max = self
+            .max_metric
+            .iter()
+            .map(|(name, cost)| (format!("max_metric[{}]", name), *cost));
+
+        min.chain(max).collect()
+    }
+}
+
+/// A page of listed layouts with the total number of layouts matching the query.
+#[derive(Debug, Clone)]
+pub struct LayoutPage {
+    pub layouts: Vec<LayoutEvaluation>,
+    pub total: u64,
+}
+
+/// A stored layout as exported by the webservice (one per line of a JSONL file) and imported
+/// into another instance.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LayoutRecord {
+    /// Name of the profile (the default profile if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub layout: String,
+    #[serde(default)]
+    pub published_by: Option<String>,
+    #[serde(default)]
+    pub highlight: bool,
+    /// Date the layout was stored (as given by the database)
+    #[serde(default)]
+    pub created: Option<String>,
+    /// How the layout was found
+    #[serde(default)]
+    pub provenance: Option<Value>,
+    /// The layout's current result (the layout is evaluated on import if omitted)
+    #[serde(default)]
+    pub result: Option<LayoutResult>,
+}
+
+/// An evaluation result of an exported layout.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LayoutResult {
+    pub total_cost: f64,
+    pub details: EvaluationResult,
+    pub printed: String,
+    /// Hash of the evaluation configuration the result was produced with (see `EvalConfig`)
+    pub config_hash: Option<String>,
+}
+
+/// Outcome of an import of layouts.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ImportSummary {
+    /// Number of stored layouts
+    pub imported: usize,
+    /// Number of stored layouts that were evaluated (instead of keeping their exported result)
+    pub evaluated: usize,
+    /// Layouts (as "profile: layout") whose exported results were produced with another than
+    /// the current configuration of their profile, so that they were evaluated again
+    #[serde(default)]
+    pub foreign_results: Vec<String>,
+    /// Number of skipped layouts that were stored already (or occurred before in the import)
+    pub duplicates: usize,
+    /// Lines that could not be imported
+    pub errors: Vec<String>,
+}
+
+/// Status of a background job of the webservice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_active(&self) -> bool {
+        matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// Snapshot of a job's state as returned by the api.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: String,
+    pub profiles: Vec<String>,
+    /// Name of the user who submitted the job (optimization jobs only)
+    pub submitted_by: Option<String>,
+    pub status: JobStatus,
+    /// Number of processed items
+    pub done: usize,
+    /// Total number of items (known once the job is running)
+    pub total: usize,
+    pub error: Option<String>,
+    /// Best layout found so far (optimization jobs only)
+    pub best_layout: Option<String>,
+    pub best_cost: Option<f64>,
+    /// Outcome of the import once it finished (import jobs only)
+    #[serde(default)]
+    pub import_summary: Option<ImportSummary>,
+}
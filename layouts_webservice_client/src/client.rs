@@ -0,0 +1,227 @@
+//! The `client` module provides a blocking client for the layouts webservice.
+
+use super::api::{
+    EvalConfig, ImportSummary, JobInfo, JobStatus, LayoutEvaluation, LayoutPage, ListQuery,
+    PostLayout,
+};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Interval of polling the state of a background job
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A client for the api of a layouts webservice, e.g. "https://keyboard-layout-optimizer.herokuapp.com/api".
+///
+/// Requests concerning layouts take the name of a profile (`None` for the service's default
+/// profile).
+#[derive(Clone, Debug)]
+pub struct Client {
+    base_url: Url,
+    token: Option<String>,
+    http: reqwest::blocking::Client,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Result<Self> {
+        let base_url =
+            Url::parse(base_url).with_context(|| format!("Invalid url '{}'", base_url))?;
+        if base_url.cannot_be_a_base() {
+            return Err(anyhow!("Invalid url '{}'", base_url));
+        }
+
+        Ok(Self {
+            base_url,
+            token: None,
+            http: reqwest::blocking::Client::new(),
+        })
+    }
+
+    /// Authenticate all requests with the given api token.
+    pub fn with_token(mut self, token: Option<&str>) -> Self {
+        self.token = token.map(|token| token.to_string());
+        self
+    }
+
+    /// The url of the given path segments below the base url (the segments are escaped).
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("checked in Client::new")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    /// The url of a route of a profile, e.g. `/api/<profile>/<layout>`.
+    fn profile_url(&self, profile: Option<&str>, segments: &[&str]) -> Url {
+        let mut all = Vec::new();
+        all.extend(profile);
+        all.extend_from_slice(segments);
+        self.url(&all)
+    }
+
+    /// Send the request (with the token) and fail if the response does not indicate success.
+    fn send(&self, request: RequestBuilder) -> Result<Response> {
+        check(self.execute(request)?)
+    }
+
+    fn execute(&self, request: RequestBuilder) -> Result<Response> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+
+        Ok(request.send()?)
+    }
+
+    /// Publish a layout. Returns its evaluation (if the layout was published before, the stored
+    /// one).
+    pub fn publish(&self, profile: Option<&str>, layout: &PostLayout) -> Result<LayoutEvaluation> {
+        // the trailing empty segment results in "/api/" or "/api/<profile>/"
+        let url = self.profile_url(profile, &[""]);
+        let evaluation = self.send(self.http.post(url).json(layout))?.json()?;
+
+        Ok(evaluation)
+    }
+
+    /// Fetch a layout with its details (`None` if it has not been published).
+    pub fn get(&self, profile: Option<&str>, layout: &str) -> Result<Option<LayoutEvaluation>> {
+        let url = self.profile_url(profile, &[layout]);
+        let response = self.execute(self.http.get(url))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let evaluation = check(response)?.json()?;
+
+        Ok(Some(evaluation))
+    }
+
+    /// List the layouts matching the query.
+    pub fn list(&self, profile: Option<&str>, query: &ListQuery) -> Result<LayoutPage> {
+        let url = self.profile_url(profile, &[""]);
+        let request = self.http.get(url).query(query);
+        let response = self.send(request.query(&query.metric_parameters()))?;
+        let total = response
+            .headers()
+            .get("X-Total-Count")
+            .and_then(|total| total.to_str().ok())
+            .and_then(|total| total.parse().ok())
+            .unwrap_or_default();
+        let layouts = response.json()?;
+
+        Ok(LayoutPage { layouts, total })
+    }
+
+    /// Fetch all versions of the evaluation configuration.
+    pub fn configs(&self) -> Result<Vec<EvalConfig>> {
+        let configs = self.send(self.http.get(self.url(&["configs"])))?.json()?;
+
+        Ok(configs)
+    }
+
+    /// Fetch the names of the metrics the layouts are evaluated with (e.g. for filtering a
+    /// listing by the costs of individual metrics).
+    pub fn metrics(&self, profile: Option<&str>) -> Result<Vec<String>> {
+        let url = self.profile_url(profile, &["metrics"]);
+        let names = self.send(self.http.get(url))?.json()?;
+
+        Ok(names)
+    }
+
+    /// Export the stored layouts (of the given profile or of all profiles) as JSONL (one
+    /// `LayoutRecord` per line). Requires an admin token.
+    pub fn export(&self, profile: Option<&str>) -> Result<String> {
+        let request = self
+            .http
+            .get(self.url(&["export"]))
+            .query(&[("profile", profile)]);
+        let jsonl = self.send(request)?.text()?;
+
+        Ok(jsonl)
+    }
+
+    /// Import layouts given as JSONL (one `LayoutRecord` per line). Layouts that are stored
+    /// already are skipped. With `reevaluate`, all layouts are evaluated with the service's current
+    /// configuration instead of keeping their exported results. Requires an admin token.
+    ///
+    /// The service imports the layouts in a background job, this waits until it is done.
+    pub fn import(&self, jsonl: String, reevaluate: bool) -> Result<ImportSummary> {
+        let request = self
+            .http
+            .post(self.url(&["import"]))
+            .query(&[("reevaluate", reevaluate)])
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(jsonl);
+        let job: JobInfo = self.send(request)?.json()?;
+
+        let id = job.id;
+        let job = self.wait_for_job(id)?;
+        match job.status {
+            JobStatus::Finished => job
+                .import_summary
+                .ok_or_else(|| anyhow!("Import job {} finished without summary", id)),
+            status => Err(anyhow!(
+                "Import job {} is {:?}: {}",
+                id,
+                status,
+                job.error.unwrap_or_default()
+            )),
+        }
+    }
+
+    /// Fetch the state of a background job (`None` if it is unknown, e.g. because it completed a
+    /// while ago).
+    pub fn job(&self, id: u64) -> Result<Option<JobInfo>> {
+        let url = self.url(&["jobs", &id.to_string()]);
+        let response = self.execute(self.http.get(url))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let job = check(response)?.json()?;
+
+        Ok(Some(job))
+    }
+
+    /// Poll the state of a background job until it finished, failed, or was cancelled.
+    pub fn wait_for_job(&self, id: u64) -> Result<JobInfo> {
+        loop {
+            let job = self
+                .job(id)?
+                .ok_or_else(|| anyhow!("Job {} is unknown", id))?;
+            if !job.status.is_active() {
+                return Ok(job);
+            }
+            std::thread::sleep(JOB_POLL_INTERVAL);
+        }
+    }
+
+    /// Fetch the OpenAPI document of the webservice.
+    pub fn openapi(&self) -> Result<Value> {
+        let document = self
+            .send(self.http.get(self.url(&["openapi.json"])))?
+            .json()?;
+
+        Ok(document)
+    }
+}
+
+/// Fail with the response's status and body if it does not indicate success.
+fn check(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let url = response.url().clone();
+    let text = response.text().unwrap_or_default();
+    Err(anyhow!(
+        "Request to {} failed with {}: {}",
+        url,
+        status,
+        text
+    ))
+}
@@ -0,0 +1,75 @@
+//! The `config_hash` module identifies the configuration an evaluation was produced with. The
+//! evaluation parameters, the keyboard configuration, and the ngram files are hashed together, so
+//! any change to them results in a new hash. The webservice versions its configurations by this
+//! hash and the optimizers publish it with their layouts, so both have to hash the same way.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// The files an evaluation depends on.
+#[derive(Clone, Debug)]
+pub struct ConfigFiles<'a> {
+    pub eval_parameters: &'a str,
+    pub layout_config: &'a str,
+    pub unigrams: &'a str,
+    pub bigrams: &'a str,
+    pub trigrams: &'a str,
+}
+
+/// The contents of the configuration files together with their hash.
+#[derive(Clone, Debug)]
+pub struct HashedConfig {
+    /// SHA-256 hash (hex) of all configuration files and ngram files
+    pub hash: String,
+    /// Content of the evaluation parameters file
+    pub eval_parameters: String,
+    /// Content of the layout configuration (keyboard and base layout) file
+    pub layout_config: String,
+    /// SHA-256 hashes (hex) of the unigram, bigram, and trigram files' contents
+    pub ngram_hashes: [String; 3],
+}
+
+/// SHA-256 hash (hex) of some data.
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Read a (configuration) file.
+pub fn read(filename: &str) -> Result<Vec<u8>> {
+    std::fs::read(filename).with_context(|| format!("Could not read file '{}'", filename))
+}
+
+impl ConfigFiles<'_> {
+    /// Read and hash the files. Options deviating from the files (e.g. a corpus instead of the
+    /// ngram files) are described by `overrides` and hashed as well (if there are any).
+    pub fn hash(&self, overrides: &[String]) -> Result<HashedConfig> {
+        let eval_parameters = String::from_utf8(read(self.eval_parameters)?)?;
+        let layout_config = String::from_utf8(read(self.layout_config)?)?;
+        let ngram_hashes = [
+            sha256_hex(&read(self.unigrams)?),
+            sha256_hex(&read(self.bigrams)?),
+            sha256_hex(&read(self.trigrams)?),
+        ];
+        // the ngram files' names do not matter, only their contents
+        let joined_ngram_hashes = ngram_hashes.join("\n");
+        let overrides = overrides.join("\n");
+
+        let mut hasher = Sha256::new();
+        for part in [&eval_parameters, &layout_config, &joined_ngram_hashes] {
+            // length prefix, so that no two different configurations share the input
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        if !overrides.is_empty() {
+            hasher.update((overrides.len() as u64).to_le_bytes());
+            hasher.update(overrides.as_bytes());
+        }
+
+        Ok(HashedConfig {
+            hash: format!("{:x}", hasher.finalize()),
+            eval_parameters,
+            layout_config,
+            ngram_hashes,
+        })
+    }
+}
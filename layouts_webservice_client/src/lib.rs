@@ -0,0 +1,7 @@
+//! A typed client for the api of the layouts webservice (`layouts_webservice`).
+
+pub mod api;
+pub mod client;
+pub mod config_hash;
+
+pub use client::Client;
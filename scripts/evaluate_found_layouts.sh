@@ -7,4 +7,4 @@ if [ -z "$1" ]
 fi
 
 
-xargs -a "$1" env RUST_LOG=info cargo run --release --bin evaluate
+RUST_LOG=info cargo run --release --bin evaluate -- --from-file "$1" "${@:2}"
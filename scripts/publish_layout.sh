@@ -20,5 +20,4 @@ else
 fi
 
 
-echo "Publishing $1 to $URL"
-curl -X 'POST' -d "{\"layout\": \"$1\", \"published_by\": \"$2\"}" $URL
+cargo run --release --bin publish -- "$1" --publish-as "$2" --publish-to "$URL"